@@ -0,0 +1,133 @@
+// Stellar - Archive Module
+// @musem23
+//
+// Opt-in finishing step after organizing (--archive): each top-level
+// destination folder is compressed into <folder>.tar.gz next to it, and can
+// be removed afterwards. Archives are recorded in history so undo unpacks them.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+const ARCHIVE_EXTENSION: &str = "tar.gz";
+
+/// A folder compressed after a run; `removed` when the folder was deleted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchivedFolder {
+    pub folder: String,
+    pub archive: String,
+    pub removed: bool,
+}
+
+#[derive(Default)]
+pub struct ArchiveResult {
+    pub archived: Vec<ArchivedFolder>,
+    pub errors: Vec<String>,
+}
+
+/// Distinct first components of the destination folders (Documents/2024 -> Documents)
+pub fn top_level_folders(files_map: &HashMap<String, Vec<PathBuf>>) -> Vec<String> {
+    files_map
+        .keys()
+        .filter_map(|key| match Path::new(key).components().next() {
+            Some(Component::Normal(name)) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Compress each of `folders` (relative to `dest_root`) into an archive next
+/// to it, then delete the folder when `remove` is set.
+/// An existing archive is never overwritten: the new one gets a -1, -2... suffix.
+pub fn archive_folders(dest_root: &Path, folders: &[String], remove: bool) -> ArchiveResult {
+    let mut result = ArchiveResult::default();
+
+    for name in folders {
+        let folder = dest_root.join(name);
+        if !folder.is_dir() {
+            continue;
+        }
+
+        let archive = unique_archive_path(dest_root, name);
+        if let Err(e) = create_archive(&folder, &archive) {
+            let _ = fs::remove_file(&archive);
+            result
+                .errors
+                .push(format!("Cannot archive {}: {}", folder.display(), e));
+            continue;
+        }
+
+        let removed = remove && fs::remove_dir_all(&folder).is_ok();
+        if remove && !removed {
+            result.errors.push(format!(
+                "Archived {} but could not remove the folder",
+                folder.display()
+            ));
+        }
+
+        result.archived.push(ArchivedFolder {
+            folder: folder.to_string_lossy().to_string(),
+            archive: archive.to_string_lossy().to_string(),
+            removed,
+        });
+    }
+
+    result
+}
+
+/// Undo an archive step: unpack the folder if it was removed, then delete the archive
+pub fn restore_archived_folder(record: &ArchivedFolder) -> Result<(), String> {
+    let archive = Path::new(&record.archive);
+    if !archive.exists() {
+        return Err(format!("Archive not found: {}", record.archive));
+    }
+
+    if record.removed {
+        let parent = Path::new(&record.folder)
+            .parent()
+            .ok_or_else(|| format!("Invalid folder: {}", record.folder))?;
+        extract_archive(archive, parent)
+            .map_err(|e| format!("Cannot unpack {}: {}", record.archive, e))?;
+    }
+
+    fs::remove_file(archive).map_err(|e| format!("Cannot remove {}: {}", record.archive, e))
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+/// The archive holds the folder itself, so unpacking into the parent recreates it
+fn create_archive(folder: &Path, archive: &Path) -> io::Result<()> {
+    let name = folder
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "folder has no name"))?;
+
+    let encoder = GzEncoder::new(File::create(archive)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(name, folder)?;
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+fn extract_archive(archive: &Path, parent: &Path) -> io::Result<()> {
+    let decoder = GzDecoder::new(File::open(archive)?);
+    tar::Archive::new(decoder).unpack(parent)
+}
+
+fn unique_archive_path(dest_root: &Path, name: &str) -> PathBuf {
+    let mut path = dest_root.join(format!("{}.{}", name, ARCHIVE_EXTENSION));
+    let mut counter = 1;
+    while path.exists() {
+        path = dest_root.join(format!("{}-{}.{}", name, counter, ARCHIVE_EXTENSION));
+        counter += 1;
+    }
+    path
+}
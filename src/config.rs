@@ -3,23 +3,120 @@
 //
 // Handles loading, saving, and providing default configuration.
 // Config is stored in TOML format at ~/.config/stellar/stellar.toml
-// or locally in ./stellar.toml (takes precedence).
+// or locally in ./stellar.toml (takes precedence). `--config <path>` replaces
+// both: that file is read and saved, and it must exist.
 // Default config is embedded from stellar.toml at compile time.
 
+use chrono::TimeDelta;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::{env, fs};
 
+use crate::modes::{ConflictSuffix, DateGranularity, DateSource, UnmatchedPolicy};
+use crate::stats;
+
 /// Default configuration embedded at compile time
 const DEFAULT_CONFIG: &str = include_str!("../stellar.toml");
 
+/// Config file given with --config, used instead of the usual locations
+static CONFIG_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
 #[derive(Deserialize, Serialize)]
 pub struct Config {
     pub protected: Protected,
     pub categories: HashMap<String, Vec<String>>,
+    /// Extension → folder, checked before the categories (torrent = "Torrents")
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
     #[serde(default)]
     pub preferences: Preferences,
+    #[serde(default)]
+    pub rename: RenameConfig,
+    /// Last settings used per folder, keyed by canonical path
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub folder_preferences: HashMap<String, FolderPreference>,
+    /// Checked top to bottom before the categories; the first matching rule wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub hashing: HashingConfig,
+    #[serde(default)]
+    pub date: DateConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    #[serde(default)]
+    pub vault: VaultConfig,
+}
+
+/// One `[[rules]]` entry: a file matches when every condition that is set matches
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct Rule {
+    /// Destination folder, relative to the organized folder
+    pub folder: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Glob on the file name, case-insensitive ("invoice-*.pdf")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Sizes like "500KB" or "1GB"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<String>,
+    /// Ages like "12h", "30d" or "2w"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_than: Option<String>,
+}
+
+/// A rule with its patterns, sizes and ages parsed once
+pub struct CompiledRule {
+    pub folder: String,
+    pub extensions: Vec<String>,
+    pub name: Option<glob::Pattern>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub older_than: Option<TimeDelta>,
+    pub newer_than: Option<TimeDelta>,
+}
+
+impl Rule {
+    pub fn compile(&self) -> Result<CompiledRule, String> {
+        let size = |s: &Option<String>| s.as_deref().map(stats::parse_size).transpose();
+        let age = |s: &Option<String>| s.as_deref().map(stats::parse_age).transpose();
+
+        Ok(CompiledRule {
+            folder: self.folder.clone(),
+            extensions: self
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            name: self
+                .name
+                .as_deref()
+                .map(glob::Pattern::new)
+                .transpose()
+                .map_err(|e| format!("invalid name pattern: {}", e))?,
+            min_size: size(&self.min_size)?,
+            max_size: size(&self.max_size)?,
+            older_than: age(&self.older_than)?,
+            newer_than: age(&self.newer_than)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+pub struct FolderPreference {
+    pub organization_mode: usize,
+    pub rename_mode: usize,
+    #[serde(default)]
+    pub recursive: bool,
 }
 
 #[derive(Deserialize, Serialize, Default, Clone)]
@@ -28,6 +125,232 @@ pub struct Preferences {
     pub organization_mode: usize,
     #[serde(default)]
     pub rename_mode: usize,
+    #[serde(default)]
+    pub date_source: DateSource,
+    /// Unset keeps each mode's default (date: month, hybrid: year)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_granularity: Option<DateGranularity>,
+    /// Folder for files without an extension (README, LICENSE...); unset means "Others"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_extension_folder: Option<String>,
+    /// Category folder for subfolders moved with --with-folders; unset means "Folders"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folders_category: Option<String>,
+    /// Files matching no category: others, leave, by-extension or a folder name
+    #[serde(default)]
+    pub unmatched_policy: UnmatchedPolicy,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RenameConfig {
+    /// Copy words stripped from the end of names (photo-copy → photo)
+    #[serde(default = "default_strip_suffixes")]
+    pub strip_suffixes: Vec<String>,
+    /// Also strip a bare -1..-9 counter (off by default: blade-runner-2 keeps its 2)
+    #[serde(default)]
+    pub strip_numeric_suffixes: bool,
+    /// Suffix given to a file whose name is taken at the destination
+    #[serde(default)]
+    pub conflict_suffix: ConflictSuffix,
+}
+
+impl Default for RenameConfig {
+    fn default() -> Self {
+        Self {
+            strip_suffixes: default_strip_suffixes(),
+            strip_numeric_suffixes: false,
+            conflict_suffix: ConflictSuffix::default(),
+        }
+    }
+}
+
+fn default_strip_suffixes() -> Vec<String> {
+    vec!["copy".to_string(), "copie".to_string()]
+}
+
+/// How files are read when hashing (duplicates, --dedupe-on-move, --verify-moves)
+#[derive(Deserialize, Serialize, Clone)]
+pub struct HashingConfig {
+    /// Read buffer, like "64KB" or "1MB"
+    #[serde(default = "default_hash_buffer_size")]
+    pub buffer_size: String,
+    /// Memory-map large files instead of reading them in chunks
+    #[serde(default = "default_hash_mmap")]
+    pub mmap: bool,
+}
+
+impl Default for HashingConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: default_hash_buffer_size(),
+            mmap: default_hash_mmap(),
+        }
+    }
+}
+
+impl HashingConfig {
+    /// Buffer size in bytes
+    pub fn buffer_bytes(&self) -> Result<usize, String> {
+        match stats::parse_size(&self.buffer_size)? {
+            0 => Err("hashing.buffer_size must be greater than 0".to_string()),
+            n => Ok(n as usize),
+        }
+    }
+}
+
+fn default_hash_buffer_size() -> String {
+    "1MB".to_string()
+}
+
+fn default_hash_mmap() -> bool {
+    true
+}
+
+/// Month folder names for date and hybrid organization
+#[derive(Deserialize, Serialize, Clone)]
+pub struct DateConfig {
+    /// January first; each folder gets its number in front (01-january)
+    #[serde(default = "default_month_names")]
+    pub month_names: Vec<String>,
+}
+
+impl Default for DateConfig {
+    fn default() -> Self {
+        Self {
+            month_names: default_month_names(),
+        }
+    }
+}
+
+impl DateConfig {
+    /// Why the configured names cannot be used as month folders
+    pub fn check(&self) -> Result<(), String> {
+        if self.month_names.len() != 12 {
+            return Err(format!(
+                "date.month_names needs 12 names, found {}",
+                self.month_names.len()
+            ));
+        }
+
+        match self
+            .month_names
+            .iter()
+            .find(|name| name.trim().is_empty() || name.contains(['/', '\\']))
+        {
+            Some(name) => Err(format!("date.month_names: invalid folder name '{}'", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// The configured names, or the English defaults when they fail `check`
+    pub fn months(&self) -> Vec<String> {
+        match self.check() {
+            Ok(()) => self.month_names.clone(),
+            Err(_) => default_month_names(),
+        }
+    }
+}
+
+/// How much undo history is kept
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct HistoryConfig {
+    /// Most operations kept; unset keeps the backend default
+    /// (50 for history.json, unlimited for SQLite), 0 removes the cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_operations: Option<usize>,
+    /// Operations older than this are dropped, like "90d"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+}
+
+impl HistoryConfig {
+    /// Why max_age cannot be used
+    pub fn check(&self) -> Result<(), String> {
+        match &self.max_age {
+            Some(age) => stats::parse_age(age)
+                .map(|_| ())
+                .map_err(|e| format!("history.max_age: {}", e)),
+            None => Ok(()),
+        }
+    }
+
+    /// The parsed max_age, None when unset or invalid
+    pub fn max_age(&self) -> Option<TimeDelta> {
+        self.max_age
+            .as_deref()
+            .and_then(|age| stats::parse_age(age).ok())
+    }
+}
+
+/// Watch mode settings
+#[derive(Deserialize, Serialize, Clone)]
+pub struct WatchConfig {
+    /// Extensions of files still being downloaded; they are left alone
+    /// until renamed to their final name
+    #[serde(default = "default_watch_ignore_extensions")]
+    pub ignore_extensions: Vec<String>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            ignore_extensions: default_watch_ignore_extensions(),
+        }
+    }
+}
+
+/// Vault settings
+#[derive(Deserialize, Serialize, Clone)]
+pub struct VaultConfig {
+    /// Largest folder `vault add` stores as one archive without --allow-large;
+    /// the archive is built in memory
+    #[serde(default = "default_vault_max_folder_size")]
+    pub max_folder_size: String,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            max_folder_size: default_vault_max_folder_size(),
+        }
+    }
+}
+
+impl VaultConfig {
+    /// The folder size limit in bytes
+    pub fn max_folder_bytes(&self) -> Result<u64, String> {
+        stats::parse_size(&self.max_folder_size)
+            .map_err(|e| format!("vault.max_folder_size: {}", e))
+    }
+}
+
+fn default_vault_max_folder_size() -> String {
+    "500MB".to_string()
+}
+
+fn default_watch_ignore_extensions() -> Vec<String> {
+    ["part", "crdownload", "download", "tmp"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_month_names() -> Vec<String> {
+    [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ]
+    .map(String::from)
+    .to_vec()
 }
 
 #[derive(Deserialize, Serialize)]
@@ -35,50 +358,323 @@ pub struct Protected {
     pub system: Vec<String>,
     pub user: Vec<String>,
     pub dev: Vec<String>,
+    /// Extra project markers, merged with the built-in ones (.git, Cargo.toml...)
+    #[serde(default)]
+    pub project_indicators: Vec<String>,
 }
 
-/// Load config from local file, user config, or embedded default
-pub fn load_config() -> Result<Config, String> {
-    let paths = [
-        Some(PathBuf::from("./stellar.toml")),
-        Some(get_user_config_path()),
-    ];
+/// Parse the active config without warnings.
+/// A config file that exists but does not parse is an error, not a silent fallback.
+pub fn read_config() -> Result<Config, String> {
+    match active_config_path() {
+        Some(path) => {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            let config: Config = toml::from_str(&content)
+                .map_err(|e| format!("Invalid config {}:\n{}", path.display(), e))?;
+            compile_rules(&config.rules)
+                .and(config.hashing.buffer_bytes())
+                .and(config.vault.max_folder_bytes())
+                .map_err(|e| format!("Invalid config {}:\n{}", path.display(), e))?;
+            Ok(config)
+        }
+        None => toml::from_str(DEFAULT_CONFIG)
+            .map_err(|e| format!("Failed to parse default config: {}", e)),
+    }
+}
 
-    for path in paths.into_iter().flatten() {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(config) = toml::from_str(&content) {
-                return Ok(config);
+/// Compile every rule, naming the first invalid one
+pub fn compile_rules(rules: &[Rule]) -> Result<Vec<CompiledRule>, String> {
+    rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            rule.compile()
+                .map_err(|e| format!("rule {} ({}): {}", i + 1, rule.folder, e))
+        })
+        .collect()
+}
+
+/// Read and save the config at `path` only, from now on (--config)
+pub fn set_config_path(path: PathBuf) {
+    if let Ok(mut current) = CONFIG_OVERRIDE.write() {
+        *current = Some(path);
+    }
+}
+
+/// Config file in use: the --config file, else ./stellar.toml, then the user
+/// config (None = embedded default). The --config file is returned even if
+/// it is missing, so that reading it fails instead of falling back.
+pub fn active_config_path() -> Option<PathBuf> {
+    if let Some(path) = config_override() {
+        return Some(path);
+    }
+    [PathBuf::from("./stellar.toml"), get_user_config_path()]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Where settings are saved: back to the file they were read from (see
+/// `active_config_path`), else the user config
+pub fn save_path() -> PathBuf {
+    active_config_path().unwrap_or_else(get_user_config_path)
+}
+
+/// Extensions listed in more than one category, with those categories (sorted)
+pub fn duplicate_extensions(
+    categories: &HashMap<String, Vec<String>>,
+) -> Vec<(String, Vec<String>)> {
+    let mut owners: HashMap<String, Vec<String>> = HashMap::new();
+    for (category, exts) in categories {
+        for ext in exts {
+            let list = owners.entry(ext.to_lowercase()).or_default();
+            if !list.contains(category) {
+                list.push(category.clone());
             }
         }
     }
 
-    toml::from_str(DEFAULT_CONFIG).map_err(|e| format!("Failed to parse default config: {}", e))
+    let mut duplicates: Vec<(String, Vec<String>)> = owners
+        .into_iter()
+        .filter(|(_, cats)| cats.len() > 1)
+        .map(|(ext, mut cats)| {
+            cats.sort();
+            (ext, cats)
+        })
+        .collect();
+    duplicates.sort();
+    duplicates
 }
 
-/// Save config to user config directory
+/// Extensions named by more than one override key (`torrent`, `.torrent`...),
+/// with those keys and their folders sorted by key, the first one being used
+pub fn duplicate_overrides(
+    overrides: &HashMap<String, String>,
+) -> Vec<(String, Vec<(String, String)>)> {
+    let mut owners: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for (key, folder) in overrides {
+        let ext = key.trim_start_matches('.').to_lowercase();
+        owners
+            .entry(ext)
+            .or_default()
+            .push((key.clone(), folder.clone()));
+    }
+
+    let mut duplicates: Vec<(String, Vec<(String, String)>)> = owners
+        .into_iter()
+        .filter(|(_, keys)| keys.len() > 1)
+        .map(|(ext, mut keys)| {
+            keys.sort();
+            (ext, keys)
+        })
+        .collect();
+    duplicates.sort();
+    duplicates
+}
+
+/// Save config to the file it was read from (`save_path`)
 pub fn save_config(config: &Config) -> Result<(), String> {
-    let path = get_user_config_path();
+    let toml_str =
+        toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    write_config_file(&save_path(), &toml_str)
+}
+
+/// Remember the settings last used in `folder` (a canonical path) in the
+/// config file in use, leaving the rest of the file as it is
+pub fn save_folder_preference(folder: &str, pref: &FolderPreference) -> Result<(), String> {
+    save_folder_preference_in(&save_path(), folder, pref)
+}
 
+/// Same as `save_folder_preference`, in the config file at `path`
+pub fn save_folder_preference_in(
+    path: &Path,
+    folder: &str,
+    pref: &FolderPreference,
+) -> Result<(), String> {
+    update_config_file(path, |doc| {
+        let mut entry = toml_edit::Table::new();
+        entry["organization_mode"] = toml_edit::value(pref.organization_mode as i64);
+        entry["rename_mode"] = toml_edit::value(pref.rename_mode as i64);
+        entry["recursive"] = toml_edit::value(pref.recursive);
+
+        let mut implicit = toml_edit::Table::new();
+        implicit.set_implicit(true);
+        doc.entry("folder_preferences")
+            .or_insert(toml_edit::Item::Table(implicit))
+            .as_table_like_mut()
+            .ok_or("folder_preferences is not a table")?
+            .insert(folder, toml_edit::Item::Table(entry));
+        Ok(())
+    })
+}
+
+/// Write the categories named in `names` (or drop those no longer in
+/// `categories`) to the config file in use, leaving the rest of the file as it is
+pub fn save_categories(
+    categories: &HashMap<String, Vec<String>>,
+    names: &[String],
+) -> Result<(), String> {
+    save_categories_in(&save_path(), categories, names)
+}
+
+/// Same as `save_categories`, in the config file at `path`
+pub fn save_categories_in(
+    path: &Path,
+    categories: &HashMap<String, Vec<String>>,
+    names: &[String],
+) -> Result<(), String> {
+    update_config_file(path, |doc| {
+        let table = doc
+            .entry("categories")
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .ok_or("categories is not a table")?;
+        for name in names {
+            match categories.get(name) {
+                Some(extensions) => {
+                    let extensions: toml_edit::Array = extensions.iter().collect();
+                    table.insert(name, toml_edit::value(extensions));
+                }
+                None => {
+                    table.remove(name);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Write the default config (with its comments) to the user config path
+/// (or the --config file), or ./stellar.toml when `local` is set.
+/// Returns the path written.
+pub fn init_config(local: bool, force: bool) -> Result<PathBuf, String> {
+    let path = if local {
+        PathBuf::from("./stellar.toml")
+    } else {
+        config_override().unwrap_or_else(get_user_config_path)
+    };
+
+    if path.exists() && !force {
+        return Err(format!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        ));
+    }
+
+    write_config_file(&path, DEFAULT_CONFIG)?;
+    Ok(path)
+}
+
+/// Find which category an extension belongs to, ignoring case on both sides.
+/// If several categories list it, the first one alphabetically wins.
+pub fn find_category(categories: &HashMap<String, Vec<String>>, ext: &str) -> Option<String> {
+    categories
+        .iter()
+        .filter(|(_, exts)| exts.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        .map(|(name, _)| name)
+        .min()
+        .cloned()
+}
+
+/// Split "pdf, .DOCX,xlsx" into extensions (lowercase, without the dot)
+pub fn parse_extensions(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|s| s.trim().to_lowercase().trim_start_matches('.').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Add extensions to a category, creating it if needed (an existing category
+/// is matched ignoring case). Returns the extensions that were not there yet.
+pub fn add_to_category(
+    categories: &mut HashMap<String, Vec<String>>,
+    name: &str,
+    extensions: &[String],
+) -> Vec<String> {
+    let key = category_key(categories, name).unwrap_or_else(|| name.to_string());
+    let exts = categories.entry(key).or_default();
+
+    let mut added = Vec::new();
+    for ext in extensions {
+        if !exts.iter().any(|e| e.eq_ignore_ascii_case(ext)) {
+            exts.push(ext.clone());
+            added.push(ext.clone());
+        }
+    }
+    added
+}
+
+/// Remove a category (matched ignoring case), returning its name and extensions
+pub fn remove_category(
+    categories: &mut HashMap<String, Vec<String>>,
+    name: &str,
+) -> Result<(String, Vec<String>), String> {
+    let key =
+        category_key(categories, name).ok_or_else(|| format!("No category named '{}'", name))?;
+    let exts = categories.remove(&key).unwrap_or_default();
+    Ok((key, exts))
+}
+
+/// Folder for an extension: an override wins, then the category, then "Others".
+/// When several override keys name the extension, the first key in sorted order is used.
+pub fn resolve_folder(
+    categories: &HashMap<String, Vec<String>>,
+    overrides: &HashMap<String, String>,
+    ext: &str,
+) -> String {
+    overrides
+        .iter()
+        .filter(|(key, _)| key.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        .min_by_key(|(key, _)| key.as_str())
+        .map(|(_, folder)| folder.clone())
+        .or_else(|| find_category(categories, ext))
+        .unwrap_or_else(|| "Others".into())
+}
+
+fn write_config_file(path: &Path, contents: &str) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create config directory: {}", e))?;
     }
 
-    let toml_str =
-        toml::to_string_pretty(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+    fs::write(path, contents).map_err(|e| format!("Failed to write config: {}", e))
+}
 
-    fs::write(&path, toml_str).map_err(|e| format!("Failed to write config: {}", e))?;
+/// Edit the config file at `path` in place: comments, layout and the settings
+/// `edit` does not touch stay as written. A missing file starts from the
+/// default config, so the result still has every section.
+fn update_config_file(
+    path: &Path,
+    edit: impl FnOnce(&mut toml_edit::DocumentMut) -> Result<(), &'static str>,
+) -> Result<(), String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => DEFAULT_CONFIG.to_string(),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Invalid config {}:\n{}", path.display(), e))?;
 
-    Ok(())
+    edit(&mut doc).map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+    write_config_file(path, &doc.to_string())
 }
 
-/// Find which category an extension belongs to
-pub fn find_category(categories: &HashMap<String, Vec<String>>, ext: &str) -> Option<String> {
-    let ext_lower = ext.to_lowercase();
+/// The category spelled `name`, or failing that the one matching it ignoring case
+fn category_key(categories: &HashMap<String, Vec<String>>, name: &str) -> Option<String> {
+    if categories.contains_key(name) {
+        return Some(name.to_string());
+    }
     categories
-        .iter()
-        .find(|(_, exts)| exts.contains(&ext_lower))
-        .map(|(name, _)| name.clone())
+        .keys()
+        .find(|k| k.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+fn config_override() -> Option<PathBuf> {
+    CONFIG_OVERRIDE.read().ok().and_then(|path| path.clone())
 }
 
 fn get_user_config_path() -> PathBuf {
@@ -3,30 +3,158 @@
 //
 // Finds duplicate files by computing SHA-256 hashes.
 // Groups files with identical content for user review or batch removal.
+// Removal is planned by a pure function so dry-runs show exactly what would go.
+// Near-duplicate images are found with a difference hash (dHash) instead.
+// Large files are memory-mapped for hashing, falling back to buffered reads.
 
+use memmap2::Mmap;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
-const BUFFER_SIZE: usize = 8192;
+use crate::config::HashingConfig;
+use crate::modes::{GroupOrder, KeepRule};
+use crate::progress::ProgressReporter;
+use crate::trash::TrashSession;
 
+/// Default read buffer when hashing (`[hashing] buffer_size`)
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Files at least this large are memory-mapped when `[hashing] mmap` is on
+const MMAP_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// How `hash_file` reads a file
+#[derive(Clone, Copy)]
+pub struct HashOptions {
+    pub buffer_size: usize,
+    pub mmap: bool,
+}
+
+impl Default for HashOptions {
+    fn default() -> Self {
+        Self {
+            buffer_size: BUFFER_SIZE,
+            mmap: true,
+        }
+    }
+}
+
+impl HashOptions {
+    /// An invalid buffer size (already reported by read_config) keeps the default
+    pub fn from_config(config: &HashingConfig) -> Self {
+        Self {
+            buffer_size: config.buffer_bytes().unwrap_or(BUFFER_SIZE),
+            mmap: config.mmap,
+        }
+    }
+}
+
+#[derive(Serialize)]
 pub struct DuplicateGroup {
     pub files: Vec<PathBuf>,
     pub size: u64,
 }
 
-/// Find duplicate files by comparing SHA-256 hashes
-pub fn find_duplicates(files: &[PathBuf]) -> Vec<DuplicateGroup> {
+impl DuplicateGroup {
+    /// Bytes taken by every copy beyond the first
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * self.files.len().saturating_sub(1) as u64
+    }
+}
+
+/// Machine-readable summary for `stellar duplicates --format json`
+#[derive(Serialize)]
+pub struct DuplicateReport<'a> {
+    pub groups: &'a [DuplicateGroup],
+    pub duplicate_files: usize,
+    pub wasted_bytes: u64,
+}
+
+impl<'a> DuplicateReport<'a> {
+    pub fn new(groups: &'a [DuplicateGroup]) -> Self {
+        let extra = |g: &DuplicateGroup| g.files.len().saturating_sub(1);
+        Self {
+            groups,
+            duplicate_files: groups.iter().map(extra).sum(),
+            wasted_bytes: groups.iter().map(DuplicateGroup::wasted_bytes).sum(),
+        }
+    }
+}
+
+/// Images that look alike, with the Hamming distance of every pair (i, j, distance)
+pub struct SimilarGroup {
+    pub files: Vec<PathBuf>,
+    pub distances: Vec<(usize, usize, u32)>,
+}
+
+/// Which copy of a group stays and which ones go
+pub struct RemovalPlan {
+    pub kept: PathBuf,
+    pub removed: Vec<PathBuf>,
+    pub bytes_freed: u64,
+}
+
+pub struct RemovalResult {
+    pub removed: usize,
+    pub bytes_freed: u64,
+    pub errors: Vec<String>,
+}
+
+/// Files inside a folder, optionally descending into non-hidden subfolders
+pub fn list_files(dir: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+    {
+        if path.is_file() {
+            files.push(path);
+        } else if recursive && path.is_dir() {
+            let hidden = path
+                .file_name()
+                .map(|n| n.to_string_lossy().starts_with('.'))
+                .unwrap_or(true);
+            if !hidden {
+                files.extend(list_files(&path, recursive));
+            }
+        }
+    }
+
+    files
+}
+
+/// Keep files of at least `min_size` bytes; empty files are always dropped
+/// since they are all trivially identical
+pub fn filter_min_size(files: Vec<PathBuf>, min_size: u64) -> Vec<PathBuf> {
+    let min_size = min_size.max(1);
+    files
+        .into_iter()
+        .filter(|p| p.metadata().map(|m| m.len() >= min_size).unwrap_or(false))
+        .collect()
+}
+
+/// Find duplicate files by comparing SHA-256 hashes; `progress` counts hashed files
+pub fn find_duplicates(
+    files: &[PathBuf],
+    options: &HashOptions,
+    progress: &dyn ProgressReporter,
+) -> Vec<DuplicateGroup> {
     let mut by_hash: HashMap<String, (Vec<PathBuf>, u64)> = HashMap::new();
 
     for path in files {
-        if let Ok((hash, size)) = hash_file(path) {
+        if let Ok((hash, size)) = hash_file(path, options) {
             let entry = by_hash.entry(hash).or_insert_with(|| (Vec::new(), size));
             entry.0.push(path.clone());
         }
+        progress.inc(1);
     }
+    progress.finish();
 
     by_hash
         .into_values()
@@ -35,30 +163,197 @@ pub fn find_duplicates(files: &[PathBuf]) -> Vec<DuplicateGroup> {
         .collect()
 }
 
-/// Format bytes as human-readable size
-pub fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
+/// Group images whose perceptual hashes differ by at most `threshold` bits (out of 64).
+/// Files that cannot be decoded are ignored; `progress` counts decoded files.
+pub fn find_similar_images(
+    files: &[PathBuf],
+    threshold: u32,
+    progress: &dyn ProgressReporter,
+) -> Vec<SimilarGroup> {
+    let hashed: Vec<(&PathBuf, u64)> = files
+        .iter()
+        .filter_map(|path| {
+            let hash = difference_hash(path);
+            progress.inc(1);
+            hash.map(|hash| (path, hash))
+        })
+        .collect();
+    progress.finish();
 
-    match bytes {
-        b if b >= GB => format!("{:.2} GB", b as f64 / GB as f64),
-        b if b >= MB => format!("{:.2} MB", b as f64 / MB as f64),
-        b if b >= KB => format!("{:.2} KB", b as f64 / KB as f64),
-        b => format!("{} B", b),
+    // Union-find: pairs within the threshold end up in the same group
+    let mut parent: Vec<usize> = (0..hashed.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
     }
+
+    for i in 0..hashed.len() {
+        for j in i + 1..hashed.len() {
+            if (hashed[i].1 ^ hashed[j].1).count_ones() <= threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashed.len() {
+        members.entry(root(&mut parent, i)).or_default().push(i);
+    }
+
+    let mut groups: Vec<SimilarGroup> = members
+        .into_values()
+        .filter(|m| m.len() > 1)
+        .map(|m| {
+            let mut distances = Vec::new();
+            for (a, &i) in m.iter().enumerate() {
+                for (b, &j) in m.iter().enumerate().skip(a + 1) {
+                    distances.push((a, b, (hashed[i].1 ^ hashed[j].1).count_ones()));
+                }
+            }
+            SimilarGroup {
+                files: m.iter().map(|&i| hashed[i].0.clone()).collect(),
+                distances,
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| a.files.cmp(&b.files));
+    groups
 }
 
-fn hash_file(path: &PathBuf) -> std::io::Result<(String, u64)> {
-    let file = File::open(path)?;
-    let size = file.metadata()?.len();
+/// Order each group so the copy to keep comes first
+pub fn sort_groups(groups: &mut [DuplicateGroup], rule: KeepRule) {
+    for group in groups {
+        sort_by_keep_rule(&mut group.files, rule);
+    }
+}
+
+/// Put the groups that matter most first (`find_duplicates` returns them in
+/// no particular order); ties are ordered by path so output is stable
+pub fn order_groups(groups: &mut [DuplicateGroup], order: GroupOrder) {
+    groups.sort_by(|a, b| {
+        let key = |g: &DuplicateGroup| match order {
+            GroupOrder::Size => g.size,
+            GroupOrder::Count => g.files.len() as u64,
+            GroupOrder::Wasted => g.wasted_bytes(),
+        };
+        key(b).cmp(&key(a)).then_with(|| a.files.cmp(&b.files))
+    });
+}
+
+/// Keep the best file of each group according to `rule` and remove the rest
+pub fn plan_removal(groups: &[DuplicateGroup], rule: KeepRule) -> Vec<RemovalPlan> {
+    groups
+        .iter()
+        .filter_map(|group| {
+            let mut files = group.files.clone();
+            sort_by_keep_rule(&mut files, rule);
+            let (kept, removed) = files.split_first()?;
+            Some(RemovalPlan {
+                kept: kept.clone(),
+                removed: removed.to_vec(),
+                bytes_freed: group.size * removed.len() as u64,
+            })
+        })
+        .collect()
+}
+
+/// Carry out a removal plan, moving files to the trash unless `use_trash` is off
+pub fn apply_removal(plans: &[RemovalPlan], use_trash: bool) -> RemovalResult {
+    let mut trash = TrashSession::new();
+    let mut result = RemovalResult {
+        removed: 0,
+        bytes_freed: 0,
+        errors: Vec::new(),
+    };
+
+    for plan in plans {
+        let size = plan.bytes_freed / plan.removed.len().max(1) as u64;
+        for file in &plan.removed {
+            match remove_file(file, use_trash.then_some(&mut trash)) {
+                Ok(_) => {
+                    result.removed += 1;
+                    result.bytes_freed += size;
+                }
+                Err(e) => result.errors.push(e),
+            }
+        }
+    }
 
-    let mut reader = BufReader::new(file);
+    result
+}
+
+/// Move a file to the trash session, or delete it permanently without one
+pub fn remove_file(file: &Path, trash: Option<&mut TrashSession>) -> Result<(), String> {
+    match trash {
+        Some(trash) => trash.add(file),
+        None => {
+            fs::remove_file(file).map_err(|e| format!("Failed to remove {}: {}", file.display(), e))
+        }
+    }
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+/// Sort so the copy to keep is first; ties fall back to the path for a stable result
+fn sort_by_keep_rule(files: &mut [PathBuf], rule: KeepRule) {
+    let modified = |p: &PathBuf| p.metadata().and_then(|m| m.modified()).ok();
+    let name_len = |p: &PathBuf| p.file_name().map(|n| n.len()).unwrap_or(0);
+
+    files.sort_by(|a, b| {
+        let order = match rule {
+            KeepRule::Oldest => modified(a).cmp(&modified(b)),
+            KeepRule::Newest => modified(b).cmp(&modified(a)),
+            KeepRule::Shallowest => a.components().count().cmp(&b.components().count()),
+            KeepRule::ShortestName => name_len(a).cmp(&name_len(b)),
+        };
+        order.then_with(|| a.cmp(b))
+    });
+}
+
+/// 64-bit dHash: shrink to 9x8 grayscale and compare each pixel with its right neighbour
+fn difference_hash(path: &Path) -> Option<u64> {
+    let image = image::open(path).ok()?;
+    let small = image
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Some(hash)
+}
+
+/// SHA-256 of a file's content, along with its size
+pub fn hash_file(path: &Path, options: &HashOptions) -> std::io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
     let mut hasher = Sha256::new();
-    let mut buffer = [0u8; BUFFER_SIZE];
 
+    if options.mmap && size >= MMAP_MIN_SIZE {
+        // SAFETY: the map is only read, and dropped before returning. A file
+        // truncated by another process meanwhile can fault, as with any mmap reader.
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            hasher.update(&map[..]);
+            return Ok((format!("{:x}", hasher.finalize()), size));
+        }
+    }
+
+    let mut buffer = vec![0u8; options.buffer_size.max(1)];
     loop {
-        let n = reader.read(&mut buffer)?;
+        let n = file.read(&mut buffer)?;
         if n == 0 {
             break;
         }
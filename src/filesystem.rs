@@ -0,0 +1,268 @@
+// Stellar - File System Module
+// @musem23
+//
+// The file operations the organizer relies on to move files, behind a trait.
+// RealFileSystem forwards to std::fs; MockFileSystem keeps an in-memory tree
+// and can fail chosen operations (EXDEV, permission denied...) so the move
+// fallbacks and skip reasons can be exercised without special mounts. It is
+// only built for tests (the `test-util` feature, enabled for this crate's own).
+
+use filetime::FileTime;
+#[cfg(any(test, feature = "test-util"))]
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(any(test, feature = "test-util"))]
+use std::path::PathBuf;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::Mutex;
+
+/// Size and kind of a path, as seen by a FileSystem
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    pub len: u64,
+    pub is_dir: bool,
+}
+
+/// File operations used to move files; shared by worker threads
+pub trait FileSystem: Sync {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Copy a file, or a folder with everything in it
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Information about the path itself (symbolic links are not followed)
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo>;
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// The actual disk, through std::fs
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        copy_path(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        fs::symlink_metadata(path).map(|m| FileInfo {
+            len: m.len(),
+            is_dir: m.is_dir(),
+        })
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// An io::Error kept as its kind and OS code, so it can be raised again
+#[cfg(any(test, feature = "test-util"))]
+type StoredError = (io::ErrorKind, Option<i32>);
+
+/// Operations recorded (and optionally failed) by MockFileSystem
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FsOp {
+    Rename,
+    Copy,
+    RemoveFile,
+    RemoveDirAll,
+    CreateDirAll,
+}
+
+/// In-memory file system for tests: files are paths with a size, every
+/// mutating call is recorded, and `fail` makes an operation on a path error out
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Default)]
+pub struct MockFileSystem {
+    files: Mutex<HashMap<PathBuf, u64>>,
+    dirs: Mutex<HashSet<PathBuf>>,
+    failures: Mutex<HashMap<(FsOp, PathBuf), StoredError>>,
+    calls: Mutex<Vec<(FsOp, PathBuf)>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl MockFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file of `len` bytes (its parent folders are created too)
+    pub fn add_file(&self, path: impl Into<PathBuf>, len: u64) {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            self.add_dirs(parent);
+        }
+        self.files.lock().unwrap().insert(path, len);
+    }
+
+    /// Make every `op` on `path` (the source for rename and copy) return `error`
+    pub fn fail(&self, op: FsOp, path: impl Into<PathBuf>, error: io::Error) {
+        self.failures
+            .lock()
+            .unwrap()
+            .insert((op, path.into()), (error.kind(), error.raw_os_error()));
+    }
+
+    /// Every mutating call so far, in order, with the path it applied to
+    pub fn calls(&self) -> Vec<(FsOp, PathBuf)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+
+    fn record(&self, op: FsOp, path: &Path) -> io::Result<()> {
+        self.calls.lock().unwrap().push((op, path.to_path_buf()));
+        match self.failures.lock().unwrap().get(&(op, path.to_path_buf())) {
+            Some((_, Some(code))) => Err(io::Error::from_raw_os_error(*code)),
+            Some((kind, None)) => Err(io::Error::from(*kind)),
+            None => Ok(()),
+        }
+    }
+
+    fn add_dirs(&self, path: &Path) {
+        let mut dirs = self.dirs.lock().unwrap();
+        for dir in path.ancestors().filter(|d| !d.as_os_str().is_empty()) {
+            dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    fn take_file(&self, path: &Path) -> io::Result<u64> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(path)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FileSystem for MockFileSystem {
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.record(FsOp::Rename, from)?;
+        let len = self.take_file(from)?;
+        self.files.lock().unwrap().insert(to.to_path_buf(), len);
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.record(FsOp::Copy, from)?;
+        let len = self.metadata(from)?.len;
+        self.files.lock().unwrap().insert(to.to_path_buf(), len);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.record(FsOp::RemoveFile, path)?;
+        self.take_file(path).map(|_| ())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.record(FsOp::RemoveDirAll, path)?;
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|p, _| !p.starts_with(path));
+        self.dirs.lock().unwrap().retain(|p| !p.starts_with(path));
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.record(FsOp::CreateDirAll, path)?;
+        self.add_dirs(path);
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        if let Some(len) = self.files.lock().unwrap().get(path) {
+            return Ok(FileInfo {
+                len: *len,
+                is_dir: false,
+            });
+        }
+        if self.dirs.lock().unwrap().contains(path) {
+            return Ok(FileInfo {
+                len: 0,
+                is_dir: true,
+            });
+        }
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+/// Check if an IO error is a cross-device link error (EXDEV)
+pub fn is_cross_device_error(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(CROSS_DEVICE_ERROR)
+}
+
+/// The error rename returns when source and destination are on different devices
+pub fn cross_device_error() -> io::Error {
+    io::Error::from_raw_os_error(CROSS_DEVICE_ERROR)
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+// EXDEV = 18 on Unix (Linux, macOS, BSD)
+// Windows uses ERROR_NOT_SAME_DEVICE = 17
+#[cfg(unix)]
+const CROSS_DEVICE_ERROR: i32 = 18; // EXDEV
+#[cfg(windows)]
+const CROSS_DEVICE_ERROR: i32 = 17; // ERROR_NOT_SAME_DEVICE
+
+/// Copy a file, or a folder with everything in it (links are recreated, not followed).
+/// Access and modification times are carried over, so a cross-device move
+/// keeps the dates that date organization relies on.
+fn copy_path(src: &Path, dest: &Path) -> io::Result<()> {
+    // Read before copying: reading the source may update its access time
+    let metadata = fs::metadata(src)?;
+    let accessed = FileTime::from_last_access_time(&metadata);
+    let modified = FileTime::from_last_modification_time(&metadata);
+
+    if metadata.is_dir() {
+        fs::create_dir(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            let target = dest.join(entry.file_name());
+            if entry.file_type()?.is_symlink() {
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+                #[cfg(windows)]
+                std::os::windows::fs::symlink_file(fs::read_link(entry.path())?, &target)?;
+            } else {
+                copy_path(&entry.path(), &target)?;
+            }
+        }
+    } else {
+        fs::copy(src, dest)?;
+    }
+
+    // Set last: creating a folder's entries updates its modification time
+    filetime::set_file_times(dest, accessed, modified)
+}
@@ -1,28 +1,61 @@
 // Stellar - History Module
 // @musem23
 //
-// Records file operations in a JSON history file for undo functionality.
-// Stores the last 50 operations at ~/.config/stellar/history.json.
+// Records file operations for undo functionality.
 // Each operation contains the original and destination paths of moved files.
+// Storage is a JSON file by default, or an SQLite database with the
+// `sqlite-history` cargo feature (see history/json.rs and history/sqlite.rs).
+// Runs in progress are journaled so an interrupted one can still be recorded
+// or undone (see history/journal.rs).
 
-use chrono::Local;
+use chrono::{Local, TimeDelta};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::{env, fs};
 
-const MAX_HISTORY: usize = 50;
+use crate::archive::{self, ArchivedFolder};
+use crate::config::HistoryConfig;
+use crate::organizer;
+
+mod journal;
+#[cfg(not(feature = "sqlite-history"))]
+mod json;
+#[cfg(feature = "sqlite-history")]
+mod sqlite;
+
+#[cfg(not(feature = "sqlite-history"))]
+use json as backend;
+#[cfg(feature = "sqlite-history")]
+use sqlite as backend;
+
+pub use journal::{pending_runs, pending_runs_in, Journal, PendingRun};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FileMove {
     pub from: String,
     pub to: String,
+    /// The file name changed (rename mode or conflict suffix), not just its folder
+    #[serde(default)]
+    pub is_rename: bool,
 }
 
+/// An operation without moves marks a run organized with --no-history,
+/// so undo stops there instead of reversing an earlier, unrelated run
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Operation {
     pub timestamp: String,
     pub folder: String,
     pub moves: Vec<FileMove>,
+    /// Folders compressed by --archive after the moves; unpacked first on undo
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archives: Vec<ArchivedFolder>,
+}
+
+impl Operation {
+    pub fn is_unrecorded(&self) -> bool {
+        self.moves.is_empty() && self.archives.is_empty()
+    }
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -30,45 +63,377 @@ struct History {
     operations: Vec<Operation>,
 }
 
+/// How much history is kept when a new operation is recorded
+#[derive(Clone, Copy, Default)]
+pub struct Retention {
+    /// None keeps the backend default, Some(0) removes the cap
+    pub max_operations: Option<usize>,
+    pub max_age: Option<TimeDelta>,
+}
+
+impl Retention {
+    pub fn from_config(config: &HistoryConfig) -> Self {
+        Self {
+            max_operations: config.max_operations,
+            max_age: config.max_age(),
+        }
+    }
+}
+
+static RETENTION: RwLock<Retention> = RwLock::new(Retention {
+    max_operations: None,
+    max_age: None,
+});
+
+/// Timestamps are stored in this sortable format, so they compare as strings
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Totals across every undone operation, with one sub-result per operation
 pub struct UndoResult {
     pub operation_time: String,
     pub restored: usize,
     pub failed: usize,
     pub errors: Vec<String>,
+    pub operations: Vec<OperationUndo>,
+    /// Why fewer operations than asked were undone (a --no-history run)
+    pub stopped_by: Option<String>,
+}
+
+pub struct OperationUndo {
+    pub timestamp: String,
+    pub folder: String,
+    pub restored: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+/// What undoing an operation would do, checked against the disk right now
+#[derive(Default)]
+pub struct UndoPreview {
+    /// Moves that can be reversed
+    pub restorable: Vec<FileMove>,
+    /// Moves whose file is no longer where it was moved to
+    pub missing: Vec<FileMove>,
+    /// Moves whose original path is now taken by another file
+    pub blocked: Vec<FileMove>,
 }
 
-/// Record a new operation to history
+/// Record a new operation to history (paths are stored absolute and canonical)
 pub fn record_operation(folder: &str, moves: Vec<FileMove>) -> Result<(), String> {
-    let mut history = load_history();
+    record_archived_operation(folder, moves, Vec::new())
+}
 
-    history.operations.push(Operation {
-        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        folder: folder.to_string(),
-        moves,
-    });
+/// Record an operation whose destination folders were then archived
+pub fn record_archived_operation(
+    folder: &str,
+    moves: Vec<FileMove>,
+    archives: Vec<ArchivedFolder>,
+) -> Result<(), String> {
+    let archives = archives
+        .into_iter()
+        .map(|a| ArchivedFolder {
+            folder: absolute_path(&a.folder),
+            archive: absolute_path(&a.archive),
+            removed: a.removed,
+        })
+        .collect();
+    let moves = moves
+        .into_iter()
+        .map(|mv| FileMove {
+            from: absolute_path(&mv.from),
+            to: absolute_path(&mv.to),
+            is_rename: mv.is_rename,
+        })
+        .collect();
+
+    backend::store(
+        &history_dir(),
+        Operation {
+            timestamp: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+            folder: absolute_path(folder),
+            moves,
+            archives,
+        },
+    )
+}
 
-    if history.operations.len() > MAX_HISTORY {
-        history.operations = history
-            .operations
-            .split_off(history.operations.len() - MAX_HISTORY);
+/// Apply the [history] limits to every operation recorded from now on
+pub fn set_retention(retention: Retention) {
+    if let Ok(mut current) = RETENTION.write() {
+        *current = retention;
     }
+}
+
+/// Remove operations recorded more than `older_than` ago and, with
+/// `missing_files`, those whose moved files and archives are all gone
+/// (they can never be undone). Returns how many were removed.
+pub fn prune_operations(
+    older_than: Option<TimeDelta>,
+    missing_files: bool,
+) -> Result<usize, String> {
+    prune_operations_in(&history_dir(), older_than, missing_files)
+}
+
+/// Same as `prune_operations`, for the history kept in `dir`
+pub fn prune_operations_in(
+    dir: &Path,
+    older_than: Option<TimeDelta>,
+    missing_files: bool,
+) -> Result<usize, String> {
+    let cutoff = older_than.map(timestamp_before);
+    backend::prune(dir, &|op: &Operation| {
+        cutoff.as_ref().is_some_and(|c| op.timestamp < *c) || (missing_files && files_gone(op))
+    })
+}
 
-    save_history(&history)
+/// Mark a run whose moves were not recorded (--no-history)
+pub fn record_unrecorded_run(folder: &str) -> Result<(), String> {
+    record_operation(folder, Vec::new())
 }
 
 /// Undo the last operation by reversing all file moves
 pub fn undo_last_operation() -> Result<UndoResult, String> {
-    let mut history = load_history();
+    undo_last_operations(1)
+}
+
+/// Undo the last N operations, newest first.
+/// A --no-history run on top of the history is consumed and reported instead;
+/// one further back ends the batch and is left for the next undo to report.
+pub fn undo_last_operations(count: usize) -> Result<UndoResult, String> {
+    let mut newest_first = backend::last(&history_dir(), count, None);
+    newest_first.reverse();
+
+    let (batch, marker) = undo_batch(&newest_first);
+    if batch == 0 {
+        return match marker {
+            Some(op) => {
+                backend::take_last(&history_dir(), 1, None)?;
+                Err(unrecorded_message(op))
+            }
+            None => Err("No operations to undo.".to_string()),
+        };
+    }
+
+    let operations = backend::take_last(&history_dir(), batch, None)?;
+    let mut result = undo_operations(&operations);
+    result.stopped_by = marker.map(|op| {
+        format!(
+            "Stopped at the run in {} ({}): it used --no-history.",
+            op.folder, op.timestamp
+        )
+    });
+    Ok(result)
+}
+
+/// How many of `newest_first` an undo reverses: the operations up to the
+/// first --no-history run, returned too when there is one
+pub fn undo_batch(newest_first: &[Operation]) -> (usize, Option<&Operation>) {
+    match newest_first.iter().position(Operation::is_unrecorded) {
+        Some(i) => (i, Some(&newest_first[i])),
+        None => (newest_first.len(), None),
+    }
+}
+
+/// Undo the most recent operation recorded in `folder` (or one of its subfolders)
+pub fn undo_last_operation_in_folder(folder: &str) -> Result<UndoResult, String> {
+    let operations = backend::take_last(&history_dir(), 1, Some(folder))?;
+
+    match operations.first() {
+        None => return Err(format!("No operations to undo in {}.", folder)),
+        Some(op) if op.is_unrecorded() => return Err(unrecorded_message(op)),
+        Some(_) => {}
+    }
+
+    Ok(undo_operations(&operations))
+}
+
+/// Reverse the given operations in order, continuing past failures.
+/// Empty folders are cleaned up once, after every operation has been reverted.
+pub fn undo_operations(operations: &[Operation]) -> UndoResult {
+    let mut folders_to_check: Vec<PathBuf> = Vec::new();
+    let mut result = UndoResult {
+        operation_time: operations
+            .first()
+            .map(|op| op.timestamp.clone())
+            .unwrap_or_default(),
+        restored: 0,
+        failed: 0,
+        errors: Vec::new(),
+        operations: Vec::new(),
+        stopped_by: None,
+    };
+
+    for operation in operations {
+        let undo = undo_operation(operation, &mut folders_to_check);
+        result.restored += undo.restored;
+        result.failed += undo.failed;
+        result.errors.extend(undo.errors.iter().cloned());
+        result.operations.push(undo);
+    }
+
+    cleanup_empty_folders(&folders_to_check, None);
+
+    result
+}
+
+/// Sort an operation's moves by whether undo can reverse them. A file inside
+/// a folder that --archive removed counts as present while the archive exists.
+pub fn preview_undo(operation: &Operation) -> UndoPreview {
+    let mut preview = UndoPreview::default();
+
+    for mv in &operation.moves {
+        let to = Path::new(&mv.to);
+        let archived = operation
+            .archives
+            .iter()
+            .any(|a| a.removed && to.starts_with(&a.folder) && Path::new(&a.archive).exists());
+
+        let list = if !entry_exists(to) && !archived {
+            &mut preview.missing
+        } else if entry_exists(Path::new(&mv.from)) {
+            &mut preview.blocked
+        } else {
+            &mut preview.restorable
+        };
+        list.push(mv.clone());
+    }
+
+    preview
+}
+
+/// Get the N most recent operations
+pub fn get_last_operations(count: usize) -> Vec<Operation> {
+    backend::last(&history_dir(), count, None)
+}
+
+/// Add an operation as is (timestamp included) to the history kept in `dir`
+pub fn store_operation_in(dir: &Path, operation: Operation) -> Result<(), String> {
+    backend::store(dir, operation)
+}
+
+/// The N most recent operations (optionally under `folder`) in the history
+/// kept in `dir`, oldest first
+pub fn get_last_operations_in(dir: &Path, count: usize, folder: Option<&str>) -> Vec<Operation> {
+    backend::last(dir, count, folder)
+}
+
+/// Remove the N most recent operations (optionally under `folder`) from the
+/// history kept in `dir`, returning them newest first
+pub fn take_last_operations_in(
+    dir: &Path,
+    count: usize,
+    folder: Option<&str>,
+) -> Result<Vec<Operation>, String> {
+    backend::take_last(dir, count, folder)
+}
+
+/// Make a path absolute and canonical so it resolves the same from any directory.
+/// Paths that no longer exist (the source of a move) canonicalize their parent,
+/// and so do symbolic links: a moved link is recorded as itself, not its target.
+pub fn absolute_path(path: &str) -> String {
+    let path = Path::new(path);
+    if !is_symlink(path) {
+        if let Ok(canonical) = fs::canonicalize(path) {
+            return canonical.to_string_lossy().to_string();
+        }
+    }
+
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|p| p.join(name))
+            .unwrap_or(absolute),
+        _ => absolute,
+    }
+    .to_string_lossy()
+    .to_string()
+}
+
+/// Get the N most recent operations recorded in `folder` (or one of its subfolders)
+pub fn get_operations_for_folder(folder: &str, count: usize) -> Vec<Operation> {
+    backend::last(&history_dir(), count, Some(folder))
+}
+
+/// Whether an operation folder lies under `folder`, compared by canonical path
+/// components so trailing slashes and relative spellings still match
+pub fn is_in_folder(operation_folder: &str, folder: &str) -> bool {
+    Path::new(&absolute_path(operation_folder)).starts_with(absolute_path(folder))
+}
+
+/// Remove empty folders and then their empty parents.
+/// With `stop_at`, only folders strictly inside it are removed.
+pub fn cleanup_empty_folders(folders: &[PathBuf], stop_at: Option<&Path>) {
+    for folder in folders {
+        let mut current = folder.clone();
+
+        while current.exists() && stop_at.is_none_or(|s| current != s && current.starts_with(s)) {
+            let is_empty = fs::read_dir(&current)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+
+            if is_empty {
+                let _ = fs::remove_dir(&current);
+                if let Some(parent) = current.parent() {
+                    current = parent.to_path_buf();
+                } else {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+}
 
-    let operation = history
-        .operations
-        .pop()
-        .ok_or_else(|| "No operations to undo.".to_string())?;
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+fn retention() -> Retention {
+    RETENTION.read().map(|r| *r).unwrap_or_default()
+}
+
+/// Timestamp of the moment `age` ago, comparable with Operation::timestamp
+fn timestamp_before(age: TimeDelta) -> String {
+    (Local::now() - age).format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// Nothing of the operation is left to undo (--no-history markers are kept)
+fn files_gone(operation: &Operation) -> bool {
+    !operation.is_unrecorded()
+        && operation.moves.iter().all(|mv| !Path::new(&mv.to).exists())
+        && operation
+            .archives
+            .iter()
+            .all(|a| !Path::new(&a.archive).exists())
+}
+
+fn unrecorded_message(operation: &Operation) -> String {
+    format!(
+        "No operations to undo: the last run in {} ({}) used --no-history.",
+        operation.folder, operation.timestamp
+    )
+}
 
+fn undo_operation(operation: &Operation, folders_to_check: &mut Vec<PathBuf>) -> OperationUndo {
     let mut restored = 0;
     let mut failed = 0;
     let mut errors = Vec::new();
-    let mut folders_to_check: Vec<PathBuf> = Vec::new();
+
+    // Archived folders must be back before their files can be restored
+    for record in &operation.archives {
+        if let Err(e) = archive::restore_archived_folder(record) {
+            errors.push(e);
+            failed += 1;
+        }
+    }
 
     for mv in &operation.moves {
         let dest_path = PathBuf::from(&mv.to);
@@ -87,66 +452,48 @@ pub fn undo_last_operation() -> Result<UndoResult, String> {
         }
     }
 
-    cleanup_empty_folders(&folders_to_check);
-
-    save_history(&history)?;
-
-    Ok(UndoResult {
-        operation_time: operation.timestamp,
+    OperationUndo {
+        timestamp: operation.timestamp.clone(),
+        folder: operation.folder.clone(),
         restored,
         failed,
         errors,
-    })
-}
-
-/// Get the N most recent operations
-pub fn get_last_operations(count: usize) -> Vec<Operation> {
-    let history = load_history();
-    let len = history.operations.len();
-    let start = len.saturating_sub(count);
-    history.operations[start..].to_vec()
+    }
 }
 
-// ============================================================================
-// Private helpers
-// ============================================================================
-
-fn get_history_path() -> PathBuf {
+/// ~/.config/stellar, where both history backends keep their file
+fn history_dir() -> PathBuf {
     let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home)
-        .join(".config")
-        .join("stellar")
-        .join("history.json")
+    PathBuf::from(home).join(".config").join("stellar")
 }
 
-fn load_history() -> History {
-    let path = get_history_path();
-    fs::read_to_string(&path)
+/// Read the JSON history file, empty when missing or unreadable
+fn load_json_history(dir: &Path) -> History {
+    fs::read_to_string(dir.join("history.json"))
         .ok()
         .and_then(|c| serde_json::from_str(&c).ok())
         .unwrap_or_default()
 }
 
-fn save_history(history: &History) -> Result<(), String> {
-    let path = get_history_path();
-
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
-    }
-
-    let json =
-        serde_json::to_string_pretty(history).map_err(|e| format!("Failed to serialize: {}", e))?;
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
 
-    fs::write(&path, json).map_err(|e| format!("Failed to write: {}", e))
+/// Whether something is at `path`; a link counts even if its target is gone
+fn entry_exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
 }
 
 fn restore_file(mv: &FileMove) -> Result<(), String> {
     let from = PathBuf::from(&mv.to);
     let to = PathBuf::from(&mv.from);
 
-    if !from.exists() {
+    if !entry_exists(&from) {
         return Err(format!("File not found: {}", mv.to));
     }
+    if entry_exists(&to) {
+        return Err(format!("Already exists: {}", mv.from));
+    }
 
     if let Some(parent) = to.parent() {
         if !parent.exists() {
@@ -154,57 +501,7 @@ fn restore_file(mv: &FileMove) -> Result<(), String> {
         }
     }
 
-    // Use move with cross-device fallback
-    move_file_with_fallback(&from, &to)
+    // Use move with cross-device fallback (also restores whole folders)
+    organizer::move_file_with_fallback(&from, &to)
         .map_err(|e| format!("Failed to restore {}: {}", mv.from, e))
 }
-
-/// Move a file, falling back to copy+delete for cross-device moves
-fn move_file_with_fallback(src: &PathBuf, dest: &PathBuf) -> std::io::Result<()> {
-    match fs::rename(src, dest) {
-        Ok(_) => Ok(()),
-        Err(e) if is_cross_device_error(&e) => {
-            // Cross-device move: fall back to copy + delete
-            fs::copy(src, dest)?;
-            fs::remove_file(src)?;
-            Ok(())
-        }
-        Err(e) => Err(e),
-    }
-}
-
-/// Check if an IO error is a cross-device link error (EXDEV)
-fn is_cross_device_error(e: &std::io::Error) -> bool {
-    // EXDEV = 18 on Unix (Linux, macOS, BSD)
-    // Windows uses ERROR_NOT_SAME_DEVICE = 17
-    #[cfg(unix)]
-    const CROSS_DEVICE_ERROR: i32 = 18; // EXDEV
-    #[cfg(windows)]
-    const CROSS_DEVICE_ERROR: i32 = 17; // ERROR_NOT_SAME_DEVICE
-
-    e.raw_os_error() == Some(CROSS_DEVICE_ERROR)
-}
-
-/// Remove empty folders after undo (recursive up to parent)
-fn cleanup_empty_folders(folders: &[PathBuf]) {
-    for folder in folders {
-        let mut current = folder.clone();
-
-        while current.exists() {
-            let is_empty = fs::read_dir(&current)
-                .map(|mut entries| entries.next().is_none())
-                .unwrap_or(false);
-
-            if is_empty {
-                let _ = fs::remove_dir(&current);
-                if let Some(parent) = current.parent() {
-                    current = parent.to_path_buf();
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
-    }
-}
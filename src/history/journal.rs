@@ -0,0 +1,207 @@
+// Stellar - Run Journal
+// @musem23
+//
+// Crash safety for organize runs. Each run that will be recorded writes
+// ~/.config/stellar/journal/<time>-<pid>-<n>.jsonl: a header line (timestamp,
+// folder), then one line per file as soon as it is moved. The file is locked
+// for the life of the run and deleted once the run is in the history, so an
+// unlocked journal is a run that was killed before it could be recorded.
+
+use super::{
+    absolute_path, backend, history_dir, undo_operations, FileMove, Operation, UndoResult,
+    TIMESTAMP_FORMAT,
+};
+use chrono::Local;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Journals started by this process, to keep their names apart
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// The moves of a run in progress, written to disk as they happen
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+/// A run that was interrupted before it reached the history
+pub struct PendingRun {
+    pub timestamp: String,
+    pub folder: String,
+    pub moves: Vec<FileMove>,
+    path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    timestamp: String,
+    folder: String,
+}
+
+impl Journal {
+    /// Start the journal of a run in `folder`
+    pub fn begin(folder: &str) -> Result<Self, String> {
+        Self::begin_in(&journal_dir(), folder)
+    }
+
+    /// Same as `begin`, with the journal written in `dir`
+    pub fn begin_in(dir: &Path, folder: &str) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+        let now = Local::now();
+        // Several folders in one process may start a run within the same second
+        let path = dir.join(format!(
+            "{}-{}-{}.jsonl",
+            now.format("%Y%m%d%H%M%S"),
+            process::id(),
+            SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+        // Held until the journal is dropped: a locked journal belongs to a live run
+        FileExt::try_lock_exclusive(&file)
+            .map_err(|e| format!("Cannot lock {}: {}", path.display(), e))?;
+
+        let header = Header {
+            timestamp: now.format(TIMESTAMP_FORMAT).to_string(),
+            folder: absolute_path(folder),
+        };
+        write_line(&mut file, &header)
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Add a move that just happened (a failed write only loses crash safety)
+    pub fn append(&self, file_move: &FileMove) {
+        let file_move = FileMove {
+            from: absolute_path(&file_move.from),
+            to: absolute_path(&file_move.to),
+            is_rename: file_move.is_rename,
+        };
+        if let Ok(mut file) = self.file.lock() {
+            let _ = write_line(&mut file, &file_move);
+        }
+    }
+
+    /// The run is in the history: the journal is no longer needed
+    pub fn finish(self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl PendingRun {
+    /// Add the run to the history under its original time, so `stellar undo`
+    /// reverses it like any other
+    pub fn record(&self) -> Result<(), String> {
+        self.record_in(&history_dir())
+    }
+
+    /// Same as `record`, into the history kept in `dir`
+    pub fn record_in(&self, dir: &Path) -> Result<(), String> {
+        backend::store(dir, self.operation())?;
+        self.discard();
+        Ok(())
+    }
+
+    /// Move the files of the run back where they were
+    pub fn undo(&self) -> UndoResult {
+        let result = undo_operations(&[self.operation()]);
+        self.discard();
+        result
+    }
+
+    /// Forget the run (its files stay where they are)
+    pub fn discard(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    fn operation(&self) -> Operation {
+        Operation {
+            timestamp: self.timestamp.clone(),
+            folder: self.folder.clone(),
+            moves: self.moves.clone(),
+            archives: Vec::new(),
+        }
+    }
+}
+
+/// Journals left by runs that are no longer running, oldest first.
+/// Journals without any move are deleted on the way.
+pub fn pending_runs() -> Vec<PendingRun> {
+    pending_runs_in(&journal_dir())
+}
+
+/// Same as `pending_runs`, for the journals in `dir`
+pub fn pending_runs_in(dir: &Path) -> Vec<PendingRun> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        .collect();
+    paths.sort();
+
+    let mut runs = Vec::new();
+    for path in paths {
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        if FileExt::try_lock_exclusive(&file).is_err() {
+            continue; // Still running
+        }
+
+        match read_journal(&path) {
+            Some(run) if !run.moves.is_empty() => runs.push(run),
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    runs
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+fn journal_dir() -> PathBuf {
+    history_dir().join("journal")
+}
+
+/// One JSON value per line, written in a single call so lines never interleave
+fn write_line<T: Serialize>(file: &mut File, value: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+}
+
+/// Parse a journal; a line cut short by the crash is ignored
+fn read_journal(path: &Path) -> Option<PendingRun> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut lines = content.lines();
+    let header: Header = serde_json::from_str(lines.next()?).ok()?;
+    let moves = lines
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    Some(PendingRun {
+        timestamp: header.timestamp,
+        folder: header.folder,
+        moves,
+        path: path.to_path_buf(),
+    })
+}
@@ -0,0 +1,89 @@
+// Stellar - JSON History Backend
+// @musem23
+//
+// Default history storage at ~/.config/stellar/history.json.
+// Keeps the last 50 operations (or [history] max_operations) and rewrites
+// the whole file on every change.
+
+use super::{is_in_folder, load_json_history, retention, timestamp_before, History, Operation};
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_MAX_HISTORY: usize = 50;
+
+/// Append an operation, dropping the oldest ones past the cap or max age
+pub fn store(dir: &Path, operation: Operation) -> Result<(), String> {
+    let mut history = load_json_history(dir);
+    history.operations.push(operation);
+
+    let retention = retention();
+    if let Some(age) = retention.max_age {
+        let cutoff = timestamp_before(age);
+        history.operations.retain(|op| op.timestamp >= cutoff);
+    }
+
+    let max = retention.max_operations.unwrap_or(DEFAULT_MAX_HISTORY);
+    if max > 0 && history.operations.len() > max {
+        history.operations = history.operations.split_off(history.operations.len() - max);
+    }
+
+    save_history(dir, &history)
+}
+
+/// Remove every operation matching `drop`, returning how many were removed
+pub fn prune(dir: &Path, drop: &dyn Fn(&Operation) -> bool) -> Result<usize, String> {
+    let mut history = load_json_history(dir);
+    let before = history.operations.len();
+    history.operations.retain(|op| !drop(op));
+
+    let removed = before - history.operations.len();
+    if removed > 0 {
+        save_history(dir, &history)?;
+    }
+    Ok(removed)
+}
+
+/// The N most recent operations (optionally under `folder`), oldest first
+pub fn last(dir: &Path, count: usize, folder: Option<&str>) -> Vec<Operation> {
+    let mut matching: Vec<Operation> = load_json_history(dir)
+        .operations
+        .into_iter()
+        .filter(|op| folder.is_none_or(|f| is_in_folder(&op.folder, f)))
+        .collect();
+    let start = matching.len().saturating_sub(count);
+    matching.split_off(start)
+}
+
+/// Remove the N most recent operations (optionally under `folder`) and
+/// return them newest first
+pub fn take_last(dir: &Path, count: usize, folder: Option<&str>) -> Result<Vec<Operation>, String> {
+    let mut history = load_json_history(dir);
+    let mut taken = Vec::new();
+    let mut index = history.operations.len();
+
+    while taken.len() < count && index > 0 {
+        index -= 1;
+        if folder.is_none_or(|f| is_in_folder(&history.operations[index].folder, f)) {
+            taken.push(history.operations.remove(index));
+        }
+    }
+
+    if !taken.is_empty() {
+        save_history(dir, &history)?;
+    }
+
+    Ok(taken)
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+fn save_history(dir: &Path, history: &History) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let json =
+        serde_json::to_string_pretty(history).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(dir.join("history.json"), json).map_err(|e| format!("Failed to write: {}", e))
+}
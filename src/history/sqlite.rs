@@ -0,0 +1,288 @@
+// Stellar - SQLite History Backend
+// @musem23
+//
+// Optional history storage (cargo feature `sqlite-history`) at
+// ~/.config/stellar/history.db. Operations and their moves live in two tables
+// indexed by timestamp and folder: history is unbounded unless [history]
+// sets a limit, each record is a single insert, and per-folder queries never
+// load the whole history.
+// An existing history.json is imported when the database is first created.
+
+use super::{
+    absolute_path, load_json_history, retention, timestamp_before, ArchivedFolder, FileMove,
+    Operation,
+};
+use rusqlite::{params, params_from_iter, Connection};
+use std::fs;
+use std::path::{Path, MAIN_SEPARATOR};
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+CREATE TABLE IF NOT EXISTS operations (
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp TEXT NOT NULL,
+    folder    TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS moves (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation_id INTEGER NOT NULL REFERENCES operations(id) ON DELETE CASCADE,
+    source       TEXT NOT NULL,
+    destination  TEXT NOT NULL,
+    is_rename    INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS archives (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation_id INTEGER NOT NULL REFERENCES operations(id) ON DELETE CASCADE,
+    folder       TEXT NOT NULL,
+    archive      TEXT NOT NULL,
+    removed      INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_operations_timestamp ON operations(timestamp);
+CREATE INDEX IF NOT EXISTS idx_operations_folder ON operations(folder);
+CREATE INDEX IF NOT EXISTS idx_moves_operation ON moves(operation_id);
+CREATE INDEX IF NOT EXISTS idx_archives_operation ON archives(operation_id);
+";
+
+/// Insert an operation and its moves in one transaction, then apply the
+/// [history] limits
+pub fn store(dir: &Path, operation: Operation) -> Result<(), String> {
+    let mut conn = open(dir)?;
+    let tx = conn.transaction().map_err(db_error)?;
+    insert_operation(&tx, &operation)?;
+
+    let retention = retention();
+    if let Some(age) = retention.max_age {
+        tx.execute(
+            "DELETE FROM operations WHERE timestamp < ?1",
+            [timestamp_before(age)],
+        )
+        .map_err(db_error)?;
+    }
+    if let Some(max) = retention.max_operations.filter(|&max| max > 0) {
+        tx.execute(
+            "DELETE FROM operations WHERE id NOT IN (
+                 SELECT id FROM operations ORDER BY timestamp DESC, id DESC LIMIT ?1
+             )",
+            [i64::try_from(max).unwrap_or(i64::MAX)],
+        )
+        .map_err(db_error)?;
+    }
+
+    tx.commit().map_err(db_error)
+}
+
+/// Remove every operation matching `drop`, returning how many were removed
+pub fn prune(dir: &Path, drop: &dyn Fn(&Operation) -> bool) -> Result<usize, String> {
+    let mut conn = open(dir)?;
+    let tx = conn.transaction().map_err(db_error)?;
+    let rows = query_last(&tx, usize::MAX, None)?;
+
+    let mut removed = 0;
+    for (id, _) in rows.iter().filter(|(_, op)| drop(op)) {
+        tx.execute("DELETE FROM operations WHERE id = ?1", [id])
+            .map_err(db_error)?;
+        removed += 1;
+    }
+    tx.commit().map_err(db_error)?;
+
+    Ok(removed)
+}
+
+/// The N most recent operations (optionally under `folder`), oldest first
+pub fn last(dir: &Path, count: usize, folder: Option<&str>) -> Vec<Operation> {
+    open(dir)
+        .and_then(|conn| query_last(&conn, count, folder))
+        .map(|rows| rows.into_iter().rev().map(|(_, op)| op).collect())
+        .unwrap_or_default()
+}
+
+/// Delete the N most recent operations (optionally under `folder`) and
+/// return them newest first
+pub fn take_last(dir: &Path, count: usize, folder: Option<&str>) -> Result<Vec<Operation>, String> {
+    let mut conn = open(dir)?;
+    let tx = conn.transaction().map_err(db_error)?;
+    let rows = query_last(&tx, count, folder)?;
+
+    for (id, _) in &rows {
+        tx.execute("DELETE FROM operations WHERE id = ?1", [id])
+            .map_err(db_error)?;
+    }
+    tx.commit().map_err(db_error)?;
+
+    Ok(rows.into_iter().map(|(_, op)| op).collect())
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+fn open(dir: &Path) -> Result<Connection, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let path = dir.join("history.db");
+    let is_new = !path.exists();
+
+    let mut conn = Connection::open(&path).map_err(db_error)?;
+    conn.execute_batch(SCHEMA).map_err(db_error)?;
+
+    if is_new {
+        import_json_history(dir, &mut conn)?;
+    }
+
+    Ok(conn)
+}
+
+/// Carry over the operations recorded by the JSON backend, oldest first
+fn import_json_history(dir: &Path, conn: &mut Connection) -> Result<(), String> {
+    let history = load_json_history(dir);
+    if history.operations.is_empty() {
+        return Ok(());
+    }
+
+    let tx = conn.transaction().map_err(db_error)?;
+    for operation in &history.operations {
+        insert_operation(&tx, operation)?;
+    }
+    tx.commit().map_err(db_error)
+}
+
+fn insert_operation(conn: &Connection, operation: &Operation) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO operations (timestamp, folder) VALUES (?1, ?2)",
+        params![operation.timestamp, operation.folder],
+    )
+    .map_err(db_error)?;
+    let id = conn.last_insert_rowid();
+
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO moves (operation_id, source, destination, is_rename)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .map_err(db_error)?;
+    for mv in &operation.moves {
+        stmt.execute(params![id, mv.from, mv.to, mv.is_rename])
+            .map_err(db_error)?;
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "INSERT INTO archives (operation_id, folder, archive, removed)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .map_err(db_error)?;
+    for record in &operation.archives {
+        stmt.execute(params![id, record.folder, record.archive, record.removed])
+            .map_err(db_error)?;
+    }
+
+    Ok(())
+}
+
+/// The N most recent operations with their row ids, newest first
+fn query_last(
+    conn: &Connection,
+    count: usize,
+    folder: Option<&str>,
+) -> Result<Vec<(i64, Operation)>, String> {
+    let limit = i64::try_from(count).unwrap_or(i64::MAX);
+
+    // Subfolders are matched as a key range so the folder index is used
+    let (filter, args) = match folder {
+        Some(folder) => {
+            let folder = absolute_path(folder);
+            let (lower, upper) = subfolder_range(&folder);
+            (
+                "WHERE folder = ?1 OR (folder >= ?2 AND folder < ?3)",
+                vec![folder, lower, upper],
+            )
+        }
+        None => ("", Vec::new()),
+    };
+    let sql = format!(
+        "SELECT id, timestamp, folder FROM operations {} \
+         ORDER BY timestamp DESC, id DESC LIMIT {}",
+        filter, limit
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(db_error)?;
+    let headers = stmt
+        .query_map(params_from_iter(args), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    let mut moves_stmt = conn
+        .prepare(
+            "SELECT source, destination, is_rename FROM moves
+             WHERE operation_id = ?1 ORDER BY id",
+        )
+        .map_err(db_error)?;
+    let mut archives_stmt = conn
+        .prepare(
+            "SELECT folder, archive, removed FROM archives
+             WHERE operation_id = ?1 ORDER BY id",
+        )
+        .map_err(db_error)?;
+
+    headers
+        .into_iter()
+        .map(|(id, timestamp, folder)| {
+            let moves = moves_stmt
+                .query_map([id], |row| {
+                    Ok(FileMove {
+                        from: row.get(0)?,
+                        to: row.get(1)?,
+                        is_rename: row.get(2)?,
+                    })
+                })
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            let archives = archives_stmt
+                .query_map([id], |row| {
+                    Ok(ArchivedFolder {
+                        folder: row.get(0)?,
+                        archive: row.get(1)?,
+                        removed: row.get(2)?,
+                    })
+                })
+                .map_err(db_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_error)?;
+            Ok((
+                id,
+                Operation {
+                    timestamp,
+                    folder,
+                    moves,
+                    archives,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Bounds of every path strictly inside `folder`: "/a/b/" <= path < "/a/b0"
+fn subfolder_range(folder: &str) -> (String, String) {
+    let mut lower = folder.to_string();
+    if !lower.ends_with(MAIN_SEPARATOR) {
+        lower.push(MAIN_SEPARATOR);
+    }
+
+    let mut upper = lower.clone();
+    upper.pop();
+    upper.push((MAIN_SEPARATOR as u8 + 1) as char);
+
+    (lower, upper)
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("History database error: {}", e)
+}
@@ -8,16 +8,18 @@ pub mod security;
 pub mod settings;
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
-use crate::config::Config;
+use crate::config::{self, Config, FolderPreference};
 use crate::duplicates;
-use crate::history;
+use crate::history::{self, FileMove};
 use crate::lock;
-use crate::modes::{OrganizationMode, RenameMode};
+use crate::modes::{GroupOrder, KeepRule, OrganizationMode, RenameMode};
 use crate::organizer;
 use crate::scanner;
+use crate::stats;
+use crate::trash::TrashSession;
 use crate::ui;
 use crate::watcher;
 
@@ -28,7 +30,7 @@ pub fn run(mut config: Config) {
 
     loop {
         match ui::select_main_menu() {
-            Some(0) => organize_folder(&config, &home_dir),
+            Some(0) => organize_folder(&mut config, &home_dir),
             Some(1) => watch_folder(&config, &home_dir),
             Some(2) => find_duplicates(&config, &home_dir),
             Some(3) => {
@@ -48,7 +50,7 @@ pub fn run(mut config: Config) {
     }
 }
 
-fn organize_folder(config: &Config, home_dir: &str) {
+fn organize_folder(config: &mut Config, home_dir: &str) {
     let folders = get_available_folders(home_dir, &config.protected);
 
     let source_dir = match ui::select_folder(&folders) {
@@ -68,7 +70,7 @@ fn organize_folder(config: &Config, home_dir: &str) {
         return;
     }
 
-    if scanner::is_project_folder(&source_dir) {
+    if scanner::is_project_folder(&source_dir, &scanner::project_indicators(&config.protected)) {
         ui::print_warning("This appears to be a project folder.");
         if !ui::confirm_with_default("Continue anyway?", false) {
             return;
@@ -83,35 +85,45 @@ fn organize_folder(config: &Config, home_dir: &str) {
         }
     };
 
-    let recursive = ui::confirm_with_default("Scan subdirectories recursively?", false);
-
-    let (org_mode, rename_mode) = if ui::confirm_use_defaults() {
-        (
-            OrganizationMode::from_index(config.preferences.organization_mode),
-            RenameMode::from_index(config.preferences.rename_mode),
-        )
-    } else {
-        let org = match ui::select_organization_mode(config.preferences.organization_mode) {
-            Some(m) => OrganizationMode::from_index(m),
-            None => return,
-        };
-        let rm = match ui::select_rename_mode(config.preferences.rename_mode) {
-            Some(idx) => RenameMode::from_index(idx),
+    let folder_key = history::absolute_path(&source_dir);
+    let last = config
+        .folder_preferences
+        .get(&folder_key)
+        .copied()
+        .filter(|_| ui::confirm_with_default("Use last settings for this folder?", true));
+
+    let settings = match last {
+        Some(pref) => pref,
+        None => match select_folder_settings(config) {
+            Some(pref) => pref,
             None => return,
-        };
-        (org, rm)
+        },
     };
+    let recursive = settings.recursive;
+    let org_mode = OrganizationMode::from_index(settings.organization_mode);
+    let rename_mode = RenameMode::from_index(settings.rename_mode);
+
+    if recursive && scanner::looks_organized(&source_dir, &config.categories) {
+        ui::print_warning(
+            "This folder looks already organized; a recursive run would reshuffle it.",
+        );
+        if !ui::confirm_with_default("Continue anyway?", false) {
+            return;
+        }
+    }
 
-    let files_map = scan_files(&source_dir, &config.categories, org_mode, recursive);
+    let scan_options = scanner::ScanOptions::new(config);
+    let mut files_map = scan_files(&source_dir, &scan_options, org_mode, recursive);
     if files_map.is_empty() {
         ui::print_info("No files to organize in this folder.");
         return;
     }
 
+    let mut options = organizer::OrganizeOptions::new(config, rename_mode.map(Into::into));
+
     if ui::ask_dry_run() {
-        let preview =
-            organizer::generate_dry_run_preview(&source_dir, &files_map, rename_mode.map(Into::into).as_ref());
-        ui::print_dry_run_preview(&preview);
+        let preview = organizer::generate_dry_run_preview(&source_dir, &files_map, &options);
+        ui::print_dry_run_preview(&preview, ui::DEFAULT_PREVIEW_LIMIT);
         if !ui::confirm("Proceed with these changes?") {
             ui::print_info("Operation cancelled.");
             return;
@@ -123,17 +135,208 @@ fn organize_folder(config: &Config, home_dir: &str) {
             return;
         }
     }
+    let Some(collisions_skipped) = ui::settle_rename_collisions(&mut files_map, &mut options)
+    else {
+        ui::print_info("Operation cancelled.");
+        return;
+    };
+
+    let record_history = ui::confirm_with_default("Record in history (allows undo)?", true);
+    let journal = record_history
+        .then(|| ui::start_journal(&source_dir))
+        .flatten();
+    options.journal = journal.as_ref();
 
-    let result = organizer::move_files(&source_dir, &files_map, rename_mode.map(Into::into).as_ref());
-    organizer::record_moves(&source_dir, result.moves);
+    let mut result = match ui::move_files_with_progress(&source_dir, &files_map, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            ui::print_error(&e.to_string());
+            return;
+        }
+    };
+    result.stats.unknown_extensions = scan_options.unknown_extensions.take();
+    for path in scan_options.ignored.take() {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::Ignored);
+    }
+    for path in scan_options.left_unmatched.take() {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::Unmatched);
+    }
+    for path in collisions_skipped {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::NameCollision);
+    }
+    let moves = result.moves.clone();
+    let recorded = if record_history {
+        organizer::record_moves(&source_dir, result.moves)
+    } else {
+        organizer::mark_unrecorded(&source_dir, &result.moves);
+        Ok(())
+    };
+    ui::finish_journal(journal, recorded);
     ui::print_statistics(&result.stats);
     ui::print_success("Files organized successfully!");
 
+    if !result.stats.unknown_extensions.is_empty() {
+        suggest_categories(
+            config,
+            &source_dir,
+            &moves,
+            &result.stats.unknown_extensions,
+            record_history,
+        );
+    }
+
+    if let Err(e) = config::save_folder_preference(&folder_key, &settings) {
+        ui::print_warning(&format!(
+            "Could not remember settings for this folder: {}",
+            e
+        ));
+    }
+    config.folder_preferences.insert(folder_key, settings);
+
     if ui::confirm("Open folder?") {
         organizer::open_folder(&source_dir);
     }
 }
 
+/// Offer to give the extensions that fell into Others a category, save it,
+/// then move their files out of Others right away
+fn suggest_categories(
+    config: &mut Config,
+    source_dir: &str,
+    moves: &[FileMove],
+    unknown: &HashMap<String, usize>,
+    record_history: bool,
+) {
+    if !ui::confirm_with_default("Create categories for them now?", false) {
+        return;
+    }
+
+    let mut extensions: Vec<_> = unknown.iter().collect();
+    extensions.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut assigned: HashMap<String, String> = HashMap::new();
+    for (ext, count) in extensions {
+        if let Some(category) = select_category_for(config, ext, *count) {
+            config
+                .categories
+                .entry(category.clone())
+                .or_default()
+                .push(ext.clone());
+            assigned.insert(ext.clone(), category);
+        }
+    }
+    if assigned.is_empty() {
+        return;
+    }
+    let mut changed: Vec<String> = assigned.values().cloned().collect();
+    changed.sort();
+    changed.dedup();
+    if let Err(e) = config::save_categories(&config.categories, &changed) {
+        ui::print_warning(&format!("Could not save the new categories: {}", e));
+    }
+
+    // Others/<rest> (hybrid: Others/2024) becomes <category>/<rest>
+    let root = Path::new(source_dir);
+    let mut files_map: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for mv in moves {
+        let to = Path::new(&mv.to);
+        let ext = to
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let Some(category) = assigned.get(&ext) else {
+            continue;
+        };
+        let Some(folder) = to.parent().and_then(|p| p.strip_prefix(root).ok()) else {
+            continue;
+        };
+        let mut components = folder.components();
+        if components.next().is_none_or(|c| c.as_os_str() != "Others") {
+            continue;
+        }
+        let rest = components.as_path();
+        let folder = if rest.as_os_str().is_empty() {
+            category.clone()
+        } else {
+            format!("{}/{}", category, rest.display())
+        };
+        files_map.entry(folder).or_default().push(to.to_path_buf());
+    }
+    if files_map.is_empty() {
+        return;
+    }
+
+    let emptied: Vec<PathBuf> = files_map
+        .values()
+        .flatten()
+        .filter_map(|p| p.parent().map(Path::to_path_buf))
+        .collect();
+    let journal = record_history
+        .then(|| ui::start_journal(source_dir))
+        .flatten();
+    let mut options = organizer::OrganizeOptions::new(config, None);
+    options.journal = journal.as_ref();
+    let result = match ui::move_files_with_progress(source_dir, &files_map, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            ui::print_error(&e.to_string());
+            return;
+        }
+    };
+    let recorded = if record_history {
+        organizer::record_moves(source_dir, result.moves)
+    } else {
+        organizer::mark_unrecorded(source_dir, &result.moves);
+        Ok(())
+    };
+    ui::finish_journal(journal, recorded);
+    history::cleanup_empty_folders(&emptied, Some(root));
+
+    ui::print_success(&format!(
+        "{} files moved out of Others",
+        result.stats.files_moved
+    ));
+}
+
+/// Existing or new category for one unknown extension; None skips it
+fn select_category_for(config: &Config, ext: &str, count: usize) -> Option<String> {
+    let mut names: Vec<&str> = config.categories.keys().map(String::as_str).collect();
+    names.sort_unstable();
+
+    let mut items = vec!["New category..."];
+    items.extend(&names);
+
+    match ui::select_from_list(&format!("Category for .{} ({} files)", ext, count), &items)? {
+        0 => ui::input_category_name(),
+        i => Some(names[i - 1].to_string()),
+    }
+}
+
+/// Ask for recursion, organization and rename modes (saved preferences as defaults)
+fn select_folder_settings(config: &Config) -> Option<FolderPreference> {
+    let recursive = ui::confirm_with_default("Scan subdirectories recursively?", false);
+
+    if ui::confirm_use_defaults() {
+        return Some(FolderPreference {
+            organization_mode: config.preferences.organization_mode,
+            rename_mode: config.preferences.rename_mode,
+            recursive,
+        });
+    }
+
+    Some(FolderPreference {
+        organization_mode: ui::select_organization_mode(config.preferences.organization_mode)?,
+        rename_mode: ui::select_rename_mode(config.preferences.rename_mode)?,
+        recursive,
+    })
+}
+
 fn watch_folder(config: &Config, home_dir: &str) {
     let folders = get_available_folders(home_dir, &config.protected);
 
@@ -171,7 +374,8 @@ fn watch_folder(config: &Config, home_dir: &str) {
         }
     };
 
-    watcher::watch_folder(&folder_path, &config.categories, rename_mode.map(Into::into));
+    let options = organizer::OrganizeOptions::new(config, rename_mode.map(Into::into));
+    watcher::watch_folder(&folder_path, &options, &config.watch, false, None);
 }
 
 fn find_duplicates(config: &Config, home_dir: &str) {
@@ -193,18 +397,19 @@ fn find_duplicates(config: &Config, home_dir: &str) {
         ui::print_error(&format!("Not a directory: {}", source_dir));
         return;
     }
-    let spinner = ui::create_spinner("Scanning for duplicates...");
 
-    let all_files: Vec<PathBuf> = fs::read_dir(&source_dir)
-        .into_iter()
-        .flatten()
-        .flatten()
-        .map(|e| e.path())
-        .filter(|p| p.is_file())
-        .collect();
-
-    let duplicate_groups = duplicates::find_duplicates(&all_files);
-    spinner.finish_and_clear();
+    let all_files = duplicates::filter_min_size(
+        duplicates::list_files(std::path::Path::new(&source_dir), false),
+        0,
+    );
+    let progress = ui::create_progress_bar(all_files.len() as u64, "Scanning for duplicates...");
+    let mut duplicate_groups = duplicates::find_duplicates(
+        &all_files,
+        &duplicates::HashOptions::from_config(&config.hashing),
+        &progress,
+    );
+    duplicates::sort_groups(&mut duplicate_groups, KeepRule::default());
+    duplicates::order_groups(&mut duplicate_groups, GroupOrder::default());
 
     if duplicate_groups.is_empty() {
         ui::print_success("No duplicate files found!");
@@ -222,41 +427,33 @@ fn handle_duplicates(groups: &[duplicates::DuplicateGroup]) {
     };
 
     match action {
-        0 => remove_all_duplicates(groups),
-        1 => review_duplicates(groups),
+        0 => remove_all_duplicates(groups, true),
+        1 => remove_all_duplicates(groups, false),
+        2 => review_duplicates(groups, true),
+        3 => review_duplicates(groups, false),
         _ => {}
     }
 }
 
-fn remove_all_duplicates(groups: &[duplicates::DuplicateGroup]) {
-    if !ui::confirm_with_default(
-        "This will permanently delete duplicate files. Continue?",
-        false,
-    ) {
+fn remove_all_duplicates(groups: &[duplicates::DuplicateGroup], use_trash: bool) {
+    if !use_trash
+        && !ui::confirm_with_default(
+            "This will permanently delete duplicate files. Continue?",
+            false,
+        )
+    {
         ui::print_info("Operation cancelled.");
         return;
     }
 
-    let mut removed = 0;
-    let mut freed_bytes: u64 = 0;
-
-    for group in groups {
-        for file in group.files.iter().skip(1) {
-            if fs::remove_file(file).is_ok() {
-                removed += 1;
-                freed_bytes += group.size;
-            }
-        }
-    }
-
-    ui::print_success(&format!(
-        "Removed {} duplicate files, freed {}",
-        removed,
-        duplicates::format_size(freed_bytes)
-    ));
+    let plans = duplicates::plan_removal(groups, KeepRule::default());
+    let result = duplicates::apply_removal(&plans, use_trash);
+    ui::print_removal_result(&result, use_trash);
 }
 
-fn review_duplicates(groups: &[duplicates::DuplicateGroup]) {
+fn review_duplicates(groups: &[duplicates::DuplicateGroup], use_trash: bool) {
+    let mut trash = TrashSession::new();
+
     for (i, group) in groups.iter().enumerate() {
         ui::print_info(&format!("Group {} of {}:", i + 1, groups.len()));
         for file in &group.files {
@@ -266,8 +463,8 @@ fn review_duplicates(groups: &[duplicates::DuplicateGroup]) {
         if let Some(keep_idx) = ui::select_file_to_keep(&group.files) {
             for (j, file) in group.files.iter().enumerate() {
                 if j != keep_idx {
-                    if let Err(e) = fs::remove_file(file) {
-                        ui::print_error(&format!("Failed to remove {}: {}", file.display(), e));
+                    if let Err(e) = duplicates::remove_file(file, use_trash.then_some(&mut trash)) {
+                        ui::print_error(&e);
                     }
                 }
             }
@@ -281,13 +478,33 @@ fn review_duplicates(groups: &[duplicates::DuplicateGroup]) {
 }
 
 fn undo_operation() -> bool {
-    let operations = history::get_last_operations(1);
+    let folder = match select_undo_folder() {
+        Ok(f) => f,
+        Err(_) => return ui::prompt_after_action(),
+    };
+
+    let operations = match &folder {
+        Some(f) => history::get_operations_for_folder(f, 1),
+        None => history::get_last_operations(1),
+    };
     if operations.is_empty() {
         ui::print_info("No operations to undo.");
         return ui::prompt_after_action();
     }
 
     let last_op = &operations[0];
+    if last_op.is_unrecorded() {
+        // Consume the marker so the next undo reaches the run before it
+        let result = match &folder {
+            Some(f) => history::undo_last_operation_in_folder(f),
+            None => history::undo_last_operation(),
+        };
+        if let Err(e) = result {
+            ui::print_info(&e);
+        }
+        return ui::prompt_after_action();
+    }
+
     ui::print_info(&format!(
         "Last operation: {} - {} files moved from {}",
         last_op.timestamp,
@@ -295,29 +512,27 @@ fn undo_operation() -> bool {
         last_op.folder
     ));
 
+    // Files moved or deleted since would only fail, so show them before asking
+    let preview = history::preview_undo(last_op);
+    ui::print_undo_preview(&preview);
+    if preview.restorable.is_empty() && last_op.archives.is_empty() {
+        ui::print_warning("None of these files can be restored.");
+    }
+
     if !ui::confirm_with_default("Undo this operation?", false) {
         ui::print_info("Undo cancelled.");
         return ui::prompt_after_action();
     }
 
     let spinner = ui::create_spinner("Undoing operation...");
-    match history::undo_last_operation() {
+    let result = match &folder {
+        Some(f) => history::undo_last_operation_in_folder(f),
+        None => history::undo_last_operation(),
+    };
+    match result {
         Ok(result) => {
             spinner.finish_and_clear();
-            if result.failed == 0 {
-                ui::print_success(&format!(
-                    "Successfully restored {} files from {}",
-                    result.restored, result.operation_time
-                ));
-            } else {
-                ui::print_warning(&format!(
-                    "Restored {} files, {} failed",
-                    result.restored, result.failed
-                ));
-                for error in &result.errors {
-                    ui::print_error(error);
-                }
-            }
+            ui::print_undo_result(&result);
         }
         Err(e) => {
             spinner.finish_and_clear();
@@ -327,6 +542,27 @@ fn undo_operation() -> bool {
     ui::prompt_after_action()
 }
 
+/// Offer to scope undo to one folder when history spans several.
+/// Err means the user backed out.
+fn select_undo_folder() -> Result<Option<String>, ()> {
+    let mut folders: Vec<String> = Vec::new();
+    for op in history::get_last_operations(usize::MAX).iter().rev() {
+        if !folders.contains(&op.folder) {
+            folders.push(op.folder.clone());
+        }
+    }
+
+    if folders.len() < 2 || !ui::confirm_with_default("Undo in a specific folder?", false) {
+        return Ok(None);
+    }
+
+    let items: Vec<&str> = folders.iter().map(String::as_str).collect();
+    match ui::select_from_list("Select folder", &items) {
+        Some(idx) => Ok(Some(folders[idx].clone())),
+        None => Err(()),
+    }
+}
+
 fn show_history() -> bool {
     let operations = history::get_last_operations(10);
     ui::print_history(&operations);
@@ -339,18 +575,21 @@ fn show_history() -> bool {
 
 fn scan_files(
     source_dir: &str,
-    categories: &HashMap<String, Vec<String>>,
+    options: &scanner::ScanOptions,
     org_mode: OrganizationMode,
     recursive: bool,
 ) -> HashMap<String, Vec<PathBuf>> {
     let mut files_map = match org_mode {
-        OrganizationMode::Category => scanner::scan_by_category(source_dir, categories),
-        OrganizationMode::Date => scanner::scan_by_date(source_dir),
-        OrganizationMode::Hybrid => scanner::scan_hybrid(source_dir, categories),
+        OrganizationMode::Category if !options.rules.is_empty() => {
+            scanner::scan_by_rules(source_dir, options)
+        }
+        OrganizationMode::Category => scanner::scan_by_category(source_dir, options),
+        OrganizationMode::Date => scanner::scan_by_date(source_dir, options),
+        OrganizationMode::Hybrid => scanner::scan_hybrid(source_dir, options),
     };
 
     if recursive {
-        let recursive_files = scanner::scan_recursive(source_dir, categories, org_mode.to_index());
+        let recursive_files = scanner::scan_recursive(source_dir, options, org_mode.to_index());
         for (category, files) in recursive_files {
             files_map.entry(category).or_default().extend(files);
         }
@@ -8,8 +8,17 @@ use std::path::PathBuf;
 
 use crate::ui;
 use crate::vault;
-use crate::vault::commands::{format_size, prompt_new_password, prompt_password, resolve_path};
-use crate::vault::storage::SecurityLevel;
+use crate::vault::commands::{
+    format_size, max_folder_bytes, oversized_folder, print_recovery_strength, prompt_new_password,
+    prompt_password, prompt_vault_password, recovery_code_format, resolve_path,
+};
+use crate::vault::crypto::KdfProfile;
+use crate::vault::locker::LockOptions;
+use crate::vault::recovery::DEFAULT_CODE_GROUPS;
+use crate::vault::storage::{self, EntrySort, SecurityLevel, VaultEntry};
+
+/// Above this many entries, extract and destroy ask for a name filter first
+const FILTER_MIN_ENTRIES: usize = 15;
 
 /// Security menu entry point
 pub fn menu(home_dir: &str) {
@@ -46,9 +55,16 @@ fn lock_file_interactive(_home_dir: &str) {
     };
 
     let keep = ui::confirm_with_default("Keep original file?", false);
+    let shred =
+        !keep && ui::confirm_with_default("Shred original (overwrite before delete)?", false);
 
     let spinner = ui::create_spinner("Encrypting (securing with Argon2)...");
-    let result = vault::lock_file(&file_path, &password, keep);
+    let options = LockOptions {
+        keep_original: keep,
+        shred,
+        ..Default::default()
+    };
+    let result = vault::lock_file(&file_path, &password, &options);
     spinner.finish_and_clear();
 
     match result {
@@ -130,7 +146,7 @@ fn vault_init(v: &vault::Vault) {
         None => return,
     };
 
-    match v.init(&password, level) {
+    match v.init(&password, level, KdfProfile::Balanced, DEFAULT_CODE_GROUPS) {
         Ok(Some(codes)) => {
             ui::print_success("Vault initialized!");
             println!();
@@ -139,6 +155,7 @@ fn vault_init(v: &vault::Vault) {
             println!("   Code 1: {}", codes.code1);
             println!("   Code 2: {}", codes.code2);
             println!();
+            print_recovery_strength(&codes);
             ui::print_warning("Both codes required. NOT shown again.");
         }
         Ok(None) => {
@@ -160,12 +177,26 @@ fn vault_add(v: &vault::Vault) {
         None => return,
     };
 
-    let password = match prompt_password("Vault password: ") {
+    if let Some(size) = oversized_folder(&file_path, max_folder_bytes()) {
+        ui::print_warning(&format!(
+            "This folder is about {} and is archived in memory.",
+            format_size(size)
+        ));
+        if !ui::confirm_with_default("Add it anyway?", false) {
+            return;
+        }
+    }
+
+    let password = match prompt_vault_password(v) {
         Some(p) => p,
         None => return,
     };
 
-    match v.add(&file_path, &password) {
+    let keep = ui::confirm_with_default("Keep the original (store a backup copy)?", false);
+    let shred =
+        !keep && ui::confirm_with_default("Shred original (overwrite before delete)?", false);
+
+    match v.add(&file_path, &password, shred, keep, None) {
         Ok(entry) => {
             ui::print_success(&format!("Added: {} ({} bytes)", entry.name, entry.size));
         }
@@ -174,18 +205,19 @@ fn vault_add(v: &vault::Vault) {
 }
 
 fn vault_list(v: &vault::Vault) {
-    let password = match prompt_password("Vault password: ") {
+    let password = match prompt_vault_password(v) {
         Some(p) => p,
         None => return,
     };
 
     match v.list(&password) {
-        Ok(entries) => {
+        Ok(mut entries) => {
+            storage::sort_entries(&mut entries, EntrySort::Name);
             if entries.is_empty() {
                 ui::print_info("Vault is empty");
             } else {
                 println!();
-                println!("{:<30} {:>12} {}", "NAME", "SIZE", "ADDED");
+                println!("{:<30} {:>12} ADDED", "NAME", "SIZE");
                 println!("{}", "-".repeat(60));
                 for entry in entries {
                     let size = format_size(entry.size);
@@ -204,28 +236,13 @@ fn vault_list(v: &vault::Vault) {
 }
 
 fn vault_extract(v: &vault::Vault) {
-    let password = match prompt_password("Vault password: ") {
+    let password = match prompt_vault_password(v) {
         Some(p) => p,
         None => return,
     };
 
-    let entries = match v.list(&password) {
-        Ok(e) => e,
-        Err(e) => {
-            ui::print_error(&format!("{}", e));
-            return;
-        }
-    };
-
-    if entries.is_empty() {
-        ui::print_info("Vault is empty");
+    let Some(entry) = select_entry(v, &password, "Select file to extract") else {
         return;
-    }
-
-    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
-    let idx = match ui::select_from_list("Select file to extract", &names) {
-        Some(i) => i,
-        None => return,
     };
 
     let dest = match ui::input_file_path("Extract to (directory)") {
@@ -236,7 +253,7 @@ fn vault_extract(v: &vault::Vault) {
         None => PathBuf::from("."),
     };
 
-    match v.extract(&entries[idx].name, &password, &dest) {
+    match v.extract(&entry.name, &password, &dest) {
         Ok(path) => {
             ui::print_success(&format!("Extracted: {}", path.display()));
         }
@@ -245,45 +262,64 @@ fn vault_extract(v: &vault::Vault) {
 }
 
 fn vault_destroy(v: &vault::Vault) {
-    let password = match prompt_password("Vault password: ") {
+    let password = match prompt_vault_password(v) {
         Some(p) => p,
         None => return,
     };
 
-    let entries = match v.list(&password) {
+    let Some(entry) = select_entry(v, &password, "Select file to destroy") else {
+        return;
+    };
+
+    ui::print_warning(&format!("This will PERMANENTLY delete '{}'", entry.name));
+    if !ui::confirm_with_default("Continue?", false) {
+        return;
+    }
+
+    match v.destroy(&entry.name, &password) {
+        Ok(()) => {
+            ui::print_success(&format!("Destroyed: {}", entry.name));
+        }
+        Err(e) => ui::print_error(&format!("{}", e)),
+    }
+}
+
+/// Pick an entry from a list; large vaults are first narrowed down by name
+fn select_entry(v: &vault::Vault, password: &str, prompt: &str) -> Option<VaultEntry> {
+    let mut entries = match v.list(password) {
         Ok(e) => e,
         Err(e) => {
             ui::print_error(&format!("{}", e));
-            return;
+            return None;
         }
     };
 
     if entries.is_empty() {
         ui::print_info("Vault is empty");
-        return;
-    }
-
-    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
-    let idx = match ui::select_from_list("Select file to destroy", &names) {
-        Some(i) => i,
-        None => return,
-    };
-
-    ui::print_warning(&format!("This will PERMANENTLY delete '{}'", names[idx]));
-    if !ui::confirm_with_default("Continue?", false) {
-        return;
+        return None;
     }
 
-    match v.destroy(&entries[idx].name, &password) {
-        Ok(()) => {
-            ui::print_success(&format!("Destroyed: {}", names[idx]));
+    if entries.len() > FILTER_MIN_ENTRIES {
+        if let Some(query) = ui::input_optional("Filter by name (empty for all)") {
+            entries = storage::filter_entries(entries, &query);
+            if entries.is_empty() {
+                ui::print_info(&format!("No entry name contains '{}'", query));
+                return None;
+            }
         }
-        Err(e) => ui::print_error(&format!("{}", e)),
     }
+    storage::sort_entries(&mut entries, EntrySort::Name);
+
+    let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    let idx = ui::select_from_list(prompt, &names)?;
+    Some(entries.swap_remove(idx))
 }
 
 fn vault_recover(v: &vault::Vault) {
-    ui::print_info("Enter recovery codes:");
+    ui::print_info(&format!(
+        "Enter recovery codes ({}):",
+        recovery_code_format(v)
+    ));
 
     let code1 = match ui::input_text("Code 1") {
         Some(c) => c,
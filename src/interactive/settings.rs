@@ -2,9 +2,10 @@
 // @musem23
 //
 // Interactive menu for managing categories, organization mode,
-// rename mode, and saving configuration.
+// rename mode, date granularity, and saving configuration.
 
 use crate::config::{self, Config};
+use crate::modes::DateGranularity;
 use crate::ui;
 
 /// Settings menu entry point
@@ -13,6 +14,10 @@ pub fn menu(config: &mut Config) {
         let choice = match ui::select_settings_menu(
             config.preferences.organization_mode,
             config.preferences.rename_mode,
+            config
+                .preferences
+                .date_granularity
+                .map(DateGranularity::to_index),
         ) {
             Some(c) => c,
             None => return,
@@ -25,7 +30,8 @@ pub fn menu(config: &mut Config) {
             3 => remove_category(config),
             4 => update_org_mode(config),
             5 => update_rename_mode(config),
-            6 => save_config(config),
+            6 => update_date_granularity(config),
+            7 => save_config(config),
             _ => return,
         }
     }
@@ -75,9 +81,24 @@ fn update_rename_mode(config: &mut Config) {
     }
 }
 
+fn update_date_granularity(config: &mut Config) {
+    let current = config
+        .preferences
+        .date_granularity
+        .map(DateGranularity::to_index)
+        .unwrap_or(1);
+    if let Some(idx) = ui::select_date_granularity(current) {
+        config.preferences.date_granularity = Some(DateGranularity::from_index(idx));
+        ui::print_success("Date granularity updated");
+    }
+}
+
 fn save_config(config: &Config) {
     match config::save_config(config) {
-        Ok(_) => ui::print_success("Config saved to ~/.config/stellar/stellar.toml"),
+        Ok(_) => ui::print_success(&format!(
+            "Config saved to {}",
+            config::save_path().display()
+        )),
         Err(e) => ui::print_error(&e),
     }
 }
@@ -0,0 +1,21 @@
+// Stellar - Library
+// @musem23
+//
+// Organization engine without the terminal layer: scanning, renaming,
+// moving with undo history, and duplicate detection.
+// Functions return results instead of printing; progress goes through a
+// ProgressReporter. The `stellar` binary is a thin wrapper around it.
+
+pub mod archive;
+pub mod config;
+pub mod duplicates;
+pub mod filesystem;
+pub mod history;
+pub mod lock;
+pub mod modes;
+pub mod organizer;
+pub mod progress;
+pub mod renamer;
+pub mod scanner;
+pub mod stats;
+pub mod trash;
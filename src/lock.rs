@@ -4,11 +4,23 @@
 // Prevents multiple Stellar instances from operating on the same folder.
 // Creates a .stellar.lock file with an exclusive lock.
 // Lock is automatically released when FolderLock is dropped.
+// acquire_timeout() waits for a busy folder instead of failing right away.
 
 use fs2::FileExt;
 use std::fs::{self, File, OpenOptions};
 use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Name of the lock file created inside the organized folder
+pub const LOCK_FILE_NAME: &str = ".stellar.lock";
+
+const BUSY_MESSAGE: &str = "Another Stellar instance is already operating on this folder.";
+
+/// Retry delays while waiting for a busy folder: doubles up to the maximum
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
 
 pub struct FolderLock {
     _file: File,
@@ -18,7 +30,32 @@ pub struct FolderLock {
 impl FolderLock {
     /// Try to acquire an exclusive lock on a folder
     pub fn acquire(folder_path: &str) -> Result<Self, String> {
-        let path = PathBuf::from(folder_path).join(".stellar.lock");
+        Self::try_acquire(folder_path)?.ok_or_else(|| BUSY_MESSAGE.to_string())
+    }
+
+    /// Like `acquire`, but retry with backoff while another instance holds the lock
+    pub fn acquire_timeout(folder_path: &str, timeout: Duration) -> Result<Self, String> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            if let Some(lock) = Self::try_acquire(folder_path)? {
+                return Ok(lock);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(BUSY_MESSAGE.to_string());
+            }
+            thread::sleep(backoff.min(deadline - now));
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// None when the folder is locked by someone else.
+    /// The file is reopened on each attempt since the holder deletes it on release.
+    fn try_acquire(folder_path: &str) -> Result<Option<Self>, String> {
+        let path = PathBuf::from(folder_path).join(LOCK_FILE_NAME);
 
         let file = OpenOptions::new()
             .write(true)
@@ -27,15 +64,11 @@ impl FolderLock {
             .open(&path)
             .map_err(|e| format!("Cannot create lock file: {}", e))?;
 
-        file.try_lock_exclusive().map_err(|e| {
-            if e.kind() == ErrorKind::WouldBlock {
-                "Another Stellar instance is already operating on this folder.".to_string()
-            } else {
-                format!("Failed to acquire lock: {}", e)
-            }
-        })?;
-
-        Ok(FolderLock { _file: file, path })
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(FolderLock { _file: file, path })),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(format!("Failed to acquire lock: {}", e)),
+        }
     }
 }
 
@@ -2,30 +2,34 @@
 // @musem23
 //
 // Entry point for the Stellar application.
-// Routes CLI arguments to appropriate modules.
+// Routes CLI arguments to the library (lib.rs) and the terminal modules.
 // Interactive mode is handled by the interactive module.
 
-mod config;
-mod duplicates;
-mod history;
 mod interactive;
-mod lock;
-mod modes;
-mod organizer;
-mod renamer;
-mod scanner;
-mod stats;
 #[cfg(test)]
 mod tests;
 mod ui;
 mod vault;
 mod watcher;
 
-use clap::{Parser, Subcommand};
-use modes::{OrganizationMode, RenameMode};
-use std::path::Path;
+use stellar_org::{
+    archive, config, duplicates, history, lock, modes, organizer, progress, renamer, scanner,
+    stats, trash,
+};
+
+use clap::{Args, Parser, Subcommand};
+use modes::{
+    ConflictPolicy, ConflictSuffix, DateSource, GroupOrder, KeepRule, OrganizationMode, RenameMode,
+    SymlinkPolicy,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use vault::commands::{resolve_path, VaultAction};
-use vault::storage::SecurityLevel;
+use vault::crypto::KdfProfile;
+use vault::locker::LockOptions;
+use vault::recovery::{DEFAULT_CODE_GROUPS, MAX_CODE_GROUPS};
+use vault::storage::{EntrySort, SecurityLevel};
 
 #[derive(Parser)]
 #[command(name = "stellar")]
@@ -41,54 +45,378 @@ use vault::storage::SecurityLevel;
 #[command(after_help = "EXAMPLES:\n    \
     stellar ~/Downloads              Organize by category (interactive)\n    \
     stellar ~/Downloads -m date      Organize by date\n    \
+    stellar organize ~/Downloads -m date   Same, as a subcommand (alias: move)\n    \
     stellar ~/Downloads -m hybrid    Organize by category/year\n    \
     stellar ~/Downloads -R           Include subdirectories\n    \
+    stellar ~/Downloads ~/Desktop    Organize several folders\n    \
+    stellar ~/Downloads --dest ~/Sorted   Organize into another folder\n    \
     stellar ~/Downloads --dry-run    Preview without changes\n    \
-    stellar ~/Downloads --watch      Auto-organize new files")]
+    stellar ~/Downloads -d --format json   Preview as JSON\n    \
+    stellar ~/Downloads --interactive-review   Untick files before moving\n    \
+    stellar ~/Downloads --watch      Auto-organize new files\n    \
+    stellar init                     Write a starter config\n    \
+    stellar config check             Validate the config\n    \
+    stellar --config work.toml ~/Downloads   Use another config file\n    \
+    stellar categories add Work pdf,docx   Add a category\n    \
+    stellar stats ~/Downloads        Show folder composition\n    \
+    stellar rename-preview ~/Downloads   Preview renamed file names\n    \
+    stellar duplicates ~/Downloads -d   Preview duplicate removal\n    \
+    stellar undo --count 3           Reverse the last three operations\n    \
+    stellar history --folder ~/Downloads   Operations in one folder")]
 struct Cli {
-    /// Path to the folder to organize (interactive mode if omitted)
+    /// Folders to organize, one after the other (interactive mode if omitted)
     #[arg(value_name = "FOLDER")]
-    folder: Option<String>,
+    folders: Vec<String>,
+
+    #[command(flatten)]
+    organize: OrganizeArgs,
+
+    /// Colored output: auto (terminal only, off when NO_COLOR is set), always or never
+    #[arg(long, global = true, default_value = "auto", value_parser = ["auto", "always", "never"])]
+    color: String,
+
+    /// Use this config file instead of ./stellar.toml or ~/.config/stellar/stellar.toml
+    /// (it must exist and be valid)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Interactive mode: do not clear the screen or draw the logo
+    #[arg(long)]
+    no_banner: bool,
+
+    /// Only print errors (ignored in interactive mode)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Answer yes to every prompt (ignored in interactive mode).
+    /// With `duplicates`, files are removed without review
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Subcommand
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
 
+/// Options for organizing folders, shared by `stellar <FOLDER>...` and `stellar organize`
+#[derive(Args)]
+struct OrganizeArgs {
     /// Organization mode
     #[arg(short, long, default_value = "category", value_parser = ["category", "date", "hybrid"])]
     mode: String,
 
     /// Rename mode
-    #[arg(short, long, default_value = "clean", value_parser = ["clean", "date-prefix", "skip"])]
+    #[arg(short, long, default_value = "clean", value_parser = ["clean", "snake", "title", "date-prefix", "skip"])]
     rename: String,
 
+    /// Rename template, e.g. "{date}_{category}_{name}.{ext}" (overrides --rename)
+    /// Placeholders: {name}, {ext}, {date}, {year}, {category}
+    #[arg(long, value_name = "TEMPLATE")]
+    rename_template: Option<String>,
+
+    /// Timestamp used for date organization and renaming (default from config: created)
+    #[arg(long, value_parser = ["created", "modified"])]
+    date_source: Option<String>,
+
+    /// Move organized folders into this directory instead of the source folder
+    #[arg(long, visible_alias = "move-to", value_name = "DIR")]
+    dest: Option<String>,
+
+    /// Put this run's files in a date-time subfolder of each category folder
+    /// (Documents/2024-01-15-1430/), so runs never mix
+    #[arg(long)]
+    run_subfolder: bool,
+
+    /// Only move files whose destination folder (Documents, 2024/01-january...)
+    /// already exists; the others are left in place and listed as skipped
+    #[arg(long)]
+    sort_into_existing_only: bool,
+
+    /// What to do when a file already exists at the destination
+    #[arg(long, default_value = "rename", value_parser = ["rename", "skip", "overwrite", "keep-newer"])]
+    on_conflict: String,
+
+    /// How renamed copies are named with --on-conflict rename (default from config: dash-number)
+    #[arg(long, value_parser = ["dash-number", "paren-number", "underscore-number", "timestamp"])]
+    conflict_suffix: Option<String>,
+
+    /// Delete files whose content already exists at the destination instead of
+    /// moving them (hashes both files on conflict; deletions cannot be undone)
+    #[arg(long)]
+    dedupe_on_move: bool,
+
+    /// Move files with N worker threads (helps with slow or cross-device moves)
+    #[arg(short, long, default_value_t = 1, value_name = "N")]
+    jobs: usize,
+
+    /// Move every file from the subfolders up into the folder itself (no categories)
+    #[arg(long, conflicts_with_all = ["watch", "recursive"])]
+    flatten: bool,
+
+    /// With --flatten, remove the subfolders left empty
+    #[arg(long, requires = "flatten")]
+    prune_empty: bool,
+
+    /// With --recursive, remove the subfolders the run left empty (never the folder itself)
+    #[arg(long, requires = "recursive", conflicts_with = "watch")]
+    clean_empty: bool,
+
+    /// After organizing, compress each top-level destination folder into
+    /// <folder>.tar.gz next to it (undo unpacks it again)
+    #[arg(long, conflicts_with_all = ["watch", "flatten"])]
+    archive: bool,
+
+    /// With --archive, delete the folders once they are archived
+    #[arg(long, requires = "archive")]
+    archive_remove: bool,
+
+    /// Hash cross-device copies before deleting the originals (slower, safer)
+    #[arg(long)]
+    verify_moves: bool,
+
+    /// Do not record this run in the undo history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Append a JSON line with the run's statistics (and skipped files) to this file
+    #[arg(long, value_name = "FILE")]
+    report: Option<String>,
+
     /// Scan subdirectories recursively
     #[arg(short = 'R', long)]
     recursive: bool,
 
+    /// Move each subfolder as a whole into the Folders category (category mode)
+    #[arg(long, conflicts_with_all = ["recursive", "flatten", "watch"])]
+    with_folders: bool,
+
+    /// Symbolic links: leave them (skip), move the link itself (move-link),
+    /// or organize a copy of the file they point to (follow)
+    #[arg(long, visible_alias = "follow-symlinks", default_value = "skip", value_parser = ["skip", "move-link", "follow"])]
+    symlinks: String,
+
+    /// Organize dotfiles too (.DS_Store, .localized and the lock file are still skipped)
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// With --recursive, also descend into hidden folders (project folders stay skipped)
+    #[arg(long, requires = "recursive")]
+    include_hidden_dirs: bool,
+
+    /// Classify by content (magic bytes) when it disagrees with the extension or there is none
+    #[arg(long)]
+    sniff: bool,
+
+    /// Wait up to N seconds for another Stellar instance to release the folder
+    #[arg(long, value_name = "SECS")]
+    lock_wait: Option<u64>,
+
+    /// With --recursive, only descend this many folder levels (0 = top folder only)
+    #[arg(long, requires = "recursive", value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Ask before moving anything when the scan finds more than N files
+    /// (--yes and --force organize them anyway)
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Only organize files at least this old: 12h, 30d, 2w
+    #[arg(long, value_name = "AGE", value_parser = stats::parse_age)]
+    older_than: Option<chrono::TimeDelta>,
+
+    /// Only organize files at most this old (combine with --older-than for a window)
+    #[arg(long, value_name = "AGE", value_parser = stats::parse_age)]
+    newer_than: Option<chrono::TimeDelta>,
+
+    /// Organize even if the folder looks like a project (.git, package.json...),
+    /// already organized (recursive runs) or holds more than --max-files files
+    #[arg(long)]
+    force: bool,
+
     /// Preview changes without applying them
     #[arg(short, long)]
     dry_run: bool,
 
+    /// Output format for --dry-run (json prints the preview to stdout)
+    #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+
+    /// With --dry-run, how many planned moves to list (0 = all)
+    #[arg(long, value_name = "N", default_value_t = ui::DEFAULT_PREVIEW_LIMIT)]
+    preview_limit: usize,
+
+    /// Go through the planned moves and untick the files to leave where they are
+    #[arg(long, conflicts_with_all = ["dry_run", "watch"])]
+    interactive_review: bool,
+
     /// Watch folder and auto-organize new files
     #[arg(short, long)]
     watch: bool,
 
-    /// Subcommand
-    #[command(subcommand)]
-    command: Option<Commands>,
+    /// With --watch, organize the files already in the folder before watching
+    #[arg(long, requires = "watch")]
+    sweep: bool,
+
+    /// With --watch, organize the files already in the folder, then exit
+    #[arg(long, requires = "watch", conflicts_with = "sweep")]
+    once: bool,
+
+    /// What the progress bar counts: files, bytes (ETA follows data volume),
+    /// or auto (bytes once the files add up to 100MB)
+    #[arg(long, default_value = "auto", value_parser = ["auto", "files", "bytes"])]
+    progress: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Organize folders (same as `stellar <FOLDER>...`)
+    #[command(visible_alias = "move")]
+    Organize {
+        /// Folders to organize, one after the other
+        #[arg(value_name = "FOLDER", required = true)]
+        folders: Vec<String>,
+        #[command(flatten)]
+        options: Box<OrganizeArgs>,
+    },
+    /// Write a starter stellar.toml to ~/.config/stellar (or ./ with --local)
+    Init {
+        /// Write ./stellar.toml in the current directory
+        #[arg(long)]
+        local: bool,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    /// List or edit the categories in the config file
+    Categories {
+        #[command(subcommand)]
+        action: CategoriesCommands,
+    },
+    /// Report folder composition without moving anything
+    Stats {
+        /// Folder to analyze
+        folder: String,
+        /// Include subdirectories
+        #[arg(short = 'R', long)]
+        recursive: bool,
+    },
+    /// Show how files would be renamed (original -> new name) without moving anything
+    RenamePreview {
+        /// Folder whose files are listed
+        folder: String,
+        /// Rename mode
+        #[arg(short, long, default_value = "clean", value_parser = ["clean", "snake", "title", "date-prefix"])]
+        rename: String,
+        /// Rename template, e.g. "{date}_{category}_{name}.{ext}" (overrides --rename)
+        #[arg(long, value_name = "TEMPLATE")]
+        rename_template: Option<String>,
+        /// Timestamp used by date-prefix and {date} (default from config: created)
+        #[arg(long, value_parser = ["created", "modified"])]
+        date_source: Option<String>,
+        /// Include subdirectories
+        #[arg(short = 'R', long)]
+        recursive: bool,
+    },
+    /// Find duplicate files in a folder (exit status 1 when some are found,
+    /// 2 on errors), and remove the extra copies with --delete
+    Duplicates {
+        /// Folder to search
+        folder: String,
+        /// Only report what would be removed (the default without --delete)
+        #[arg(short, long, conflicts_with = "delete")]
+        dry_run: bool,
+        /// Remove the extra copies (to the trash unless --permanent)
+        #[arg(long)]
+        delete: bool,
+        /// With --delete, delete permanently instead of moving to the trash
+        #[arg(long, requires = "delete")]
+        permanent: bool,
+        /// Which copy to keep in each group
+        #[arg(long, default_value = "shallowest", value_parser = ["oldest", "newest", "shallowest", "shortest-name"])]
+        keep: String,
+        /// Group order, biggest first: wasted space (size x extra copies), size or copies
+        #[arg(long, default_value = "wasted", value_parser = ["size", "count", "wasted"])]
+        sort_by: String,
+        /// Include subdirectories
+        #[arg(short = 'R', long)]
+        recursive: bool,
+        /// Ignore files smaller than this (e.g. 500KB, 10MB, 1GB); empty files are always ignored
+        #[arg(long, value_name = "SIZE", value_parser = stats::parse_size, default_value = "1")]
+        min_size: u64,
+        /// Report visually similar images (resized or re-encoded copies) instead;
+        /// nothing is removed, so the options that choose and remove copies do not apply
+        #[arg(long, conflicts_with_all = ["delete", "permanent", "keep", "sort_by"])]
+        similar: bool,
+        /// Maximum differing bits (out of 64) for --similar
+        #[arg(long, default_value_t = 10, requires = "similar")]
+        threshold: u32,
+        /// Output format (json prints the groups to stdout; it cannot remove copies)
+        #[arg(
+            long,
+            default_value = "text",
+            value_parser = ["text", "json"],
+            conflicts_with_all = ["similar", "delete", "permanent"]
+        )]
+        format: String,
+    },
+    /// Undo the most recent organize operations
+    Undo {
+        /// Number of operations to reverse, newest first
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+    },
+    /// Show recent organize operations
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryCommands>,
+        /// Only show operations in this folder (or its subfolders)
+        #[arg(long)]
+        folder: Option<String>,
+        /// Number of operations to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+    },
+    /// Restore files removed to the trash
+    Trash {
+        #[command(subcommand)]
+        action: TrashCommands,
+    },
     /// Lock a file in place (encrypt)
     Lock {
-        /// File to lock
+        /// File to lock, or a directory to lock file by file in place
         file: String,
         /// Keep the original file
         #[arg(short, long)]
         keep: bool,
+        /// Overwrite the original with random bytes before deleting it
+        /// (best-effort on SSDs and copy-on-write filesystems)
+        #[arg(long, conflicts_with = "keep")]
+        shred: bool,
+        /// For a directory, also lock the files of its subdirectories
+        #[arg(short = 'R', long)]
+        recursive: bool,
+        /// Write the .stlr files to this directory instead of next to the originals
+        #[arg(long, value_name = "DIR")]
+        dest: Option<String>,
+        /// Give the .stlr file a random name; the real name is stored encrypted inside
+        #[arg(long)]
+        hide_name: bool,
+        /// List the files that would be locked and their .stlr paths, without locking
+        #[arg(short, long)]
+        dry_run: bool,
     },
     /// Unlock a .stlr file (decrypt)
     Unlock {
-        /// File to unlock
+        /// File to unlock, or a directory whose .stlr files are unlocked in place
         file: String,
+        /// For a directory, also unlock the files of its subdirectories
+        #[arg(short = 'R', long)]
+        recursive: bool,
     },
     /// Vault commands (centralized secure storage)
     Vault {
@@ -97,6 +425,55 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Validate the active config and report extensions claimed by several categories
+    Check,
+}
+
+#[derive(Subcommand)]
+enum CategoriesCommands {
+    /// Show every category with its extensions
+    List,
+    /// Add extensions to a category, creating it if needed
+    Add {
+        /// Category (folder) name
+        name: String,
+        /// Extensions, comma or space separated: pdf,docx,xlsx
+        #[arg(required = true, value_delimiter = ',')]
+        extensions: Vec<String>,
+    },
+    /// Remove a category
+    Remove {
+        /// Category (folder) name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// Remove old operations, or those that can no longer be undone
+    Prune {
+        /// Remove operations older than this: 12h, 30d, 2w
+        #[arg(long, value_name = "AGE", value_parser = stats::parse_age, required_unless_present = "missing")]
+        older_than: Option<chrono::TimeDelta>,
+        /// Remove operations whose moved files (and archives) are all gone
+        #[arg(long)]
+        missing: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommands {
+    /// List trash batches
+    List,
+    /// Restore a batch to its original paths (the most recent if omitted)
+    Restore {
+        /// Batch id shown by `stellar trash list`
+        batch: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum VaultCommands {
     /// Initialize a new vault
@@ -104,15 +481,61 @@ enum VaultCommands {
         /// Security level: standard (with recovery) or maximum (no recovery)
         #[arg(short, long, default_value = "standard", value_parser = ["standard", "maximum"])]
         level: String,
+        /// Key derivation cost: interactive (fast), balanced, or paranoid (slow, 256MB)
+        #[arg(long, default_value = "balanced", value_parser = ["interactive", "balanced", "paranoid"])]
+        profile: String,
+        /// Groups of 4 characters per recovery code: 3 gives 120 bits for both
+        /// codes, 7 the full 256 bits of the vault key
+        #[arg(long, default_value_t = DEFAULT_CODE_GROUPS as u8, value_name = "N",
+              value_parser = clap::value_parser!(u8).range(3..=MAX_CODE_GROUPS as i64))]
+        recovery_groups: u8,
     },
     /// Add files to the vault
     Add {
         /// Files or directories to add
         #[arg(required = true)]
         files: Vec<String>,
+        /// Keep the originals: the vault holds an encrypted backup
+        #[arg(short, long)]
+        keep: bool,
+        /// Overwrite the originals with random bytes before deleting them
+        /// (best-effort on SSDs and copy-on-write filesystems)
+        #[arg(long, conflicts_with = "keep")]
+        shred: bool,
+        /// Store the file under this name instead of its own (single file only)
+        #[arg(long = "as", value_name = "NAME", conflicts_with = "per_file")]
+        name: Option<String>,
+        /// Add each file of a directory as its own entry instead of one archive
+        #[arg(long)]
+        per_file: bool,
+        /// With --per-file, also add the files of subdirectories
+        #[arg(short = 'R', long, requires = "per_file")]
+        recursive: bool,
+        /// List the entries that would be added (names checked against the
+        /// vault), without encrypting or deleting anything
+        #[arg(short, long)]
+        dry_run: bool,
+        /// Add folders larger than [vault] max_folder_size (default 500MB) as
+        /// one archive anyway; the archive is built in memory
+        #[arg(long)]
+        allow_large: bool,
     },
     /// List vault contents
-    List,
+    List {
+        /// Order: name, size (largest first) or date added (newest first)
+        #[arg(long, default_value = "name", value_parser = ["name", "size", "date"])]
+        sort: String,
+        /// Print the entries as JSON (name, size, added_at, is_directory...)
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the entries whose name contains a text (case-insensitive)
+    Find {
+        /// Part of the name to look for
+        query: String,
+    },
+    /// Show entry counts, original size and on-disk size of the vault
+    Stats,
     /// Extract a file from the vault
     Extract {
         /// Name of the file to extract
@@ -121,6 +544,21 @@ enum VaultCommands {
         #[arg(short, long, default_value = ".")]
         dest: String,
     },
+    /// View a file without keeping a decrypted copy (shredded when you are done)
+    Open {
+        /// Name of the file to open
+        name: String,
+        /// Shred the decrypted copy after this many minutes even without a keypress
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Rename a file stored in the vault
+    Rename {
+        /// Current name of the file
+        old_name: String,
+        /// New name for the file
+        new_name: String,
+    },
     /// Permanently remove a file from the vault
     Destroy {
         /// Name of the file to destroy
@@ -133,17 +571,141 @@ enum VaultCommands {
 fn main() {
     let cli = Cli::parse();
 
+    // Quiet and yes are meant for scripts; the interactive menus always ask and talk
+    if !cli.folders.is_empty() || cli.command.is_some() {
+        ui::set_quiet(cli.quiet);
+        ui::set_assume_yes(cli.yes);
+    }
+    ui::set_color_mode(match cli.color.as_str() {
+        "always" => ui::ColorMode::Always,
+        "never" => ui::ColorMode::Never,
+        _ => ui::ColorMode::Auto,
+    });
+    ui::set_no_banner(cli.no_banner);
+    let progress = match &cli.command {
+        Some(Commands::Organize { options, .. }) => &options.progress,
+        _ => &cli.organize.progress,
+    };
+    ui::set_progress_unit(match progress.as_str() {
+        "files" => ui::ProgressUnit::Files,
+        "bytes" => ui::ProgressUnit::Bytes,
+        _ => ui::ProgressUnit::Auto,
+    });
+
+    if let Some(path) = &cli.config {
+        config::set_config_path(path.clone());
+        // `init` creates the file; every other command needs it now, without fallback
+        if !matches!(cli.command, Some(Commands::Init { .. })) {
+            if let Err(e) = config::read_config() {
+                ui::print_error(&e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if matches!(
+        cli.command,
+        None | Some(Commands::Organize { .. } | Commands::Undo { .. } | Commands::History { .. })
+    ) {
+        resolve_pending_runs();
+    }
+
     // Handle subcommands first
     if let Some(cmd) = cli.command {
         match cmd {
-            Commands::Lock { file, keep } => vault::commands::run_lock(&file, keep),
-            Commands::Unlock { file } => vault::commands::run_unlock(&file),
+            Commands::Organize { folders, options } => match load_config() {
+                Ok(config) => run_cli_folders(&config, &folders, &options),
+                Err(e) => ui::print_error(&format!("Failed to load config: {}", e)),
+            },
+            Commands::Init { local, force } => run_init(local, force),
+            Commands::Config {
+                action: ConfigCommands::Check,
+            } => run_config_check(),
+            Commands::Categories { action } => run_categories(action),
+            Commands::Stats { folder, recursive } => run_stats(&folder, recursive),
+            Commands::RenamePreview {
+                folder,
+                rename,
+                rename_template,
+                date_source,
+                recursive,
+            } => run_rename_preview(
+                &folder,
+                &rename,
+                rename_template,
+                date_source.as_deref(),
+                recursive,
+            ),
+            Commands::Duplicates {
+                folder,
+                dry_run: _,
+                delete,
+                permanent,
+                keep,
+                sort_by,
+                recursive,
+                min_size,
+                similar,
+                threshold,
+                format,
+            } => {
+                let scan = DuplicateScan {
+                    folder,
+                    recursive,
+                    min_size,
+                };
+                if similar {
+                    run_similar_images(&scan, threshold);
+                } else {
+                    run_duplicates(
+                        &scan,
+                        delete,
+                        permanent,
+                        KeepRule::from_str(&keep),
+                        GroupOrder::from_str(&sort_by),
+                        format == "json",
+                    );
+                }
+            }
+            Commands::Undo { count } => run_undo(count),
+            Commands::History {
+                action:
+                    Some(HistoryCommands::Prune {
+                        older_than,
+                        missing,
+                    }),
+                ..
+            } => run_history_prune(older_than, missing),
+            Commands::History {
+                action: None,
+                folder,
+                count,
+            } => run_history(folder.as_deref(), count),
+            Commands::Trash { action } => run_trash(action),
+            Commands::Lock {
+                file,
+                keep,
+                shred,
+                recursive,
+                dest,
+                hide_name,
+                dry_run,
+            } => {
+                let options = LockOptions {
+                    keep_original: keep,
+                    shred,
+                    dest: dest.as_deref().and_then(resolve_path).map(PathBuf::from),
+                    hide_name,
+                };
+                vault::commands::run_lock(&file, &options, recursive, dry_run)
+            }
+            Commands::Unlock { file, recursive } => vault::commands::run_unlock(&file, recursive),
             Commands::Vault { action } => vault::commands::run_vault(convert_vault_action(action)),
         }
         return;
     }
 
-    let config = match config::load_config() {
+    let config = match load_config() {
         Ok(c) => c,
         Err(e) => {
             ui::print_error(&format!("Failed to load config: {}", e));
@@ -151,105 +713,900 @@ fn main() {
         }
     };
 
-    if let Some(ref folder_path) = cli.folder {
-        run_cli_mode(&config, folder_path, &cli);
-    } else {
+    if cli.folders.is_empty() {
         interactive::run(config);
+    } else {
+        run_cli_folders(&config, &cli.folders, &cli.organize);
     }
 }
 
 fn convert_vault_action(cmd: VaultCommands) -> VaultAction {
     match cmd {
-        VaultCommands::Init { level } => VaultAction::Init {
+        VaultCommands::Init {
+            level,
+            profile,
+            recovery_groups,
+        } => VaultAction::Init {
             level: if level == "maximum" {
                 SecurityLevel::Maximum
             } else {
                 SecurityLevel::Standard
             },
+            profile: match profile.as_str() {
+                "interactive" => KdfProfile::Interactive,
+                "paranoid" => KdfProfile::Paranoid,
+                _ => KdfProfile::Balanced,
+            },
+            recovery_groups: usize::from(recovery_groups),
+        },
+        VaultCommands::Add {
+            files,
+            keep,
+            shred,
+            name,
+            per_file,
+            recursive,
+            dry_run,
+            allow_large,
+        } => VaultAction::Add {
+            files,
+            shred,
+            keep,
+            name,
+            per_file,
+            recursive,
+            dry_run,
+            allow_large,
         },
-        VaultCommands::Add { files } => VaultAction::Add { files },
-        VaultCommands::List => VaultAction::List,
+        VaultCommands::List { sort, json } => VaultAction::List {
+            sort: EntrySort::from_str(&sort),
+            json,
+        },
+        VaultCommands::Find { query } => VaultAction::Find { query },
+        VaultCommands::Stats => VaultAction::Stats,
         VaultCommands::Extract { name, dest } => VaultAction::Extract { name, dest },
+        VaultCommands::Open { name, timeout } => VaultAction::Open {
+            name,
+            timeout_minutes: timeout,
+        },
+        VaultCommands::Rename { old_name, new_name } => VaultAction::Rename { old_name, new_name },
         VaultCommands::Destroy { name } => VaultAction::Destroy { name },
         VaultCommands::Recover => VaultAction::Recover,
     }
 }
 
-fn run_cli_mode(config: &config::Config, folder_path: &str, cli: &Cli) {
+/// Organize one folder; the stats are None when nothing was organized
+/// (invalid folder, lock held, cancelled, dry-run or watch)
+fn run_cli_mode(
+    config: &config::Config,
+    folder_path: &str,
+    args: &OrganizeArgs,
+) -> Option<stats::OrganizationStats> {
     let source_dir = match resolve_path(folder_path) {
         Some(p) => p,
         None => {
             ui::print_error(&format!("Invalid path: {}", folder_path));
-            return;
+            return None;
         }
     };
 
     if !Path::new(&source_dir).is_dir() {
         ui::print_error(&format!("Not a directory: {}", source_dir));
-        return;
+        return None;
     }
 
-    if scanner::is_project_folder(&source_dir) {
-        ui::print_error("This is a project folder (contains .git, package.json, etc.). Aborting.");
-        return;
+    if !args.force
+        && scanner::is_project_folder(&source_dir, &scanner::project_indicators(&config.protected))
+    {
+        ui::print_error(
+            "This is a project folder (contains .git, package.json, etc.). Aborting (use --force to override).",
+        );
+        return None;
     }
 
-    if cli.watch {
-        let rename_mode = RenameMode::from_str(&cli.rename);
-        ui::print_info(&format!("Watching folder: {}", source_dir));
-        watcher::watch_folder(&source_dir, &config.categories, rename_mode.map(Into::into));
-        return;
+    // Re-running recursively on an organized folder would reshuffle it
+    if args.recursive
+        && !args.force
+        && !args.dry_run
+        && !args.watch
+        && scanner::looks_organized(&source_dir, &config.categories)
+    {
+        ui::print_warning(
+            "This folder looks already organized (category folders, few loose files); a recursive run would reshuffle them.",
+        );
+        if !ui::confirm_with_default("Organize anyway?", false) {
+            ui::print_info("Operation cancelled.");
+            return None;
+        }
+    }
+
+    let org_mode = OrganizationMode::from_str(&args.mode);
+    if args.with_folders && org_mode != OrganizationMode::Category {
+        ui::print_error("--with-folders only works with --mode category.");
+        return None;
+    }
+
+    let date_source = args
+        .date_source
+        .as_deref()
+        .map(DateSource::from_str)
+        .unwrap_or(config.preferences.date_source);
+
+    let mut options = organizer::OrganizeOptions::new(config, resolve_rename_mode(args));
+    options.date_source = date_source;
+    options.conflict_policy = ConflictPolicy::from_str(&args.on_conflict);
+    if let Some(suffix) = &args.conflict_suffix {
+        options.conflict_suffix = ConflictSuffix::from_str(suffix);
+    }
+    options.dedupe_on_move = args.dedupe_on_move;
+    options.jobs = args.jobs.max(1);
+    options.symlinks = SymlinkPolicy::from_str(&args.symlinks);
+    options.verify_moves = args.verify_moves;
+    if args.run_subfolder {
+        options.run_subfolder = Some(organizer::run_subfolder_name());
+    }
+    options.existing_only = args.sort_into_existing_only;
+
+    if let Some(dest) = &args.dest {
+        match resolve_path(dest) {
+            Some(p) if !Path::new(&p).is_file() => options.dest_root = Some(PathBuf::from(p)),
+            _ => {
+                ui::print_error(&format!("Invalid destination: {}", dest));
+                return None;
+            }
+        }
+    }
+
+    if args.watch && !args.once {
+        // --sweep organizes what is already there once the watcher is listening
+        let sweep = || {
+            organize_folder(config, &source_dir, &options, org_mode, args);
+        };
+        let sweep: &dyn Fn() = &sweep;
+        watcher::watch_folder(
+            &source_dir,
+            &options,
+            &config.watch,
+            args.dry_run,
+            args.sweep.then_some(sweep),
+        );
+        return None;
     }
 
-    let _lock = match lock::FolderLock::acquire(&source_dir) {
+    organize_folder(config, &source_dir, &options, org_mode, args)
+}
+
+/// One organize pass over `source_dir`: lock, scan, move, record history
+fn organize_folder(
+    config: &config::Config,
+    source_dir: &str,
+    options: &organizer::OrganizeOptions,
+    org_mode: OrganizationMode,
+    args: &OrganizeArgs,
+) -> Option<stats::OrganizationStats> {
+    let acquired = match args.lock_wait {
+        Some(secs) => lock::FolderLock::acquire_timeout(source_dir, Duration::from_secs(secs)),
+        None => lock::FolderLock::acquire(source_dir),
+    };
+    let _lock = match acquired {
         Ok(l) => l,
         Err(e) => {
             ui::print_error(&e);
-            return;
+            return None;
         }
     };
 
-    let org_mode = OrganizationMode::from_str(&cli.mode);
-    let rename_mode = RenameMode::from_str(&cli.rename);
-    let files_map = scan_files(&source_dir, &config.categories, org_mode, cli.recursive);
+    let mut scan_options = scanner::ScanOptions::new(config);
+    scan_options.date_source = options.date_source;
+    scan_options.include_hidden = args.include_hidden;
+    scan_options.include_hidden_dirs = args.include_hidden_dirs;
+    scan_options.sniff = args.sniff;
+    scan_options.max_depth = args.max_depth;
+    scan_options.older_than = args.older_than;
+    scan_options.newer_than = args.newer_than;
+    scan_options.with_folders = args.with_folders;
+    for line in scanner::IgnoreList::load(Path::new(source_dir)).invalid {
+        ui::print_warning(&format!(
+            "Invalid pattern in {}: {}",
+            scanner::IGNORE_FILE_NAME,
+            line
+        ));
+    }
+    let mut files_map = if args.flatten {
+        let files = scanner::scan_flatten(source_dir, &scan_options);
+        // An empty folder name puts files directly in the destination root
+        std::collections::HashMap::from([(String::new(), files)])
+            .into_iter()
+            .filter(|(_, files)| !files.is_empty())
+            .collect()
+    } else {
+        scan_files(source_dir, &scan_options, org_mode, args.recursive)
+    };
+    let filtered_by_age = scan_options.filtered_by_age.take();
+    let ignored = scan_options.ignored.take();
+    let left_unmatched = scan_options.left_unmatched.take();
+
+    let json_output = args.dry_run && args.format == "json";
 
     if files_map.is_empty() {
+        if json_output {
+            ui::print_json(&stats::DryRunPreview::new());
+        }
         ui::print_info("No files to organize.");
-        return;
+        if !filtered_by_age.is_empty() {
+            ui::print_info(&format!(
+                "{} file(s) outside the age filter",
+                filtered_by_age.len()
+            ));
+        }
+        if !ignored.is_empty() {
+            ui::print_info(&format!(
+                "{} file(s) matched {}",
+                ignored.len(),
+                scanner::IGNORE_FILE_NAME
+            ));
+        }
+        if !left_unmatched.is_empty() {
+            ui::print_info(&format!(
+                "{} file(s) without a category left in place",
+                left_unmatched.len()
+            ));
+        }
+        return Some(stats::OrganizationStats::default());
     }
 
-    if cli.dry_run {
-        let preview = organizer::generate_dry_run_preview(
-            &source_dir,
-            &files_map,
-            rename_mode.map(Into::into).as_ref(),
-        );
-        ui::print_dry_run_preview(&preview);
+    if args.dry_run {
+        let preview = organizer::generate_dry_run_preview(source_dir, &files_map, options);
+        if json_output {
+            ui::print_json(&preview);
+            return None;
+        }
+        ui::print_dry_run_preview(&preview, args.preview_limit);
         ui::print_info("Dry-run complete. No changes were made.");
-        return;
+        return None;
+    }
+
+    // Guards against pointing Stellar at a huge folder by mistake; declining
+    // skips this folder and moves on to the next one
+    if let Some(warning) = file_cap_warning(&files_map, args.max_files) {
+        ui::print_warning(&warning);
+        if !args.force && !ui::confirm_with_default("Organize them anyway?", false) {
+            ui::print_info("Operation cancelled.");
+            return None;
+        }
     }
 
+    if args.interactive_review {
+        let preview = organizer::generate_dry_run_preview(source_dir, &files_map, options);
+        let Some(left_out) = ui::review_moves(&preview) else {
+            ui::print_info("Operation cancelled.");
+            return None;
+        };
+        organizer::exclude_files(&mut files_map, &left_out);
+        if files_map.is_empty() {
+            ui::print_info("No files selected.");
+            return Some(stats::OrganizationStats::default());
+        }
+        if !left_out.is_empty() {
+            ui::print_info(&format!("{} file(s) left where they are", left_out.len()));
+        }
+    }
+
+    let mut options = options.clone();
+    let Some(collisions_skipped) = ui::settle_rename_collisions(&mut files_map, &mut options)
+    else {
+        ui::print_info("Operation cancelled.");
+        return None;
+    };
+
     ui::print_preview(&files_map);
-    let result = organizer::move_files(&source_dir, &files_map, rename_mode.map(Into::into).as_ref());
-    organizer::record_moves(&source_dir, result.moves);
+    // Recorded runs are journaled so an interrupted one can still be undone
+    let journal = if args.no_history {
+        None
+    } else {
+        ui::start_journal(source_dir)
+    };
+    options.journal = journal.as_ref();
+    let mut result = match ui::move_files_with_progress(source_dir, &files_map, &options) {
+        Ok(r) => r,
+        Err(e) => {
+            ui::print_error(&e.to_string());
+            return None;
+        }
+    };
+    result.stats.unknown_extensions = scan_options.unknown_extensions.take();
+    for path in filtered_by_age {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::FilteredByAge);
+    }
+    for path in ignored {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::Ignored);
+    }
+    for path in left_unmatched {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::Unmatched);
+    }
+    for path in collisions_skipped {
+        result
+            .stats
+            .add_skipped_with_reason(path, stats::SkipReason::NameCollision);
+    }
+    let archived = if args.archive {
+        archive_destination(source_dir, &files_map, &options, args.archive_remove)
+    } else {
+        Vec::new()
+    };
+    let recorded = if args.no_history {
+        organizer::mark_unrecorded(source_dir, &result.moves);
+        Ok(())
+    } else {
+        organizer::record_archived_moves(source_dir, result.moves, archived)
+    };
+    ui::finish_journal(journal, recorded);
+    if args.prune_empty || args.clean_empty {
+        history::cleanup_empty_folders(&result.touched_dirs, Some(Path::new(source_dir)));
+    }
+    if let Some(report) = args.report.as_deref().and_then(resolve_path) {
+        if let Err(e) = stats::append_report(Path::new(&report), source_dir, &result.stats) {
+            ui::print_error(&e);
+        }
+    }
     ui::print_statistics(&result.stats);
     ui::print_success("Files organized successfully!");
+    Some(result.stats)
+}
+
+/// Organize each folder in turn (own lock, stats and history entry);
+/// a folder that cannot be organized is reported and the others still run
+fn run_cli_folders(config: &config::Config, folders: &[String], args: &OrganizeArgs) {
+    if args.watch && !args.once && folders.len() > 1 {
+        ui::print_error("--watch takes a single folder.");
+        return;
+    }
+
+    let mut organized = Vec::new();
+    let mut failed = Vec::new();
+    for (i, folder) in folders.iter().enumerate() {
+        if folders.len() > 1 {
+            ui::print_info(&format!("Folder {}/{}: {}", i + 1, folders.len(), folder));
+        }
+        match run_cli_mode(config, folder, args) {
+            Some(stats) => organized.push((folder.clone(), stats)),
+            None => failed.push(folder.clone()),
+        }
+    }
+
+    if folders.len() > 1 && !args.dry_run {
+        ui::print_combined_summary(&organized, &failed);
+    }
+}
+
+/// --max-files: the count and the cap, when the scan found more files than the cap
+fn file_cap_warning(
+    files_map: &std::collections::HashMap<String, Vec<PathBuf>>,
+    max_files: Option<usize>,
+) -> Option<String> {
+    let max = max_files?;
+    let count = organizer::file_count(files_map);
+    (count > max).then(|| {
+        format!(
+            "Found {} files to organize, more than --max-files {}.",
+            count, max
+        )
+    })
+}
+
+/// Offer to record or undo the runs that were killed before reaching the history
+fn resolve_pending_runs() {
+    for run in history::pending_runs() {
+        ui::print_warning(&format!(
+            "A run in {} ({}) was interrupted after moving {} file(s).",
+            run.folder,
+            run.timestamp,
+            run.moves.len()
+        ));
+        match ui::select_pending_run_action() {
+            Some(0) => match run.record() {
+                Ok(()) => ui::print_success("Recorded in history (`stellar undo` reverses it)."),
+                Err(e) => ui::print_error(&e),
+            },
+            Some(1) => ui::print_undo_result(&run.undo()),
+            _ => ui::print_info("Left as is; you will be asked again next time."),
+        }
+    }
+}
+
+/// --archive: compress the top-level folders that received files
+fn archive_destination(
+    source_dir: &str,
+    files_map: &HashMap<String, Vec<PathBuf>>,
+    options: &organizer::OrganizeOptions,
+    remove: bool,
+) -> Vec<archive::ArchivedFolder> {
+    let folders = archive::top_level_folders(files_map);
+    let spinner = ui::create_spinner("Archiving folders...");
+    let result = archive::archive_folders(options.dest_root(source_dir), &folders, remove);
+    spinner.finish_and_clear();
+
+    for error in &result.errors {
+        ui::print_error(error);
+    }
+    ui::print_archived(&result.archived);
+    result.archived
+}
+
+/// Load config from local file, user config, or embedded default.
+/// Warns about extensions listed in several categories.
+fn load_config() -> Result<config::Config, String> {
+    let config = config::read_config()?;
+
+    for (ext, categories) in config::duplicate_extensions(&config.categories) {
+        ui::print_warning(&format!(
+            ".{} is listed in {}; using {}",
+            ext,
+            categories.join(", "),
+            categories[0]
+        ));
+    }
+    for (ext, keys) in config::duplicate_overrides(&config.overrides) {
+        ui::print_warning(&format!(
+            ".{} has several overrides ({}); using {}",
+            ext,
+            override_keys(&keys),
+            keys[0].1
+        ));
+    }
+    if let Err(e) = config.date.check() {
+        ui::print_warning(&format!("{}; using the English month names", e));
+    }
+    if let Err(e) = config.history.check() {
+        ui::print_warning(&format!("{}; history is not trimmed by age", e));
+    }
+    history::set_retention(history::Retention::from_config(&config.history));
+
+    Ok(config)
+}
+
+/// Pick the rename mode from --rename-template or --rename
+fn resolve_rename_mode(args: &OrganizeArgs) -> Option<renamer::RenameMode> {
+    match &args.rename_template {
+        Some(template) => {
+            let unknown = renamer::unknown_placeholders(template);
+            if !unknown.is_empty() {
+                ui::print_warning(&format!(
+                    "Unknown template placeholders left as-is: {}",
+                    unknown
+                        .iter()
+                        .map(|p| format!("{{{}}}", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            Some(renamer::RenameMode::Template(template.clone()))
+        }
+        None => RenameMode::from_str(&args.rename).map(Into::into),
+    }
+}
+
+fn run_init(local: bool, force: bool) {
+    match config::init_config(local, force) {
+        Ok(path) => {
+            ui::print_success(&format!("Config written to {}", path.display()));
+            ui::print_info("Edit [categories] to choose where each extension goes.");
+        }
+        Err(e) => ui::print_error(&e),
+    }
+}
+
+/// "torrent = Downloads, .torrent = Torrents" for the keys of a duplicated override
+fn override_keys(keys: &[(String, String)]) -> String {
+    keys.iter()
+        .map(|(key, folder)| format!("{} = {}", key, folder))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Exits with status 1 when the config has problems, for use in scripts
+fn run_config_check() {
+    let source = config::active_config_path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "built-in default".to_string());
+
+    let config = match config::read_config() {
+        Ok(c) => c,
+        Err(e) => {
+            ui::print_error(&e);
+            std::process::exit(1);
+        }
+    };
+
+    let duplicates = config::duplicate_extensions(&config.categories);
+    let duplicate_overrides = config::duplicate_overrides(&config.overrides);
+    let month_names = config.date.check();
+    let history = config.history.check();
+    if duplicates.is_empty()
+        && duplicate_overrides.is_empty()
+        && month_names.is_ok()
+        && history.is_ok()
+    {
+        ui::print_success(&format!("Config OK ({})", source));
+        return;
+    }
+
+    if !duplicates.is_empty() {
+        ui::print_error(&format!(
+            "{} extension(s) belong to several categories in {} (the first one alphabetically is used):",
+            duplicates.len(),
+            source
+        ));
+        for (ext, categories) in &duplicates {
+            eprintln!("    .{} -> {}", ext, categories.join(", "));
+        }
+    }
+    if !duplicate_overrides.is_empty() {
+        ui::print_error(&format!(
+            "{} extension(s) have several overrides in {} (the first key alphabetically is used):",
+            duplicate_overrides.len(),
+            source
+        ));
+        for (ext, keys) in &duplicate_overrides {
+            eprintln!("    .{} -> {}", ext, override_keys(keys));
+        }
+    }
+    if let Err(e) = month_names {
+        ui::print_error(&format!("{} (the English names are used)", e));
+    }
+    if let Err(e) = history {
+        ui::print_error(&format!("{} (history is not trimmed by age)", e));
+    }
+    std::process::exit(1);
+}
+
+/// Edit [categories] and save the config; exits with status 1 on failure
+fn run_categories(action: CategoriesCommands) {
+    let mut config = match config::read_config() {
+        Ok(c) => c,
+        Err(e) => {
+            ui::print_error(&e);
+            std::process::exit(1);
+        }
+    };
+
+    let (message, changed) = match action {
+        CategoriesCommands::List => {
+            ui::display_categories(&config.categories);
+            return;
+        }
+        CategoriesCommands::Add { name, extensions } => {
+            let extensions = config::parse_extensions(&extensions.join(","));
+            if extensions.is_empty() {
+                ui::print_error("No extensions given.");
+                std::process::exit(1);
+            }
+            let added = config::add_to_category(&mut config.categories, &name, &extensions);
+            if added.is_empty() {
+                ui::print_info(&format!("'{}' already has these extensions.", name));
+                return;
+            }
+            for (ext, categories) in config::duplicate_extensions(&config.categories) {
+                if added.contains(&ext) {
+                    ui::print_warning(&format!(
+                        ".{} is also listed in {}; the first one alphabetically is used",
+                        ext,
+                        categories.join(", ")
+                    ));
+                }
+            }
+            let changed = config
+                .categories
+                .keys()
+                .filter(|k| k.eq_ignore_ascii_case(&name))
+                .cloned()
+                .collect();
+            (format!("Added {} to '{}'", added.join(", "), name), changed)
+        }
+        CategoriesCommands::Remove { name } => {
+            match config::remove_category(&mut config.categories, &name) {
+                Ok((name, _)) => (format!("Category '{}' removed", name), vec![name]),
+                Err(e) => {
+                    ui::print_error(&e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+
+    if let Err(e) = config::save_categories(&config.categories, &changed) {
+        ui::print_error(&e);
+        std::process::exit(1);
+    }
+    ui::print_success(&format!(
+        "{} (saved to {})",
+        message,
+        config::save_path().display()
+    ));
+}
+
+fn run_stats(folder_path: &str, recursive: bool) {
+    let config = match load_config() {
+        Ok(c) => c,
+        Err(e) => {
+            ui::print_error(&format!("Failed to load config: {}", e));
+            return;
+        }
+    };
+
+    let source_dir = match resolve_path(folder_path) {
+        Some(p) => p,
+        None => {
+            ui::print_error(&format!("Invalid path: {}", folder_path));
+            return;
+        }
+    };
+
+    if !Path::new(&source_dir).is_dir() {
+        ui::print_error(&format!("Not a directory: {}", source_dir));
+        return;
+    }
+
+    let scan_options = scanner::ScanOptions::new(&config);
+    let files_map = scan_files(
+        &source_dir,
+        &scan_options,
+        OrganizationMode::Category,
+        recursive,
+    );
+
+    let mut report = stats::FolderReport::new();
+    for (category, files) in &files_map {
+        for file in files {
+            if file.extension().is_none() {
+                report.add_extensionless(file);
+            } else {
+                report.add_file(category, file);
+            }
+        }
+    }
+
+    ui::print_folder_report(&source_dir, &report);
+}
+
+fn run_rename_preview(
+    folder_path: &str,
+    rename: &str,
+    rename_template: Option<String>,
+    date_source: Option<&str>,
+    recursive: bool,
+) {
+    let config = match load_config() {
+        Ok(c) => c,
+        Err(e) => {
+            ui::print_error(&format!("Failed to load config: {}", e));
+            return;
+        }
+    };
+
+    let source_dir = match resolve_path(folder_path) {
+        Some(p) if Path::new(&p).is_dir() => p,
+        _ => {
+            ui::print_error(&format!("Not a directory: {}", folder_path));
+            return;
+        }
+    };
+
+    let mode = match rename_template {
+        Some(template) => renamer::RenameMode::Template(template),
+        None => match RenameMode::from_str(rename) {
+            Some(mode) => mode.into(),
+            None => return,
+        },
+    };
+    let mut options = organizer::OrganizeOptions::new(&config, Some(mode));
+    options.date_source = date_source
+        .map(DateSource::from_str)
+        .unwrap_or(config.preferences.date_source);
+
+    let scan_options = scanner::ScanOptions::new(&config);
+    let files_map = scan_files(
+        &source_dir,
+        &scan_options,
+        OrganizationMode::Category,
+        recursive,
+    );
+
+    ui::print_rename_preview(&organizer::preview_renames(&files_map, &options));
+}
+
+/// Which files a duplicates run looks at
+struct DuplicateScan {
+    folder: String,
+    recursive: bool,
+    min_size: u64,
+}
+
+/// Exit status of `stellar duplicates` when it cannot run (bad folder or
+/// config), kept apart from the 1 that means "duplicates found"
+const DUPLICATES_ERROR_STATUS: i32 = 2;
+
+impl DuplicateScan {
+    /// The files to compare; exits with DUPLICATES_ERROR_STATUS when the folder is invalid
+    fn files(&self) -> Vec<PathBuf> {
+        let source_dir = match resolve_path(&self.folder) {
+            Some(p) if Path::new(&p).is_dir() => p,
+            _ => {
+                ui::print_error(&format!("Not a directory: {}", self.folder));
+                std::process::exit(DUPLICATES_ERROR_STATUS);
+            }
+        };
+
+        let files = duplicates::list_files(Path::new(&source_dir), self.recursive);
+        duplicates::filter_min_size(files, self.min_size)
+    }
+}
+
+/// Exits with status 1 when duplicates are found and left in place, and 2 when
+/// the scan cannot run, for use in scripts
+fn run_duplicates(
+    scan: &DuplicateScan,
+    delete: bool,
+    permanent: bool,
+    keep: KeepRule,
+    order: GroupOrder,
+    json_output: bool,
+) {
+    let files = scan.files();
+
+    let hashing = match load_config() {
+        Ok(c) => duplicates::HashOptions::from_config(&c.hashing),
+        Err(e) => {
+            ui::print_error(&format!("Failed to load config: {}", e));
+            std::process::exit(DUPLICATES_ERROR_STATUS);
+        }
+    };
+
+    let progress = ui::create_progress_bar(files.len() as u64, "Scanning for duplicates...");
+    let mut groups = duplicates::find_duplicates(&files, &hashing, &progress);
+    duplicates::order_groups(&mut groups, order);
+
+    if json_output {
+        // The copy --keep would keep comes first in each group
+        duplicates::sort_groups(&mut groups, keep);
+        ui::print_json(&duplicates::DuplicateReport::new(&groups));
+        if !groups.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if groups.is_empty() {
+        ui::print_success("No duplicate files found!");
+        return;
+    }
+
+    let plans = duplicates::plan_removal(&groups, keep);
+    ui::print_removal_plan(&plans);
+
+    if !delete {
+        ui::print_info("No files were removed (use --delete to remove the extra copies).");
+        std::process::exit(1);
+    }
+
+    let prompt = if permanent {
+        "Permanently delete these files?"
+    } else {
+        "Move these files to the trash?"
+    };
+    if !ui::confirm_with_default(prompt, false) {
+        ui::print_info("Operation cancelled.");
+        std::process::exit(1);
+    }
+
+    let result = duplicates::apply_removal(&plans, !permanent);
+    ui::print_removal_result(&result, !permanent);
+    // Copies that could not be removed are still duplicates
+    if !result.errors.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Report near-duplicate pictures among files of the Images category
+fn run_similar_images(scan: &DuplicateScan, threshold: u32) {
+    let config = match load_config() {
+        Ok(c) => c,
+        Err(e) => {
+            ui::print_error(&format!("Failed to load config: {}", e));
+            std::process::exit(DUPLICATES_ERROR_STATUS);
+        }
+    };
+
+    let files = scan.files();
+
+    let images: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            config::find_category(&config.categories, &ext).as_deref() == Some("Images")
+        })
+        .collect();
+
+    let progress = ui::create_progress_bar(images.len() as u64, "Comparing images...");
+    let groups = duplicates::find_similar_images(&images, threshold, &progress);
+
+    ui::print_similar_images(&groups);
+    if !groups.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn run_undo(count: usize) {
+    if count == 0 {
+        ui::print_error("--count must be at least 1");
+        return;
+    }
+
+    match history::undo_last_operations(count) {
+        Ok(result) => ui::print_undo_result(&result),
+        Err(e) => ui::print_info(&e),
+    }
+}
+
+fn run_history(folder: Option<&str>, count: usize) {
+    let operations = match folder {
+        Some(folder) => match resolve_path(folder) {
+            Some(p) => history::get_operations_for_folder(&p, count),
+            None => {
+                ui::print_error(&format!("Invalid path: {}", folder));
+                return;
+            }
+        },
+        None => history::get_last_operations(count),
+    };
+
+    ui::print_history(&operations);
+}
+
+fn run_history_prune(older_than: Option<chrono::TimeDelta>, missing: bool) {
+    match history::prune_operations(older_than, missing) {
+        Ok(0) => ui::print_info("No operations to prune."),
+        Ok(removed) => ui::print_success(&format!("Removed {} operation(s) from history", removed)),
+        Err(e) => ui::print_error(&e),
+    }
+}
+
+fn run_trash(action: TrashCommands) {
+    match action {
+        TrashCommands::List => ui::print_trash(&trash::list_batches()),
+        TrashCommands::Restore { batch } => match trash::restore_batch(batch.as_deref()) {
+            Ok(result) => ui::print_restore_result(&result),
+            Err(e) => ui::print_error(&e),
+        },
+    }
 }
 
 fn scan_files(
     source_dir: &str,
-    categories: &std::collections::HashMap<String, Vec<String>>,
+    options: &scanner::ScanOptions,
     org_mode: OrganizationMode,
     recursive: bool,
 ) -> std::collections::HashMap<String, Vec<std::path::PathBuf>> {
     let mut files_map = match org_mode {
-        OrganizationMode::Category => scanner::scan_by_category(source_dir, categories),
-        OrganizationMode::Date => scanner::scan_by_date(source_dir),
-        OrganizationMode::Hybrid => scanner::scan_hybrid(source_dir, categories),
+        OrganizationMode::Category if !options.rules.is_empty() => {
+            scanner::scan_by_rules(source_dir, options)
+        }
+        OrganizationMode::Category => scanner::scan_by_category(source_dir, options),
+        OrganizationMode::Date => scanner::scan_by_date(source_dir, options),
+        OrganizationMode::Hybrid => scanner::scan_hybrid(source_dir, options),
     };
 
     if recursive {
-        let recursive_files = scanner::scan_recursive(source_dir, categories, org_mode.to_index());
+        let recursive_files = scanner::scan_recursive(source_dir, options, org_mode.to_index());
         for (category, files) in recursive_files {
             files_map.entry(category).or_default().extend(files);
         }
@@ -3,7 +3,12 @@
 //
 // Type-safe enums for organization and rename modes.
 // Replaces magic numbers (usize) with proper types.
+// The `from_str` parsers are lenient (unknown values fall back to the default),
+// so they are plain functions rather than fallible `FromStr` impls.
 
+#![allow(clippy::should_implement_trait)]
+
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// How files are organized into folders
@@ -67,6 +72,10 @@ pub enum RenameMode {
     DatePrefix,
     /// Skip renaming entirely
     Skip,
+    /// Snake case: my_file_name.pdf
+    SnakeCase,
+    /// Title case: My File Name.pdf
+    TitleCase,
 }
 
 #[allow(dead_code)]
@@ -76,6 +85,8 @@ impl RenameMode {
             0 => Some(Self::Clean),
             1 => Some(Self::DatePrefix),
             2 => None, // Skip
+            3 => Some(Self::SnakeCase),
+            4 => Some(Self::TitleCase),
             _ => Some(Self::Clean),
         }
     }
@@ -85,6 +96,8 @@ impl RenameMode {
             Self::Clean => 0,
             Self::DatePrefix => 1,
             Self::Skip => 2,
+            Self::SnakeCase => 3,
+            Self::TitleCase => 4,
         }
     }
 
@@ -93,6 +106,8 @@ impl RenameMode {
             "clean" | "c" => Some(Self::Clean),
             "date-prefix" | "date" | "d" => Some(Self::DatePrefix),
             "skip" | "none" | "s" => None,
+            "snake" | "snake-case" | "snake_case" => Some(Self::SnakeCase),
+            "title" | "title-case" => Some(Self::TitleCase),
             _ => Some(Self::Clean),
         }
     }
@@ -104,6 +119,8 @@ impl fmt::Display for RenameMode {
             Self::Clean => write!(f, "Clean"),
             Self::DatePrefix => write!(f, "Date prefix"),
             Self::Skip => write!(f, "Skip"),
+            Self::SnakeCase => write!(f, "Snake case"),
+            Self::TitleCase => write!(f, "Title case"),
         }
     }
 }
@@ -114,7 +131,313 @@ impl From<RenameMode> for crate::renamer::RenameMode {
         match mode {
             RenameMode::Clean => crate::renamer::RenameMode::Clean,
             RenameMode::DatePrefix => crate::renamer::RenameMode::DatePrefix,
+            RenameMode::SnakeCase => crate::renamer::RenameMode::SnakeCase,
+            RenameMode::TitleCase => crate::renamer::RenameMode::TitleCase,
             RenameMode::Skip => crate::renamer::RenameMode::Clean, // Fallback, won't be used
         }
     }
 }
+
+/// Which file timestamp drives date-based organization and renaming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateSource {
+    /// Creation time, falling back to modification time when unavailable
+    #[default]
+    Created,
+    /// Modification time
+    Modified,
+}
+
+impl DateSource {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "modified" | "mtime" | "m" => Self::Modified,
+            _ => Self::Created,
+        }
+    }
+}
+
+impl fmt::Display for DateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Created => write!(f, "Created"),
+            Self::Modified => write!(f, "Modified"),
+        }
+    }
+}
+
+/// How finely date-based folders are split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateGranularity {
+    /// 2024/
+    Year,
+    /// 2024/01-january/
+    Month,
+    /// 2024/01-january/15/
+    Day,
+}
+
+impl DateGranularity {
+    pub fn from_index(idx: usize) -> Self {
+        match idx {
+            0 => Self::Year,
+            2 => Self::Day,
+            _ => Self::Month,
+        }
+    }
+
+    pub fn to_index(self) -> usize {
+        match self {
+            Self::Year => 0,
+            Self::Month => 1,
+            Self::Day => 2,
+        }
+    }
+}
+
+impl fmt::Display for DateGranularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Year => write!(f, "Year"),
+            Self::Month => write!(f, "Month"),
+            Self::Day => write!(f, "Day"),
+        }
+    }
+}
+
+/// What to do when the destination file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Keep both: report.pdf → report-1.pdf
+    #[default]
+    Rename,
+    /// Leave the source file where it is
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Replace the existing file only if the source is newer
+    KeepNewer,
+}
+
+impl ConflictPolicy {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "skip" => Self::Skip,
+            "overwrite" => Self::Overwrite,
+            "keep-newer" | "newer" => Self::KeepNewer,
+            _ => Self::Rename,
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rename => write!(f, "Rename"),
+            Self::Skip => write!(f, "Skip"),
+            Self::Overwrite => write!(f, "Overwrite"),
+            Self::KeepNewer => write!(f, "Keep newer"),
+        }
+    }
+}
+
+/// How a renamed copy is told apart when its name is taken (--on-conflict rename)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictSuffix {
+    /// report-1.pdf, report-2.pdf
+    #[default]
+    DashNumber,
+    /// report (1).pdf, report (2).pdf
+    ParenNumber,
+    /// report_1.pdf, report_2.pdf
+    UnderscoreNumber,
+    /// report-20240115.pdf (today), then report-20240115-1.pdf
+    Timestamp,
+}
+
+impl ConflictSuffix {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "paren-number" | "paren" => Self::ParenNumber,
+            "underscore-number" | "underscore" => Self::UnderscoreNumber,
+            "timestamp" | "date" => Self::Timestamp,
+            _ => Self::DashNumber,
+        }
+    }
+}
+
+impl fmt::Display for ConflictSuffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DashNumber => write!(f, "dash-number"),
+            Self::ParenNumber => write!(f, "paren-number"),
+            Self::UnderscoreNumber => write!(f, "underscore-number"),
+            Self::Timestamp => write!(f, "timestamp"),
+        }
+    }
+}
+
+/// Where files that match no category, override or rule go
+/// (`[preferences] unmatched_policy`)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UnmatchedPolicy {
+    /// The Others folder
+    #[default]
+    Others,
+    /// Stay where they are, listed as skipped
+    Leave,
+    /// A folder named after the extension: xyz/
+    ByExtension,
+    /// A folder with this name instead of Others
+    Folder(String),
+}
+
+impl UnmatchedPolicy {
+    /// Any value other than the keywords is a folder name
+    pub fn from_str(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "" | "others" => Self::Others,
+            "leave" => Self::Leave,
+            "by-extension" => Self::ByExtension,
+            _ => Self::Folder(s.trim().to_string()),
+        }
+    }
+
+    /// Folder for an unmatched file with extension `ext`; None leaves it in place
+    pub fn folder(&self, ext: &str) -> Option<String> {
+        match self {
+            Self::Others => Some("Others".into()),
+            Self::Leave => None,
+            Self::ByExtension => Some(ext.to_string()),
+            Self::Folder(name) => Some(name.clone()),
+        }
+    }
+}
+
+/// Rejects folder names that would leave the organized folder ("../x", "/x", "a/b")
+impl TryFrom<String> for UnmatchedPolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        match Self::from_str(&s) {
+            Self::Folder(name) if name == ".." || name == "." || name.contains(['/', '\\']) => {
+                Err(format!(
+                    "unmatched_policy \"{}\" must be others, leave, by-extension or a plain folder name",
+                    name
+                ))
+            }
+            policy => Ok(policy),
+        }
+    }
+}
+
+impl From<UnmatchedPolicy> for String {
+    fn from(policy: UnmatchedPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+impl fmt::Display for UnmatchedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Others => write!(f, "others"),
+            Self::Leave => write!(f, "leave"),
+            Self::ByExtension => write!(f, "by-extension"),
+            Self::Folder(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// How symbolic links found in the scanned folder are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Leave links where they are
+    #[default]
+    Skip,
+    /// Move the link itself (its target is untouched)
+    MoveLink,
+    /// Organize a copy of the target in place of the link
+    Follow,
+}
+
+impl SymlinkPolicy {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "move-link" | "move" => Self::MoveLink,
+            "follow" => Self::Follow,
+            _ => Self::Skip,
+        }
+    }
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skip => write!(f, "Skip"),
+            Self::MoveLink => write!(f, "Move link"),
+            Self::Follow => write!(f, "Follow"),
+        }
+    }
+}
+
+/// Which copy of a duplicate group is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeepRule {
+    /// Least recently modified copy
+    Oldest,
+    /// Most recently modified copy
+    Newest,
+    /// Copy with the fewest path components (root files beat nested copies)
+    #[default]
+    Shallowest,
+    /// Copy with the shortest file name (report.pdf beats report (1).pdf)
+    ShortestName,
+}
+
+impl KeepRule {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "oldest" => Self::Oldest,
+            "newest" => Self::Newest,
+            "shortest-name" | "shortest" => Self::ShortestName,
+            _ => Self::Shallowest,
+        }
+    }
+}
+
+impl fmt::Display for KeepRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Oldest => write!(f, "Oldest"),
+            Self::Newest => write!(f, "Newest"),
+            Self::Shallowest => write!(f, "Shallowest"),
+            Self::ShortestName => write!(f, "Shortest name"),
+        }
+    }
+}
+
+/// Order of duplicate groups in reports, biggest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupOrder {
+    /// Size of one copy
+    Size,
+    /// Number of copies
+    Count,
+    /// Space taken by the extra copies (size x copies beyond the first)
+    #[default]
+    Wasted,
+}
+
+impl GroupOrder {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "size" => Self::Size,
+            "count" => Self::Count,
+            _ => Self::Wasted,
+        }
+    }
+}
@@ -2,99 +2,473 @@
 // @musem23
 //
 // Moves and renames files to their destination folders.
-// Handles naming conflicts by appending numeric suffixes.
+// Handles naming conflicts by renaming, skipping, or overwriting (ConflictPolicy).
 // Generates dry-run previews and records moves for undo functionality.
+// Moves can be spread over several worker threads (OrganizeOptions::jobs).
+// With a run subfolder, each run lands in its own Documents/<date-time>/ folder.
 
-use std::collections::HashMap;
+use chrono::Local;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
-use std::{fs, process::Command};
-
-use crate::history::{self, FileMove};
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use std::{fs, thread};
+
+use crate::archive::ArchivedFolder;
+use crate::config::{self, Config, RenameConfig};
+use crate::duplicates::{self, HashOptions};
+use crate::filesystem::{self, FileSystem, RealFileSystem};
+use crate::history::{self, FileMove, Journal};
+use crate::modes::{ConflictPolicy, ConflictSuffix, DateSource, SymlinkPolicy, UnmatchedPolicy};
+use crate::progress::ProgressReporter;
 use crate::renamer::{self, RenameMode};
-use crate::stats::{DryRunPreview, OrganizationStats, SkipReason};
-use crate::ui;
+use crate::stats::{DryRunPreview, OrganizationStats, PlannedRename, RenameCollision, SkipReason};
+
+/// Name of the per-run folder created by --run-subfolder (2024-01-15-143005)
+const RUN_SUBFOLDER_FORMAT: &str = "%Y-%m-%d-%H%M%S";
+
+/// Category shown in the stats for files moved straight into the root (--flatten)
+const FLATTENED_CATEGORY: &str = "Flattened";
+
+/// Category that the stats count the files of `folder_name` under
+fn stats_category(folder_name: &str) -> &str {
+    if folder_name.is_empty() {
+        FLATTENED_CATEGORY
+    } else {
+        folder_name
+    }
+}
+
+/// Settings shared by the move and dry-run passes of one organization run
+#[derive(Clone)]
+pub struct OrganizeOptions<'a> {
+    pub rename_mode: Option<RenameMode>,
+    pub rename: &'a RenameConfig,
+    /// Names picked by the user for files whose new names collide
+    pub names: HashMap<PathBuf, String>,
+    pub categories: &'a HashMap<String, Vec<String>>,
+    pub overrides: &'a HashMap<String, String>,
+    /// Where files matching no category go
+    pub unmatched: &'a UnmatchedPolicy,
+    /// Category folder for files without an extension (watch mode)
+    pub no_extension_folder: &'a str,
+    pub date_source: DateSource,
+    /// Root for category folders (defaults to the source folder)
+    pub dest_root: Option<PathBuf>,
+    /// Folder added under each category folder so runs never share one (--run-subfolder)
+    pub run_subfolder: Option<String>,
+    /// Only fill destination folders that already exist; other files stay put
+    pub existing_only: bool,
+    pub conflict_policy: ConflictPolicy,
+    /// Naming of the copies kept by ConflictPolicy::Rename
+    pub conflict_suffix: ConflictSuffix,
+    /// Delete the source instead of moving when the destination has identical content
+    pub dedupe_on_move: bool,
+    /// Worker threads used to move files (1 = sequential)
+    pub jobs: usize,
+    pub symlinks: SymlinkPolicy,
+    /// Hash cross-device copies against the source before deleting it
+    pub verify_moves: bool,
+    /// How files are read when hashing for --dedupe-on-move and --verify-moves
+    pub hashing: HashOptions,
+    /// File operations used for moves (RealFileSystem outside of tests)
+    pub fs: &'a dyn FileSystem,
+    /// Where each move is written as soon as it happens (runs recorded in history)
+    pub journal: Option<&'a Journal>,
+}
+
+impl<'a> OrganizeOptions<'a> {
+    pub fn new(config: &'a Config, rename_mode: Option<RenameMode>) -> Self {
+        Self {
+            rename_mode,
+            rename: &config.rename,
+            names: HashMap::new(),
+            categories: &config.categories,
+            overrides: &config.overrides,
+            unmatched: &config.preferences.unmatched_policy,
+            no_extension_folder: config
+                .preferences
+                .no_extension_folder
+                .as_deref()
+                .unwrap_or("Others"),
+            date_source: config.preferences.date_source,
+            dest_root: None,
+            run_subfolder: None,
+            existing_only: false,
+            conflict_policy: ConflictPolicy::default(),
+            conflict_suffix: config.rename.conflict_suffix,
+            dedupe_on_move: false,
+            jobs: 1,
+            symlinks: SymlinkPolicy::default(),
+            verify_moves: false,
+            hashing: HashOptions::from_config(&config.hashing),
+            fs: &RealFileSystem,
+            journal: None,
+        }
+    }
+
+    /// Hash settings for checking cross-device copies, None when --verify-moves is off
+    fn verification(&self) -> Option<&HashOptions> {
+        self.verify_moves.then_some(&self.hashing)
+    }
+
+    /// Folder that receives the organized category folders
+    pub fn dest_root<'p>(&'p self, source_dir: &'p str) -> &'p Path {
+        self.dest_root.as_deref().unwrap_or(Path::new(source_dir))
+    }
+
+    /// With `existing_only`, whether the folder for `folder_name` is missing
+    fn lacks_folder(&self, dest_root: &Path, folder_name: &str) -> bool {
+        self.existing_only && !self.fs.exists(&dest_root.join(folder_name))
+    }
+
+    /// Run subfolder used for `files_map`: `run_subfolder`, numbered (-2, -3...)
+    /// when one of the destination folders already has a folder of that name
+    fn run_folder(
+        &self,
+        dest_root: &Path,
+        files_map: &HashMap<String, Vec<PathBuf>>,
+    ) -> Option<String> {
+        let base = self.run_subfolder.as_ref()?;
+        let taken = |name: &String| {
+            files_map
+                .keys()
+                .any(|folder| self.fs.exists(&dest_root.join(folder).join(name)))
+        };
+        std::iter::once(base.clone())
+            .chain((2..).map(|n| format!("{}-{}", base, n)))
+            .find(|name| !taken(name))
+    }
+
+    /// Folder that receives the files of `folder_name`, inside the run subfolder if any
+    fn dest_dir(&self, dest_root: &Path, folder_name: &str, run: Option<&str>) -> PathBuf {
+        let dir = dest_root.join(folder_name);
+        match run {
+            Some(run) => dir.join(run),
+            None => dir,
+        }
+    }
+}
 
 pub struct MoveResult {
     pub stats: OrganizationStats,
     pub moves: Vec<FileMove>,
+    /// Folders that files were moved out of (candidates for --clean-empty)
+    pub touched_dirs: Vec<PathBuf>,
 }
 
-/// Move files to their destination folders with optional renaming
+/// Problems that stop a whole run before any file is moved.
+/// Per-file problems are not errors: they end up in `stats.skipped_files`.
+#[derive(Debug)]
+pub enum OrganizeError {
+    /// The source folder is missing or not a folder
+    NotADirectory(PathBuf),
+    /// The source folder cannot be listed
+    SourceUnreadable(PathBuf, io::Error),
+    /// The destination root cannot be created
+    DestinationUnavailable(PathBuf, io::Error),
+}
+
+impl std::fmt::Display for OrganizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrganizeError::NotADirectory(p) => write!(f, "Not a directory: {}", p.display()),
+            OrganizeError::SourceUnreadable(p, e) => {
+                write!(f, "Cannot read {}: {}", p.display(), e)
+            }
+            OrganizeError::DestinationUnavailable(p, e) => {
+                write!(f, "Cannot create destination {}: {}", p.display(), e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OrganizeError {}
+
+/// A file whose destination has been decided but not yet moved
+struct PlannedMove<'a> {
+    source: &'a Path,
+    dest: PathBuf,
+    size: u64,
+    /// Set when the source is a symbolic link that the policy lets through
+    symlink: Option<SymlinkPolicy>,
+}
+
+/// Move files to their destination folders with optional renaming.
+/// `progress` is told about every file, moved or skipped.
+/// Fails only when the source or destination folder is unusable.
 pub fn move_files(
     source_dir: &str,
     files_map: &HashMap<String, Vec<PathBuf>>,
-    rename_mode: Option<&RenameMode>,
-) -> MoveResult {
+    options: &OrganizeOptions,
+    progress: &dyn ProgressReporter,
+) -> Result<MoveResult, OrganizeError> {
+    let source = Path::new(source_dir);
+    if !source.is_dir() {
+        return Err(OrganizeError::NotADirectory(source.to_path_buf()));
+    }
+    fs::read_dir(source).map_err(|e| OrganizeError::SourceUnreadable(source.to_path_buf(), e))?;
+
+    let dest_root = options.dest_root(source_dir);
+    options
+        .fs
+        .create_dir_all(dest_root)
+        .map_err(|e| OrganizeError::DestinationUnavailable(dest_root.to_path_buf(), e))?;
+
     let mut stats = OrganizationStats::new();
     let mut moves: Vec<FileMove> = Vec::new();
+    let run = options.run_folder(dest_root, files_map);
 
-    let total: usize = files_map.values().map(|v| v.len()).sum();
-    let progress = ui::create_progress_bar(total as u64, "Organizing files...");
+    for (folder_name, files) in in_order(files_map) {
+        let dest_dir = options.dest_dir(dest_root, folder_name, run.as_deref());
+        let started = Instant::now();
 
-    for (folder_name, files) in files_map {
-        let dest_dir = Path::new(source_dir).join(folder_name);
+        if options.lacks_folder(dest_root, folder_name) {
+            for file_path in &files {
+                stats
+                    .add_skipped_with_reason(file_path.to_path_buf(), SkipReason::NoMatchingFolder);
+                progress.inc_file(path_size(file_path));
+            }
+            continue;
+        }
 
         // Try to create destination directory with proper error handling
-        if let Err(e) = fs::create_dir_all(&dest_dir) {
+        if let Err(e) = options.fs.create_dir_all(&dest_dir) {
             // Log all files that couldn't be moved due to directory creation failure
-            for file_path in files {
+            for file_path in &files {
                 stats.add_skipped_with_reason(
-                    file_path.clone(),
+                    file_path.to_path_buf(),
                     SkipReason::DirectoryCreationFailed(e.to_string()),
                 );
-                progress.inc(1);
+                progress.inc_file(path_size(file_path));
             }
             continue;
         }
 
+        if options.jobs > 1 {
+            let (partial, category_moves) =
+                move_category_parallel(folder_name, &files, &dest_dir, options, progress);
+            stats.merge(partial);
+            moves.extend(category_moves);
+            stats.add_category_duration(stats_category(folder_name), started.elapsed());
+            continue;
+        }
+
         for file_path in files {
-            let result = move_single_file(file_path, &dest_dir, rename_mode, &mut stats);
+            // Measured before the move, while the file is still here
+            let size = path_size(file_path);
+            let result = move_single_file(folder_name, file_path, &dest_dir, options, &mut stats);
             if let Some(file_move) = result {
                 moves.push(file_move);
             }
-            progress.inc(1);
+            progress.inc_file(size);
         }
+        stats.add_category_duration(stats_category(folder_name), started.elapsed());
     }
 
-    progress.finish_with_message("Done!");
+    progress.finish();
     stats.finish();
 
-    MoveResult { stats, moves }
+    let touched_dirs = source_dirs(&moves);
+    Ok(MoveResult {
+        stats,
+        moves,
+        touched_dirs,
+    })
+}
+
+/// Number of files (and folders) in `files_map`, across all categories
+pub fn file_count(files_map: &HashMap<String, Vec<PathBuf>>) -> usize {
+    files_map.values().map(Vec::len).sum()
+}
+
+/// Total size of every file (and folder) in `files_map`
+pub fn total_size(files_map: &HashMap<String, Vec<PathBuf>>) -> u64 {
+    files_map.values().flatten().map(|p| path_size(p)).sum()
+}
+
+/// Size of a file, or the total size of the files in a folder
+pub fn path_size(path: &Path) -> u64 {
+    if !path.is_dir() || is_symlink(path) {
+        return path.metadata().map(|m| m.len()).unwrap_or(0);
+    }
+
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| path_size(&e.path())).sum())
+        .unwrap_or(0)
 }
 
 /// Generate a preview of what would happen without making changes
 pub fn generate_dry_run_preview(
     source_dir: &str,
     files_map: &HashMap<String, Vec<PathBuf>>,
-    rename_mode: Option<&RenameMode>,
+    options: &OrganizeOptions,
 ) -> DryRunPreview {
     let mut preview = DryRunPreview::new();
+    let dest_root = options.dest_root(source_dir);
+    let run = options.run_folder(dest_root, files_map);
 
-    for (folder_name, files) in files_map {
-        let dest_dir = Path::new(source_dir).join(folder_name);
+    for (folder_name, files) in in_order(files_map) {
+        if options.lacks_folder(dest_root, folder_name) {
+            continue;
+        }
+        let dest_dir = options.dest_dir(dest_root, folder_name, run.as_deref());
 
         for file_path in files {
-            let size = file_path.metadata().map(|m| m.len()).unwrap_or(0);
-            let (new_name, is_rename) = get_new_name(file_path, rename_mode);
+            if options.symlinks == SymlinkPolicy::Skip && is_symlink(file_path) {
+                continue;
+            }
+
+            let size = path_size(file_path);
+            let (new_name, is_rename) = get_new_name(folder_name, file_path, options);
             let dest_path = dest_dir.join(&new_name);
+            if is_same_entry(file_path, &dest_path) {
+                continue;
+            }
 
-            preview.add_move(file_path.clone(), dest_path, size, is_rename);
+            preview.add_move(file_path.to_path_buf(), dest_path, size, is_rename);
         }
     }
 
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    for mv in &preview.moves {
+        *counts.entry(mv.to.clone()).or_insert(0) += 1;
+    }
+    for mv in &mut preview.moves {
+        mv.collides = counts[&mv.to] > 1;
+    }
+
     preview
 }
 
+/// The new name of every file, category by category, without moving anything.
+/// Files of a category that end up with the same name are marked as colliding.
+pub fn preview_renames(
+    files_map: &HashMap<String, Vec<PathBuf>>,
+    options: &OrganizeOptions,
+) -> Vec<PlannedRename> {
+    let mut renames = Vec::new();
+
+    for (category, files) in in_order(files_map) {
+        let start = renames.len();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file_path in files {
+            let (new_name, _) = get_new_name(category, file_path, options);
+            *counts.entry(new_name.clone()).or_insert(0) += 1;
+            renames.push(PlannedRename {
+                from: file_path.to_path_buf(),
+                to: new_name,
+                category: category.clone(),
+                collides: false,
+            });
+        }
+        for rename in &mut renames[start..] {
+            rename.collides = counts[&rename.to] > 1;
+        }
+    }
+
+    renames
+}
+
+/// Files that would get the same new name in the same folder, grouped by name.
+/// Without a choice from the user, all but the first of a group are suffixed.
+pub fn rename_collisions(
+    files_map: &HashMap<String, Vec<PathBuf>>,
+    options: &OrganizeOptions,
+) -> Vec<RenameCollision> {
+    let mut collisions: Vec<RenameCollision> = Vec::new();
+
+    for rename in preview_renames(files_map, options) {
+        if !rename.collides {
+            continue;
+        }
+        match collisions
+            .iter_mut()
+            .find(|c| c.category == rename.category && c.name == rename.to)
+        {
+            Some(collision) => collision.files.push(rename.from),
+            None => collisions.push(RenameCollision {
+                category: rename.category,
+                name: rename.to,
+                files: vec![rename.from],
+            }),
+        }
+    }
+
+    collisions
+}
+
+/// Take `excluded` out of `files_map`, dropping folders left without files
+pub fn exclude_files(files_map: &mut HashMap<String, Vec<PathBuf>>, excluded: &[PathBuf]) {
+    for files in files_map.values_mut() {
+        files.retain(|f| !excluded.contains(f));
+    }
+    files_map.retain(|_, files| !files.is_empty());
+}
+
+/// Leave out every file of a collision but the first, which keeps the name.
+/// Returns the files taken out of `files_map`.
+pub fn skip_collisions(
+    files_map: &mut HashMap<String, Vec<PathBuf>>,
+    collisions: &[RenameCollision],
+) -> Vec<PathBuf> {
+    let mut skipped = Vec::new();
+
+    for collision in collisions {
+        let Some(files) = files_map.get_mut(&collision.category) else {
+            continue;
+        };
+        for path in collision.files.iter().skip(1) {
+            files.retain(|f| f != path);
+            skipped.push(path.clone());
+        }
+    }
+    files_map.retain(|_, files| !files.is_empty());
+
+    skipped
+}
+
+/// Run subfolder name for a run started now
+pub fn run_subfolder_name() -> String {
+    Local::now().format(RUN_SUBFOLDER_FORMAT).to_string()
+}
+
 /// Record file moves to history for undo functionality
-pub fn record_moves(folder: &str, moves: Vec<FileMove>) {
+pub fn record_moves(folder: &str, moves: Vec<FileMove>) -> Result<(), String> {
+    if moves.is_empty() {
+        return Ok(());
+    }
+    history::record_operation(folder, moves)
+}
+
+/// Record file moves together with the folders --archive compressed afterwards
+pub fn record_archived_moves(
+    folder: &str,
+    moves: Vec<FileMove>,
+    archives: Vec<ArchivedFolder>,
+) -> Result<(), String> {
+    if moves.is_empty() && archives.is_empty() {
+        return Ok(());
+    }
+    history::record_archived_operation(folder, moves, archives)
+}
+
+/// Leave a --no-history marker so a later undo does not reach past this run
+pub fn mark_unrecorded(folder: &str, moves: &[FileMove]) {
     if !moves.is_empty() {
-        let _ = history::record_operation(folder, moves);
+        let _ = history::record_unrecorded_run(folder);
     }
 }
 
 /// Open folder in system file manager
 pub fn open_folder(path: &str) {
+    let _ = open_with_system(Path::new(path));
+}
+
+/// Open a file or folder with the platform's default application
+pub fn open_with_system(path: &Path) -> io::Result<Child> {
     let cmd = if cfg!(target_os = "macos") {
         "open"
     } else if cfg!(target_os = "windows") {
@@ -103,74 +477,335 @@ pub fn open_folder(path: &str) {
         "xdg-open"
     };
 
-    let _ = Command::new(cmd).arg(path).spawn();
+    Command::new(cmd).arg(path).spawn()
 }
 
 // ============================================================================
 // Private helpers
 // ============================================================================
 
+/// Categories by name, each with its files sorted by path, so that runs over
+/// the same tree move, report and record files in the same order
+fn in_order(files_map: &HashMap<String, Vec<PathBuf>>) -> Vec<(&String, Vec<&Path>)> {
+    let mut categories: Vec<_> = files_map
+        .iter()
+        .map(|(name, files)| {
+            let mut files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+            files.sort();
+            (name, files)
+        })
+        .collect();
+    categories.sort_by(|a, b| a.0.cmp(b.0));
+    categories
+}
+
 fn move_single_file(
+    folder_name: &str,
     file_path: &Path,
     dest_dir: &Path,
-    rename_mode: Option<&RenameMode>,
+    options: &OrganizeOptions,
     stats: &mut OrganizationStats,
 ) -> Option<FileMove> {
+    let plan = plan_move(
+        folder_name,
+        file_path,
+        dest_dir,
+        options,
+        stats,
+        &HashSet::new(),
+    )?;
+    execute_move(&plan, folder_name, options, stats)
+}
+
+/// Plan every destination up front (so workers never race for a name),
+/// then move the files across `options.jobs` threads
+fn move_category_parallel(
+    folder_name: &str,
+    files: &[&Path],
+    dest_dir: &Path,
+    options: &OrganizeOptions,
+    progress: &dyn ProgressReporter,
+) -> (OrganizationStats, Vec<FileMove>) {
+    let mut stats = OrganizationStats::default();
+    let mut reserved: HashSet<PathBuf> = HashSet::new();
+    let mut plans = Vec::new();
+
+    for file_path in files {
+        match plan_move(
+            folder_name,
+            file_path,
+            dest_dir,
+            options,
+            &mut stats,
+            &reserved,
+        ) {
+            Some(plan) => {
+                reserved.insert(plan.dest.clone());
+                plans.push(plan);
+            }
+            None => progress.inc_file(path_size(file_path)),
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let workers = options.jobs.min(plans.len());
+
+    // Moves are tagged with their plan's position to restore the planned order
+    let partials: Vec<(OrganizationStats, Vec<(usize, FileMove)>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut partial = OrganizationStats::default();
+                    let mut moves = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(plan) = plans.get(i) else {
+                            break;
+                        };
+                        if let Some(file_move) =
+                            execute_move(plan, folder_name, options, &mut partial)
+                        {
+                            moves.push((i, file_move));
+                        }
+                        progress.inc_file(plan.size);
+                    }
+                    (partial, moves)
+                })
+            })
+            .collect();
+
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    let mut moves = Vec::new();
+    for (partial, worker_moves) in partials {
+        stats.merge(partial);
+        moves.extend(worker_moves);
+    }
+    moves.sort_by_key(|(i, _)| *i);
+
+    (
+        stats,
+        moves.into_iter().map(|(_, file_move)| file_move).collect(),
+    )
+}
+
+/// Distinct parent folders of the moved files, sorted
+fn source_dirs(moves: &[FileMove]) -> Vec<PathBuf> {
+    moves
+        .iter()
+        .filter_map(|mv| Path::new(&mv.from).parent().map(Path::to_path_buf))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Decide where a file goes; None means it was skipped or deduplicated.
+/// `reserved` holds destinations already claimed by other files in this run.
+fn plan_move<'a>(
+    folder_name: &str,
+    file_path: &'a Path,
+    dest_dir: &Path,
+    options: &OrganizeOptions,
+    stats: &mut OrganizationStats,
+    reserved: &HashSet<PathBuf>,
+) -> Option<PlannedMove<'a>> {
     // Check if source file exists
-    if !file_path.exists() {
+    if !options.fs.exists(file_path) {
         stats.add_skipped_with_reason(file_path.to_path_buf(), SkipReason::FileNotFound);
         return None;
     }
 
-    let size = file_path.metadata().map(|m| m.len()).unwrap_or(0);
-    let (new_name, was_renamed) = get_new_name(file_path, rename_mode);
+    let symlink = is_symlink(file_path).then_some(options.symlinks);
+    if symlink == Some(SymlinkPolicy::Skip) {
+        stats.add_skipped_with_reason(file_path.to_path_buf(), SkipReason::Symlink);
+        return None;
+    }
+
+    let size = path_size(file_path);
+    let (new_name, was_renamed) = get_new_name(folder_name, file_path, options);
+    let target = dest_dir.join(&new_name);
+
+    // A re-run over organized folders finds files already at their destination;
+    // they are neither moved nor compared with themselves (--dedupe-on-move)
+    if is_same_entry(file_path, &target) {
+        stats.add_skipped_with_reason(file_path.to_path_buf(), SkipReason::AlreadyInPlace);
+        return None;
+    }
+
+    // Identical content already at the destination: drop the source instead
+    if options.dedupe_on_move
+        && options.fs.exists(&target)
+        && same_content(file_path, &target, &options.hashing)
+    {
+        match options.fs.remove_file(file_path) {
+            Ok(_) => stats.add_duplicate(),
+            Err(e) => {
+                stats.add_skipped_with_reason(file_path.to_path_buf(), categorize_io_error(&e))
+            }
+        }
+        return None;
+    }
+
+    let dest = match resolve_destination(
+        options.fs,
+        file_path,
+        &target,
+        options.conflict_policy,
+        options.conflict_suffix,
+        reserved,
+    ) {
+        Some(p) => p,
+        None => {
+            stats.add_skipped_with_reason(file_path.to_path_buf(), SkipReason::ConflictSkipped);
+            return None;
+        }
+    };
 
     if was_renamed {
         stats.add_renamed();
     }
 
-    let dest_path = resolve_conflict(&dest_dir.join(&new_name));
-    let from = file_path.to_string_lossy().to_string();
-    let to = dest_path.to_string_lossy().to_string();
+    Some(PlannedMove {
+        source: file_path,
+        dest,
+        size,
+        symlink,
+    })
+}
+
+fn execute_move(
+    plan: &PlannedMove,
+    folder_name: &str,
+    options: &OrganizeOptions,
+    stats: &mut OrganizationStats,
+) -> Option<FileMove> {
+    let from = plan.source.to_string_lossy().to_string();
+    let to = plan.dest.to_string_lossy().to_string();
+
+    let moved = match plan.symlink {
+        Some(SymlinkPolicy::MoveLink) => {
+            move_symlink(plan.source, &plan.dest).map_err(|e| categorize_io_error(&e))
+        }
+        Some(SymlinkPolicy::Follow) => {
+            replace_link_with_copy(plan.source, &plan.dest).map_err(|e| categorize_io_error(&e))
+        }
+        _ => move_file_checked(options.fs, plan.source, &plan.dest, options.verification()),
+    };
 
-    match move_file_with_fallback(file_path, &dest_path) {
+    match moved {
         Ok(_) => {
-            let folder = dest_dir.file_name()?.to_string_lossy().to_string();
-            stats.add_file(&folder, size);
-            Some(FileMove { from, to })
+            stats.add_file(stats_category(folder_name), plan.size);
+            let file_move = FileMove {
+                from,
+                to,
+                is_rename: plan.source.file_name() != plan.dest.file_name(),
+            };
+            if let Some(journal) = options.journal {
+                journal.append(&file_move);
+            }
+            Some(file_move)
         }
-        Err(e) => {
-            let reason = categorize_io_error(&e);
-            stats.add_skipped_with_reason(file_path.to_path_buf(), reason);
+        Err(reason) => {
+            stats.add_skipped_with_reason(plan.source.to_path_buf(), reason);
             None
         }
     }
 }
 
-/// Move a file, falling back to copy+delete for cross-device moves
-fn move_file_with_fallback(src: &Path, dest: &Path) -> io::Result<()> {
-    match fs::rename(src, dest) {
+/// Like move_file_with_fallback, but with `verify` a cross-device copy must
+/// hash the same as the source before the source is deleted. On a mismatch
+/// the copy is removed and the source kept.
+fn move_file_checked(
+    fs: &dyn FileSystem,
+    src: &Path,
+    dest: &Path,
+    verify: Option<&HashOptions>,
+) -> Result<(), SkipReason> {
+    match fs.rename(src, dest) {
+        Ok(_) => Ok(()),
+        Err(e) if filesystem::is_cross_device_error(&e) => {
+            fs.copy(src, dest).map_err(|e| categorize_io_error(&e))?;
+            if verify.is_some_and(|hashing| !copy_matches(src, dest, hashing)) {
+                let _ = remove_path(fs, dest);
+                return Err(SkipReason::VerificationFailed);
+            }
+            remove_path(fs, src).map_err(|e| categorize_io_error(&e))
+        }
+        Err(e) => Err(categorize_io_error(&e)),
+    }
+}
+
+/// Move a file or folder, falling back to copy+delete for cross-device moves
+pub fn move_file_with_fallback(src: &Path, dest: &Path) -> io::Result<()> {
+    move_file_with_fallback_in(&RealFileSystem, src, dest)
+}
+
+/// move_file_with_fallback on the given file system
+pub fn move_file_with_fallback_in(fs: &dyn FileSystem, src: &Path, dest: &Path) -> io::Result<()> {
+    match fs.rename(src, dest) {
         Ok(_) => Ok(()),
-        Err(e) if is_cross_device_error(&e) => {
+        Err(e) if filesystem::is_cross_device_error(&e) => {
             // Cross-device move: fall back to copy + delete
-            fs::copy(src, dest)?;
-            fs::remove_file(src)?;
-            Ok(())
+            fs.copy(src, dest)?;
+            remove_path(fs, src)
         }
         Err(e) => Err(e),
     }
 }
 
-/// Check if an IO error is a cross-device link error (EXDEV)
-fn is_cross_device_error(e: &io::Error) -> bool {
-    // EXDEV = 18 on Unix (Linux, macOS, BSD)
-    // Windows uses ERROR_NOT_SAME_DEVICE = 17
+/// Remove a file, or a folder with everything in it (a link is removed, not followed)
+fn remove_path(fs: &dyn FileSystem, path: &Path) -> io::Result<()> {
+    match fs.metadata(path) {
+        Ok(info) if info.is_dir => fs.remove_dir_all(path),
+        _ => fs.remove_file(path),
+    }
+}
+
+/// Whether a copied file (or every file of a copied folder) matches its source
+fn copy_matches(src: &Path, dest: &Path, hashing: &HashOptions) -> bool {
+    if !src.is_dir() {
+        return same_content(src, dest, hashing);
+    }
+
+    fs::read_dir(src)
+        .map(|entries| {
+            entries.flatten().all(|entry| {
+                let path = entry.path();
+                is_symlink(&path) || copy_matches(&path, &dest.join(entry.file_name()), hashing)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Whether the path itself is a symbolic link (the link is not followed)
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Recreate the link at `dest`, then remove the original.
+/// Relative targets are made absolute so the moved link still resolves.
+fn move_symlink(src: &Path, dest: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+    let target = match src.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target,
+    };
+
     #[cfg(unix)]
-    const CROSS_DEVICE_ERROR: i32 = 18; // EXDEV
+    std::os::unix::fs::symlink(&target, dest)?;
     #[cfg(windows)]
-    const CROSS_DEVICE_ERROR: i32 = 17; // ERROR_NOT_SAME_DEVICE
+    std::os::windows::fs::symlink_file(&target, dest)?;
+
+    fs::remove_file(src)
+}
 
-    e.raw_os_error() == Some(CROSS_DEVICE_ERROR)
+/// Copy the link's target to `dest` and drop the link; the target stays where it is
+fn replace_link_with_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::copy(src, dest)?;
+    fs::remove_file(src)
 }
 
 /// Categorize IO errors into user-friendly skip reasons
@@ -182,15 +817,32 @@ fn categorize_io_error(e: &io::Error) -> SkipReason {
     }
 }
 
-fn get_new_name(file_path: &Path, rename_mode: Option<&RenameMode>) -> (String, bool) {
+/// New name of a file going to `folder_name` (a key of the files map)
+fn get_new_name(folder_name: &str, file_path: &Path, options: &OrganizeOptions) -> (String, bool) {
     let original = file_path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_default();
 
-    match rename_mode {
+    if let Some(name) = options.names.get(file_path) {
+        return (name.clone(), *name != original);
+    }
+
+    // Folders moved as a unit keep their name
+    if file_path.is_dir() {
+        return (original, false);
+    }
+
+    match &options.rename_mode {
         Some(mode) => {
-            let renamed = renamer::rename_file(file_path, mode);
+            let category = destination_category(folder_name, file_path, options);
+            let renamed = renamer::rename_file(
+                file_path,
+                mode,
+                &category,
+                options.date_source,
+                options.rename,
+            );
             let was_renamed = renamed != original;
             (renamed, was_renamed)
         }
@@ -198,8 +850,90 @@ fn get_new_name(file_path: &Path, rename_mode: Option<&RenameMode>) -> (String,
     }
 }
 
-fn resolve_conflict(path: &Path) -> PathBuf {
-    if !path.exists() {
+/// Category for {category}: the first part of the folder the file goes to
+/// (Documents/2024 → Documents). Date folders (2024/03-march) name no
+/// category, so the file's extension is looked up as the scanner does.
+fn destination_category(folder_name: &str, file_path: &Path, options: &OrganizeOptions) -> String {
+    let first = folder_name.split('/').next().unwrap_or_default();
+    if !first.is_empty() && !first.bytes().all(|b| b.is_ascii_digit()) {
+        return first.to_string();
+    }
+
+    let ext = file_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    config::resolve_folder(options.categories, options.overrides, &ext)
+}
+
+/// Apply the conflict policy; None means the file should stay where it is.
+/// A destination reserved by another file in the run is renamed around (or skipped)
+/// rather than overwritten, since the order of parallel moves is not fixed.
+fn resolve_destination(
+    fs: &dyn FileSystem,
+    src: &Path,
+    dest: &Path,
+    policy: ConflictPolicy,
+    suffix: ConflictSuffix,
+    reserved: &HashSet<PathBuf>,
+) -> Option<PathBuf> {
+    if reserved.contains(dest) {
+        return match policy {
+            ConflictPolicy::Skip => None,
+            _ => Some(resolve_conflict(fs, dest, suffix, reserved)),
+        };
+    }
+
+    if !fs.exists(dest) {
+        return Some(dest.to_path_buf());
+    }
+
+    match policy {
+        ConflictPolicy::Rename => Some(resolve_conflict(fs, dest, suffix, reserved)),
+        ConflictPolicy::Skip => None,
+        ConflictPolicy::Overwrite => Some(dest.to_path_buf()),
+        ConflictPolicy::KeepNewer => is_newer(src, dest).then(|| dest.to_path_buf()),
+    }
+}
+
+/// Whether two paths name the same directory entry. Only the parents are
+/// resolved, so a link and the file it points to stay different entries.
+fn is_same_entry(a: &Path, b: &Path) -> bool {
+    let resolve = |p: &Path| {
+        let parent = p.parent().filter(|d| !d.as_os_str().is_empty());
+        let parent = fs::canonicalize(parent.unwrap_or(Path::new("."))).ok()?;
+        Some(parent.join(p.file_name()?))
+    };
+    a == b || matches!((resolve(a), resolve(b)), (Some(x), Some(y)) if x == y)
+}
+
+fn same_content(a: &Path, b: &Path, hashing: &HashOptions) -> bool {
+    match (
+        duplicates::hash_file(a, hashing),
+        duplicates::hash_file(b, hashing),
+    ) {
+        (Ok((hash_a, size_a)), Ok((hash_b, size_b))) => size_a == size_b && hash_a == hash_b,
+        _ => false,
+    }
+}
+
+fn is_newer(src: &Path, dest: &Path) -> bool {
+    let modified = |p: &Path| p.metadata().and_then(|m| m.modified()).ok();
+    match (modified(src), modified(dest)) {
+        (Some(s), Some(d)) => s > d,
+        _ => false,
+    }
+}
+
+/// First free name for `path` with the configured suffix
+pub fn resolve_conflict(
+    fs: &dyn FileSystem,
+    path: &Path,
+    suffix: ConflictSuffix,
+    reserved: &HashSet<PathBuf>,
+) -> PathBuf {
+    let taken = |p: &Path| fs.exists(p) || reserved.contains(p);
+    if !taken(path) {
         return path.to_path_buf();
     }
 
@@ -210,8 +944,28 @@ fn resolve_conflict(path: &Path) -> PathBuf {
         .unwrap_or_default();
     let parent = path.parent().unwrap_or(Path::new("."));
 
+    let numbered = |stem: &str, i: usize| match suffix {
+        ConflictSuffix::ParenNumber => format!("{} ({}){}", stem, i, ext),
+        ConflictSuffix::UnderscoreNumber => format!("{}_{}{}", stem, i, ext),
+        ConflictSuffix::DashNumber | ConflictSuffix::Timestamp => {
+            format!("{}-{}{}", stem, i, ext)
+        }
+    };
+
+    // The date alone usually suffices; a second copy the same day gets a counter
+    let stem = if suffix == ConflictSuffix::Timestamp {
+        let dated = format!("{}-{}", stem, Local::now().format("%Y%m%d"));
+        let candidate = parent.join(format!("{}{}", dated, ext));
+        if !taken(&candidate) {
+            return candidate;
+        }
+        dated
+    } else {
+        stem.to_string()
+    };
+
     (1..)
-        .map(|i| parent.join(format!("{}-{}{}", stem, i, ext)))
-        .find(|p| !p.exists())
+        .map(|i| parent.join(numbered(&stem, i)))
+        .find(|p| !taken(p))
         .unwrap_or_else(|| path.to_path_buf())
 }
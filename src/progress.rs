@@ -0,0 +1,55 @@
+// Stellar - Progress Module
+// @musem23
+//
+// Progress reporting for long-running engine functions (moves, hashing).
+// The terminal passes an indicatif bar; tests and library users can pass NoProgress.
+
+use indicatif::ProgressBar;
+
+/// Told about work done by moves and duplicate scans; shared across worker threads
+pub trait ProgressReporter: Sync {
+    /// `n` more items were handled (done or skipped)
+    fn inc(&self, n: u64);
+    /// One file (or folder) of `bytes` was moved or skipped; counts as one item
+    /// unless the reporter tracks data volume
+    fn inc_file(&self, _bytes: u64) {
+        self.inc(1);
+    }
+    /// All items were handled
+    fn finish(&self);
+}
+
+/// Reporter that ignores progress
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn inc(&self, _n: u64) {}
+    fn finish(&self) {}
+}
+
+/// Byte-weighted bar for moves: advances by each file's size, so one huge
+/// file no longer looks stuck and the ETA follows the data volume
+pub struct ByteProgress(pub ProgressBar);
+
+impl ProgressReporter for ByteProgress {
+    /// Item counts are meaningless on a byte scale
+    fn inc(&self, _n: u64) {}
+
+    fn inc_file(&self, bytes: u64) {
+        self.0.inc(bytes);
+    }
+
+    fn finish(&self) {
+        self.0.finish_with_message("Done!");
+    }
+}
+
+impl ProgressReporter for ProgressBar {
+    fn inc(&self, n: u64) {
+        ProgressBar::inc(self, n);
+    }
+
+    fn finish(&self) {
+        self.finish_with_message("Done!");
+    }
+}
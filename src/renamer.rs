@@ -3,20 +3,42 @@
 //
 // Renames files using different strategies:
 // - Clean: lowercase, dashes, remove accents and duplicates (élève → eleve)
-// - DatePrefix: prepend modification date (2024-01-15-filename)
+// - SnakeCase / TitleCase: same cleanup with underscores or capitalized words
+// - DatePrefix: prepend creation or modification date (2024-01-15-filename)
+// - Template: expand placeholders like {date}_{category}_{name}.{ext}
 // Uses Unicode normalization (NFD) to handle accented characters.
 
-use chrono::{DateTime, Local};
 use std::path::Path;
 use unicode_normalization::UnicodeNormalization;
 
+use crate::config::RenameConfig;
+use crate::modes::DateSource;
+use crate::scanner;
+
+#[derive(Clone)]
 pub enum RenameMode {
     Clean,
+    SnakeCase,
+    TitleCase,
     DatePrefix,
+    Template(String),
 }
 
+/// Placeholders understood by `apply_template`
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["name", "ext", "date", "year", "category"];
+
 /// Rename a file according to the specified mode
-pub fn rename_file(path: &Path, mode: &RenameMode) -> String {
+pub fn rename_file(
+    path: &Path,
+    mode: &RenameMode,
+    category: &str,
+    date_source: DateSource,
+    options: &RenameConfig,
+) -> String {
+    if let RenameMode::Template(template) = mode {
+        return apply_template(path, template, category, date_source, options);
+    }
+
     let stem = path
         .file_stem()
         .map(|s| s.to_string_lossy().to_string())
@@ -25,11 +47,14 @@ pub fn rename_file(path: &Path, mode: &RenameMode) -> String {
     let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
 
     let new_stem = match mode {
-        RenameMode::Clean => slugify(&stem),
+        RenameMode::Clean => slugify(&stem, options),
+        RenameMode::SnakeCase => snake_case(&stem, options),
+        RenameMode::TitleCase => title_case(&stem, options),
         RenameMode::DatePrefix => {
-            let date = get_file_date(path);
-            format!("{}-{}", date, slugify(&stem))
+            let date = get_file_date(path, date_source);
+            format!("{}-{}", date, slugify(&stem, options))
         }
+        RenameMode::Template(_) => unreachable!("templates are expanded above"),
     };
 
     match ext {
@@ -38,10 +63,69 @@ pub fn rename_file(path: &Path, mode: &RenameMode) -> String {
     }
 }
 
+/// Expand a rename template: {name} (slugified stem), {ext}, {date}, {year}, {category}
+/// Unknown placeholders are left as-is (see `unknown_placeholders`)
+pub fn apply_template(
+    path: &Path,
+    template: &str,
+    category: &str,
+    date_source: DateSource,
+    options: &RenameConfig,
+) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    // Avoid a trailing dot for extensionless files
+    let template = if ext.is_empty() {
+        template.replace(".{ext}", "")
+    } else {
+        template.to_string()
+    };
+
+    let date = get_file_date(path, date_source);
+    let expanded = template
+        .replace("{name}", &slugify(&stem, options))
+        .replace("{ext}", &ext)
+        .replace("{date}", &date)
+        .replace("{year}", &date[..4])
+        .replace("{category}", category);
+
+    // Templates produce a file name, never a path
+    expanded.replace(['/', '\\'], "-")
+}
+
+/// List placeholders in a template that `apply_template` does not know
+pub fn unknown_placeholders(template: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) => {
+                let name = &after[..close];
+                if !TEMPLATE_PLACEHOLDERS.contains(&name) && !unknown.iter().any(|u| u == name) {
+                    unknown.push(name.to_string());
+                }
+                rest = &after[close + 1..];
+            }
+            None => break,
+        }
+    }
+
+    unknown
+}
+
 /// Convert text to a clean, URL-friendly slug
 /// Handles accents: élève café → eleve-cafe
-fn slugify(text: &str) -> String {
-    let normalized: String = text.nfd().collect();
+fn slugify(text: &str, options: &RenameConfig) -> String {
+    let normalized: String = strip_os_dedup_markers(text).nfd().collect();
 
     let mut result = String::with_capacity(text.len());
     let mut prev_dash = true; // Start true to avoid leading dash
@@ -56,19 +140,125 @@ fn slugify(text: &str) -> String {
         }
     }
 
-    remove_copy_suffixes(&result)
+    remove_copy_suffixes(&result, options)
+}
+
+/// Clean slug with underscores: élève café → eleve_cafe
+fn snake_case(text: &str, options: &RenameConfig) -> String {
+    slugify(text, options).replace('-', "_")
 }
 
-/// Remove common duplicate suffixes like (1), -copy, (copie)
-fn remove_copy_suffixes(name: &str) -> String {
-    const PATTERNS: &[&str] = &[
-        "-1", "-2", "-3", "-4", "-5", "-6", "-7", "-8", "-9", "-copy", "-copie",
-    ];
+/// Clean slug with capitalized words: élève café → Eleve Cafe
+fn title_case(text: &str, options: &RenameConfig) -> String {
+    slugify(text, options)
+        .split('-')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Remove duplicate markers added by file managers and browsers
+/// - macOS: "photo copy", "photo copy 2"
+/// - Windows: "report - Copy", "report - Copy (2)"
+/// - Chrome/Firefox: "report (1)"
+pub fn strip_os_dedup_markers(stem: &str) -> String {
+    let mut result = stem.trim_end();
+
+    loop {
+        let before = result.len();
+        result = strip_paren_counter(result).trim_end();
+        result = strip_copy_marker(result).trim_end();
+        result = result.strip_suffix(" -").unwrap_or(result).trim_end();
+
+        if result.len() == before {
+            break;
+        }
+    }
 
+    if result.is_empty() {
+        stem.to_string()
+    } else {
+        result.to_string()
+    }
+}
+
+/// "report (1)" → "report"
+fn strip_paren_counter(stem: &str) -> &str {
+    let Some(inner) = stem.strip_suffix(')') else {
+        return stem;
+    };
+
+    match inner.rfind('(') {
+        Some(open)
+            if open > 0
+                && inner.len() > open + 1
+                && inner[open + 1..].chars().all(|c| c.is_ascii_digit()) =>
+        {
+            &inner[..open]
+        }
+        _ => stem,
+    }
+}
+
+/// "photo copy 2" → "photo", "photo copy" → "photo"
+fn strip_copy_marker(stem: &str) -> &str {
+    const COPY_WORDS: &[&str] = &[" copy", " copie"];
+
+    let without_counter = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let candidates = [stem, without_counter.strip_suffix(' ').unwrap_or(stem)];
+
+    for candidate in candidates {
+        let lower = candidate.to_ascii_lowercase();
+        for word in COPY_WORDS {
+            if lower.ends_with(word) && candidate.len() > word.len() {
+                return &candidate[..candidate.len() - word.len()];
+            }
+        }
+    }
+
+    stem
+}
+
+/// Remove copy suffixes like -copy, -copie, -copy-2 (and -1..-9 when opted in)
+fn remove_copy_suffixes(name: &str, options: &RenameConfig) -> String {
     let mut result = name.to_string();
-    for pattern in PATTERNS {
-        if result.ends_with(pattern) {
-            result.truncate(result.len() - pattern.len());
+
+    loop {
+        let before = result.len();
+
+        // A copy word followed by a counter: photo-copy-2
+        if let Some(stripped) = strip_single_digit(&result) {
+            if options
+                .strip_suffixes
+                .iter()
+                .any(|w| ends_with_word(stripped, w))
+            {
+                result.truncate(stripped.len());
+            }
+        }
+
+        for word in &options.strip_suffixes {
+            if ends_with_word(&result, word) {
+                result.truncate(result.len() - word.len() - 1);
+            }
+        }
+
+        // A bare counter is only stripped on request: blade-runner-2 keeps its 2
+        if options.strip_numeric_suffixes {
+            if let Some(stripped) = strip_single_digit(&result) {
+                result.truncate(stripped.len());
+            }
+        }
+
+        if result.len() == before {
+            break;
         }
     }
 
@@ -79,13 +269,21 @@ fn remove_copy_suffixes(name: &str) -> String {
     result.trim_matches('-').to_string()
 }
 
-fn get_file_date(path: &Path) -> String {
-    path.metadata()
-        .ok()
-        .and_then(|m| m.modified().ok())
-        .map(|t| {
-            let dt: DateTime<Local> = t.into();
-            dt.format("%Y-%m-%d").to_string()
-        })
-        .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string())
+/// "name-3" → Some("name") for a single 1-9 digit counter
+fn strip_single_digit(name: &str) -> Option<&str> {
+    let stripped = name.strip_suffix(|c: char| ('1'..='9').contains(&c))?;
+    let stripped = stripped.strip_suffix('-')?;
+    (!stripped.is_empty()).then_some(stripped)
+}
+
+fn ends_with_word(name: &str, word: &str) -> bool {
+    name.len() > word.len() + 1
+        && name.ends_with(word)
+        && name[..name.len() - word.len()].ends_with('-')
+}
+
+fn get_file_date(path: &Path, source: DateSource) -> String {
+    scanner::file_datetime(path, source)
+        .format("%Y-%m-%d")
+        .to_string()
 }
@@ -4,14 +4,28 @@
 // Scans directories for files and groups them by category, date, or hybrid mode.
 // Supports recursive scanning while respecting project folders and protected paths.
 // Detects project folders by common indicators (.git, package.json, Cargo.toml, etc.)
+// Can optionally classify by content (magic bytes) instead of trusting the extension.
+// Files and folders matching the folder's .stellarignore are never organized.
 
-use chrono::{DateTime, Datelike, Local};
+use chrono::{DateTime, Datelike, Local, TimeDelta};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use crate::config;
+use crate::config::{self, CompiledRule, Config, Protected};
+use crate::lock::LOCK_FILE_NAME;
+use crate::modes::{DateGranularity, DateSource, UnmatchedPolicy};
+use crate::organizer;
 
+/// Per-folder list of glob patterns for files Stellar must leave alone
+pub const IGNORE_FILE_NAME: &str = ".stellarignore";
+
+/// Loose files tolerated by `looks_organized` (a few new downloads)
+const ORGANIZED_MAX_LOOSE_FILES: usize = 2;
+
+/// Built-in project markers; `[protected] project_indicators` adds to these
 const PROJECT_INDICATORS: &[&str] = &[
     ".git",
     ".svn",
@@ -48,74 +62,339 @@ const PROTECTED_SUBFOLDERS: &[&str] = &[
     "obj",
 ];
 
-const MONTHS: &[&str] = &[
-    "01-january",
-    "02-february",
-    "03-march",
-    "04-april",
-    "05-may",
-    "06-june",
-    "07-july",
-    "08-august",
-    "09-september",
-    "10-october",
-    "11-november",
-    "12-december",
+/// Bytes read from the start of a file when sniffing its type
+const SNIFF_LEN: usize = 16;
+
+/// Formats stored as ZIP archives; their own extension is more precise than the magic
+const ZIP_CONTAINERS: &[&str] = &[
+    "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub", "jar", "apk", "ipa", "pages", "numbers",
+    "key",
 ];
 
+/// Settings that control how files are grouped during a scan
+pub struct ScanOptions<'a> {
+    pub categories: &'a HashMap<String, Vec<String>>,
+    pub overrides: &'a HashMap<String, String>,
+    pub date_source: DateSource,
+    /// None keeps each mode's default (date: month, hybrid: year)
+    pub date_granularity: Option<DateGranularity>,
+    /// Organize dot-prefixed files (OS junk and the lock file are always skipped)
+    pub include_hidden: bool,
+    /// Descend into dot-prefixed folders during recursive scans
+    pub include_hidden_dirs: bool,
+    /// Deepest subfolder level a recursive scan visits (1 = immediate subfolders, None = no limit)
+    pub max_depth: Option<usize>,
+    /// Subfolders containing one of these are left alone by recursive scans
+    pub project_indicators: Vec<String>,
+    /// Classify by magic bytes when they are known, falling back to the extension
+    pub sniff: bool,
+    /// Category folder for files without an extension
+    pub no_extension_folder: &'a str,
+    /// Only files at least this old (by `date_source`)
+    pub older_than: Option<TimeDelta>,
+    /// Only files at most this old (combines with `older_than` into a window)
+    pub newer_than: Option<TimeDelta>,
+    /// Files left out by the age filter, so the caller can report them as skipped
+    pub filtered_by_age: RefCell<Vec<PathBuf>>,
+    /// Files and folders matching the scanned folder's .stellarignore, reported as skipped
+    pub ignored: RefCell<Vec<PathBuf>>,
+    /// `[[rules]]` from the config, checked before the categories
+    pub rules: Vec<CompiledRule>,
+    /// Extensions that matched no category or override, with file counts
+    pub unknown_extensions: RefCell<HashMap<String, usize>>,
+    /// Where those files go (`[preferences] unmatched_policy`)
+    pub unmatched: &'a UnmatchedPolicy,
+    /// Files left in place by `UnmatchedPolicy::Leave`, reported as skipped
+    pub left_unmatched: RefCell<Vec<PathBuf>>,
+    /// Move each top-level subfolder as a whole into `folders_category`
+    pub with_folders: bool,
+    pub folders_category: &'a str,
+    /// Month folder names, January first (`[date] month_names`, already validated)
+    pub month_names: Vec<String>,
+}
+
+impl<'a> ScanOptions<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            categories: &config.categories,
+            overrides: &config.overrides,
+            date_source: config.preferences.date_source,
+            date_granularity: config.preferences.date_granularity,
+            include_hidden: false,
+            include_hidden_dirs: false,
+            max_depth: None,
+            project_indicators: project_indicators(&config.protected),
+            sniff: false,
+            no_extension_folder: config
+                .preferences
+                .no_extension_folder
+                .as_deref()
+                .unwrap_or("Others"),
+            older_than: None,
+            newer_than: None,
+            filtered_by_age: RefCell::new(Vec::new()),
+            ignored: RefCell::new(Vec::new()),
+            // read_config already rejected invalid rules
+            rules: config::compile_rules(&config.rules).unwrap_or_default(),
+            unknown_extensions: RefCell::new(HashMap::new()),
+            unmatched: &config.preferences.unmatched_policy,
+            left_unmatched: RefCell::new(Vec::new()),
+            with_folders: false,
+            folders_category: config
+                .preferences
+                .folders_category
+                .as_deref()
+                .unwrap_or("Folders"),
+            month_names: config.date.months(),
+        }
+    }
+}
+
+/// Patterns of a folder's .stellarignore, one glob per line (`#` comments).
+/// A pattern without a slash matches a name at any depth, one with a slash
+/// matches the path relative to the folder, and a trailing slash only matches
+/// folders (everything inside them is left alone too).
+pub struct IgnoreList {
+    root: PathBuf,
+    patterns: Vec<IgnorePattern>,
+    /// Lines that are not valid globs, left out of the list
+    pub invalid: Vec<String>,
+}
+
+struct IgnorePattern {
+    glob: glob::Pattern,
+    /// Matched against the relative path rather than the name
+    anchored: bool,
+    dir_only: bool,
+}
+
+impl IgnoreList {
+    /// Read `folder/.stellarignore`; a missing file ignores nothing
+    pub fn load(folder: &Path) -> Self {
+        let content = fs::read_to_string(folder.join(IGNORE_FILE_NAME)).unwrap_or_default();
+        Self::parse(folder, &content)
+    }
+
+    pub fn parse(folder: &Path, content: &str) -> Self {
+        let mut list = Self {
+            root: folder.to_path_buf(),
+            patterns: Vec::new(),
+            invalid: Vec::new(),
+        };
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let dir_only = line.ends_with('/');
+            let pattern = line.trim_end_matches('/');
+            let anchored = pattern.contains('/');
+            match glob::Pattern::new(pattern.trim_start_matches('/')) {
+                Ok(glob) => list.patterns.push(IgnorePattern {
+                    glob,
+                    anchored,
+                    dir_only,
+                }),
+                Err(_) => list.invalid.push(line.to_string()),
+            }
+        }
+        list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether `path` (inside the folder) matches one of the patterns
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let relative: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let Some(name) = relative.last() else {
+            return false;
+        };
+        let relative = relative.join("/");
+        let is_dir = path.is_dir();
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        self.patterns.iter().any(|p| {
+            (is_dir || !p.dir_only)
+                && if p.anchored {
+                    p.glob.matches_with(&relative, options)
+                } else {
+                    p.glob.matches_with(name, options)
+                }
+        })
+    }
+}
+
+/// Built-in project markers merged with the ones from config
+pub fn project_indicators(protected: &Protected) -> Vec<String> {
+    let mut indicators: Vec<String> = PROJECT_INDICATORS.iter().map(|s| s.to_string()).collect();
+    for extra in &protected.project_indicators {
+        if !indicators.contains(extra) {
+            indicators.push(extra.clone());
+        }
+    }
+    indicators
+}
+
 /// Check if a folder contains project indicator files
-pub fn is_project_folder(path: &str) -> bool {
+pub fn is_project_folder(path: &str, indicators: &[String]) -> bool {
     let path = Path::new(path);
-    PROJECT_INDICATORS
+    indicators
         .iter()
         .any(|indicator| path.join(indicator).exists())
 }
 
+/// Whether a folder looks like the output of an earlier run: at least two
+/// subfolders, most of them named after a category (or a year), and at most
+/// a couple of loose files. A recursive scan would reshuffle such a folder.
+pub fn looks_organized(path: &str, categories: &HashMap<String, Vec<String>>) -> bool {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut organized_dirs = 0;
+    let mut other_dirs = 0;
+    let mut loose_files = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => {
+                let is_year = name.len() == 4 && name.chars().all(|c| c.is_ascii_digit());
+                let is_category = name.eq_ignore_ascii_case("others")
+                    || categories.keys().any(|c| c.eq_ignore_ascii_case(&name));
+                if is_category || is_year {
+                    organized_dirs += 1;
+                } else {
+                    other_dirs += 1;
+                }
+            }
+            Ok(_) => loose_files += 1,
+            Err(_) => {}
+        }
+    }
+
+    organized_dirs >= 2
+        && organized_dirs >= 2 * other_dirs
+        && loose_files <= ORGANIZED_MAX_LOOSE_FILES
+}
+
 /// Scan files and group by category
-pub fn scan_by_category(
-    source_dir: &str,
-    categories: &HashMap<String, Vec<String>>,
-) -> HashMap<String, Vec<PathBuf>> {
-    scan_files(source_dir, |_path, ext| {
-        config::find_category(categories, ext).unwrap_or_else(|| "Others".into())
+pub fn scan_by_category(source_dir: &str, options: &ScanOptions) -> HashMap<String, Vec<PathBuf>> {
+    scan_files(source_dir, options, |path, ext| {
+        category_folder(path, options, ext)
+    })
+}
+
+/// Scan files and group by the first matching `[[rules]]` entry,
+/// falling back to the category when no rule matches
+pub fn scan_by_rules(source_dir: &str, options: &ScanOptions) -> HashMap<String, Vec<PathBuf>> {
+    scan_files(source_dir, options, |path, ext| {
+        rule_folder(path, options, ext).or_else(|| category_folder(path, options, ext))
     })
 }
 
 /// Scan files and group by year/month
-pub fn scan_by_date(source_dir: &str) -> HashMap<String, Vec<PathBuf>> {
-    scan_files(source_dir, |path, _| get_date_folder(path))
+pub fn scan_by_date(source_dir: &str, options: &ScanOptions) -> HashMap<String, Vec<PathBuf>> {
+    scan_files(source_dir, options, |path, _| {
+        Some(date_folder(path, options, DateGranularity::Month))
+    })
 }
 
 /// Scan files and group by category/year (hybrid)
-pub fn scan_hybrid(
-    source_dir: &str,
-    categories: &HashMap<String, Vec<String>>,
-) -> HashMap<String, Vec<PathBuf>> {
-    scan_files(source_dir, |path, ext| {
-        let category = config::find_category(categories, ext).unwrap_or_else(|| "Others".into());
-        let year = get_year(path);
-        format!("{}/{}", category, year)
+pub fn scan_hybrid(source_dir: &str, options: &ScanOptions) -> HashMap<String, Vec<PathBuf>> {
+    scan_files(source_dir, options, |path, ext| {
+        let category = category_folder(path, options, ext)?;
+        let date = date_folder(path, options, DateGranularity::Year);
+        Some(format!("{}/{}", category, date))
     })
 }
 
-/// Recursively scan subdirectories (skips project/protected folders)
+/// Recursively scan subdirectories (skips project/protected folders),
+/// down to `options.max_depth` levels below the source folder
 pub fn scan_recursive(
     source_dir: &str,
-    categories: &HashMap<String, Vec<String>>,
+    options: &ScanOptions,
     org_mode: usize,
 ) -> HashMap<String, Vec<PathBuf>> {
     let mut results: HashMap<String, Vec<PathBuf>> = HashMap::new();
-    scan_recursive_inner(source_dir, source_dir, categories, org_mode, &mut results);
+    let ignore = IgnoreList::load(Path::new(source_dir));
+    scan_recursive_inner(source_dir, 0, options, &ignore, org_mode, &mut results);
     results
 }
 
+/// Every file in the subfolders of `source_dir`, for flattening them into it.
+/// Category folders are flattened too; protected folders are not.
+pub fn scan_flatten(source_dir: &str, options: &ScanOptions) -> Vec<PathBuf> {
+    let mut results = Vec::new();
+    let ignore = IgnoreList::load(Path::new(source_dir));
+    scan_flatten_inner(Path::new(source_dir), 0, options, &ignore, &mut results);
+    results
+}
+
+/// Date of a file according to the configured source
+/// Creation time is unavailable on many Linux filesystems, so it falls back to mtime
+pub fn file_datetime(path: &Path, source: DateSource) -> DateTime<Local> {
+    let metadata = path.metadata().ok();
+    let created = match source {
+        DateSource::Created => metadata.as_ref().and_then(|m| m.created().ok()),
+        DateSource::Modified => None,
+    };
+
+    created
+        .or_else(|| metadata.and_then(|m| m.modified().ok()))
+        .map(|t| t.into())
+        .unwrap_or_else(Local::now)
+}
+
+/// Category from the file's magic bytes, or None when the signature is unknown.
+/// ZIP-based formats (docx, epub...) are left to their extension.
+pub fn sniff_category(path: &Path, options: &ScanOptions) -> Option<String> {
+    let sniffed = sniff_extension(path)?;
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if sniffed == "zip" && ZIP_CONTAINERS.contains(&ext.as_str()) {
+        return None;
+    }
+
+    Some(config::resolve_folder(
+        options.categories,
+        options.overrides,
+        sniffed,
+    ))
+}
+
 // ============================================================================
 // Private helpers
 // ============================================================================
 
-fn scan_files<F>(source_dir: &str, get_folder: F) -> HashMap<String, Vec<PathBuf>>
+fn scan_files<F>(
+    source_dir: &str,
+    options: &ScanOptions,
+    get_folder: F,
+) -> HashMap<String, Vec<PathBuf>>
 where
-    F: Fn(&Path, &str) -> String,
+    F: Fn(&Path, &str) -> Option<String>,
 {
     let mut grouped: HashMap<String, Vec<PathBuf>> = HashMap::new();
 
@@ -123,39 +402,47 @@ where
         Ok(e) => e,
         Err(_) => return grouped,
     };
+    let ignore = IgnoreList::load(Path::new(source_dir));
 
     for entry in entries.flatten() {
         let path = entry.path();
+        if path.is_dir() && options.with_folders {
+            // Category, project and protected folders stay where they are
+            if !should_skip_directory(&path, options, options.include_hidden_dirs)
+                && !is_ignored(&path, &ignore, options)
+                && passes_age_filter(&path, options)
+            {
+                let folder = options.folders_category.to_string();
+                grouped.entry(folder).or_default().push(path);
+            }
+            continue;
+        }
         if !path.is_file() {
             continue;
         }
 
-        if let Some(name) = path.file_name() {
-            let name_str = name.to_string_lossy();
-            if name_str.starts_with('.')
-                || name_str.ends_with(".DS_Store")
-                || name_str.ends_with(".localized")
-            {
-                continue;
-            }
+        if is_skipped_file(&path, options.include_hidden)
+            || is_ignored(&path, &ignore, options)
+            || !passes_age_filter(&path, options)
+        {
+            continue;
         }
 
-        let ext = match path.extension() {
-            Some(e) => e.to_string_lossy().to_lowercase(),
-            None => continue,
-        };
-
-        let folder = get_folder(&path, &ext);
-        grouped.entry(folder).or_default().push(path);
+        let ext = file_extension(&path);
+        if let Some(folder) = get_folder(&path, &ext) {
+            grouped.entry(folder).or_default().push(path);
+        }
     }
 
     grouped
 }
 
+/// `depth` is 0 for the source folder, whose own files the flat scans already handle
 fn scan_recursive_inner(
-    root_dir: &str,
     current_dir: &str,
-    categories: &HashMap<String, Vec<String>>,
+    depth: usize,
+    options: &ScanOptions,
+    ignore: &IgnoreList,
     org_mode: usize,
     results: &mut HashMap<String, Vec<PathBuf>>,
 ) {
@@ -168,73 +455,355 @@ fn scan_recursive_inner(
         let path = entry.path();
 
         if path.is_dir() {
-            if should_skip_directory(&path, categories) {
+            if options.max_depth.is_some_and(|max| depth >= max)
+                || should_skip_directory(&path, options, options.include_hidden_dirs)
+                || is_ignored(&path, ignore, options)
+            {
                 continue;
             }
             scan_recursive_inner(
-                root_dir,
                 &path.to_string_lossy(),
-                categories,
+                depth + 1,
+                options,
+                ignore,
                 org_mode,
                 results,
             );
-        } else if path.is_file() && current_dir != root_dir {
-            if let Some(folder) = classify_file(&path, categories, org_mode) {
+        } else if path.is_file()
+            && depth > 0
+            && !is_skipped_file(&path, options.include_hidden)
+            && !is_ignored(&path, ignore, options)
+            && passes_age_filter(&path, options)
+        {
+            if let Some(folder) = classify_file(&path, options, org_mode) {
                 results.entry(folder).or_default().push(path);
             }
         }
     }
 }
 
-fn should_skip_directory(path: &Path, categories: &HashMap<String, Vec<String>>) -> bool {
+/// Folder of the first rule that matches the file
+fn rule_folder(path: &Path, options: &ScanOptions, ext: &str) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+    let glob_options = glob::MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+
+    options
+        .rules
+        .iter()
+        .find(|rule| {
+            (rule.extensions.is_empty() || rule.extensions.iter().any(|e| e == ext))
+                && rule
+                    .name
+                    .as_ref()
+                    .is_none_or(|p| p.matches_with(&name, glob_options))
+                && rule.min_size.is_none_or(|min| size >= min)
+                && rule.max_size.is_none_or(|max| size <= max)
+                && age_in_window(path, options.date_source, rule.older_than, rule.newer_than)
+        })
+        .map(|rule| rule.folder.clone())
+}
+
+/// Whether a file is at least `older_than` and at most `newer_than` old
+fn age_in_window(
+    path: &Path,
+    source: DateSource,
+    older_than: Option<TimeDelta>,
+    newer_than: Option<TimeDelta>,
+) -> bool {
+    if older_than.is_none() && newer_than.is_none() {
+        return true;
+    }
+
+    let age = Local::now() - file_datetime(path, source);
+    older_than.is_none_or(|min| age >= min) && newer_than.is_none_or(|max| age <= max)
+}
+
+/// Whether a file's age fits the window; files outside it are kept in `filtered_by_age`
+fn passes_age_filter(path: &Path, options: &ScanOptions) -> bool {
+    let inside = age_in_window(
+        path,
+        options.date_source,
+        options.older_than,
+        options.newer_than,
+    );
+
+    if !inside {
+        options
+            .filtered_by_age
+            .borrow_mut()
+            .push(path.to_path_buf());
+    }
+    inside
+}
+
+fn scan_flatten_inner(
+    dir: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    ignore: &IgnoreList,
+    results: &mut Vec<PathBuf>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            if options.max_depth.is_none_or(|max| depth < max)
+                && !is_protected_directory(&path, options, options.include_hidden_dirs)
+                && !is_ignored(&path, ignore, options)
+            {
+                scan_flatten_inner(&path, depth + 1, options, ignore, results);
+            }
+        } else if path.is_file()
+            && depth > 0
+            && !is_skipped_file(&path, options.include_hidden)
+            && !is_ignored(&path, ignore, options)
+            && passes_age_filter(&path, options)
+        {
+            results.push(path);
+        }
+    }
+}
+
+/// Whether .stellarignore covers the path; matches are kept in `ignored`
+fn is_ignored(path: &Path, ignore: &IgnoreList, options: &ScanOptions) -> bool {
+    let ignored = ignore.is_ignored(path);
+    if ignored {
+        options.ignored.borrow_mut().push(path.to_path_buf());
+    }
+    ignored
+}
+
+/// OS junk, the lock file and .stellarignore are never organized; other
+/// dotfiles only on request
+fn is_skipped_file(path: &Path, include_hidden: bool) -> bool {
+    let name = match path.file_name() {
+        Some(n) => n.to_string_lossy(),
+        None => return true,
+    };
+
+    name == LOCK_FILE_NAME
+        || name == IGNORE_FILE_NAME
+        || name.ends_with(".DS_Store")
+        || name.ends_with(".localized")
+        || (name.starts_with('.') && !include_hidden)
+}
+
+fn should_skip_directory(path: &Path, options: &ScanOptions, include_hidden: bool) -> bool {
     let name = match path.file_name() {
         Some(n) => n.to_string_lossy().to_lowercase(),
         None => return true,
     };
 
-    name.starts_with('.')
+    is_protected_directory(path, options, include_hidden)
+        || is_category_folder(&name, options)
+        || is_extension_folder(path, &name, options)
+}
+
+/// Hidden (unless requested), dev or project folders, and linked folders
+/// (never entered, so a link cycle cannot loop a scan)
+fn is_protected_directory(path: &Path, options: &ScanOptions, include_hidden: bool) -> bool {
+    let name = match path.file_name() {
+        Some(n) => n.to_string_lossy().to_lowercase(),
+        None => return true,
+    };
+
+    (name.starts_with('.') && !include_hidden)
         || PROTECTED_SUBFOLDERS.contains(&name.as_str())
-        || is_project_folder(&path.to_string_lossy())
-        || is_category_folder(&name, categories)
+        || organizer::is_symlink(path)
+        || is_project_folder(&path.to_string_lossy(), &options.project_indicators)
 }
 
-fn is_category_folder(name: &str, categories: &HashMap<String, Vec<String>>) -> bool {
+fn is_category_folder(name: &str, options: &ScanOptions) -> bool {
     let lower = name.to_lowercase();
-    lower == "others" || categories.keys().any(|c| c.to_lowercase() == lower)
+    lower == "others"
+        || matches!(options.unmatched, UnmatchedPolicy::Folder(f) if f.to_lowercase() == lower)
+        || lower == options.no_extension_folder.to_lowercase()
+        || lower == options.folders_category.to_lowercase()
+        || options.categories.keys().any(|c| c.to_lowercase() == lower)
+        || options
+            .overrides
+            .values()
+            .any(|f| f.to_lowercase() == lower)
+        || options.rules.iter().any(|r| {
+            r.folder
+                .split('/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+                == lower
+        })
 }
 
-fn classify_file(
-    path: &Path,
-    categories: &HashMap<String, Vec<String>>,
-    org_mode: usize,
-) -> Option<String> {
-    let ext = path.extension()?.to_string_lossy().to_lowercase();
+/// With `unmatched_policy = "by-extension"`, a folder a previous run made:
+/// named after an extension that matches no category (xyz/) and holding only
+/// files with that extension
+fn is_extension_folder(path: &Path, name: &str, options: &ScanOptions) -> bool {
+    if *options.unmatched != UnmatchedPolicy::ByExtension
+        || config::resolve_folder(options.categories, options.overrides, name) != "Others"
+    {
+        return false;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return false;
+    };
 
-    Some(match org_mode {
-        0 => config::find_category(categories, &ext).unwrap_or_else(|| "Others".into()),
-        1 => get_date_folder(path),
-        2 => {
-            let cat = config::find_category(categories, &ext).unwrap_or_else(|| "Others".into());
-            format!("{}/{}", cat, get_year(path))
+    let mut files = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !is_skipped_file(p, false))
+        .peekable();
+    files.peek().is_some()
+        && files.all(|p| {
+            p.extension()
+                .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(name))
+        })
+}
+
+/// None when the unmatched policy leaves the file in place (kept in `left_unmatched`)
+fn category_folder(path: &Path, options: &ScanOptions, ext: &str) -> Option<String> {
+    if options.sniff {
+        if let Some(category) = sniff_category(path, options) {
+            return Some(category);
         }
-        _ => "Others".into(),
-    })
+    }
+    if ext.is_empty() {
+        return Some(options.no_extension_folder.to_string());
+    }
+
+    let folder = config::resolve_folder(options.categories, options.overrides, ext);
+    if folder != "Others" {
+        return Some(folder);
+    }
+
+    *options
+        .unknown_extensions
+        .borrow_mut()
+        .entry(ext.to_string())
+        .or_default() += 1;
+    let folder = options.unmatched.folder(ext);
+    if folder.is_none() {
+        options.left_unmatched.borrow_mut().push(path.to_path_buf());
+    }
+    folder
 }
 
-fn get_file_datetime(path: &Path) -> DateTime<Local> {
-    path.metadata()
-        .ok()
-        .and_then(|m| m.modified().ok())
-        .map(|t| t.into())
-        .unwrap_or_else(Local::now)
+/// Lowercased extension, empty for files without one
+fn file_extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Canonical extension for a known file signature
+fn sniff_extension(path: &Path) -> Option<&'static str> {
+    let mut header = [0u8; SNIFF_LEN];
+    let mut file = fs::File::open(path).ok()?;
+    let mut len = 0;
+    while len < SNIFF_LEN {
+        match file.read(&mut header[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    let h = &header[..len];
+
+    let ext = if h.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if h.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else if h.starts_with(b"GIF87a") || h.starts_with(b"GIF89a") {
+        "gif"
+    } else if h.starts_with(b"II*\0") || h.starts_with(b"MM\0*") {
+        "tiff"
+    } else if h.starts_with(b"%PDF-") {
+        "pdf"
+    } else if h.starts_with(b"PK\x03\x04") {
+        "zip"
+    } else if h.starts_with(b"Rar!") {
+        "rar"
+    } else if h.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        "7z"
+    } else if h.starts_with(&[0x1F, 0x8B]) {
+        "gz"
+    } else if h.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        "mkv"
+    } else if h.starts_with(b"ID3") {
+        "mp3"
+    } else if h.starts_with(b"fLaC") {
+        "flac"
+    } else if h.starts_with(b"OggS") {
+        "ogg"
+    } else if h.starts_with(b"wOFF") {
+        "woff"
+    } else if h.starts_with(b"wOF2") {
+        "woff2"
+    } else if h.starts_with(b"RIFF") && h.len() >= 12 {
+        match &h[8..12] {
+            b"WEBP" => "webp",
+            b"WAVE" => "wav",
+            b"AVI " => "avi",
+            _ => return None,
+        }
+    } else if h.len() >= 12 && &h[4..8] == b"ftyp" {
+        match &h[8..12] {
+            b"qt  " => "mov",
+            b"M4A " => "m4a",
+            b"M4V " => "m4v",
+            b"heic" | b"heix" | b"mif1" => "heic",
+            _ => "mp4",
+        }
+    } else {
+        return None;
+    };
+
+    Some(ext)
+}
+
+fn classify_file(path: &Path, options: &ScanOptions, org_mode: usize) -> Option<String> {
+    let ext = file_extension(path);
+
+    match org_mode {
+        0 => rule_folder(path, options, &ext).or_else(|| category_folder(path, options, &ext)),
+        1 => Some(date_folder(path, options, DateGranularity::Month)),
+        2 => {
+            let cat = category_folder(path, options, &ext)?;
+            Some(format!(
+                "{}/{}",
+                cat,
+                date_folder(path, options, DateGranularity::Year)
+            ))
+        }
+        _ => Some("Others".into()),
+    }
 }
 
-fn get_date_folder(path: &Path) -> String {
-    let dt = get_file_datetime(path);
-    let month_idx = dt.month0() as usize;
-    format!("{}/{}", dt.format("%Y"), MONTHS[month_idx])
+/// Date folder using the configured granularity, or the mode's default
+fn date_folder(path: &Path, options: &ScanOptions, mode_default: DateGranularity) -> String {
+    let granularity = options.date_granularity.unwrap_or(mode_default);
+    get_date_folder(path, options.date_source, granularity, &options.month_names)
 }
 
-fn get_year(path: &Path) -> String {
-    get_file_datetime(path).format("%Y").to_string()
+fn get_date_folder(
+    path: &Path,
+    source: DateSource,
+    granularity: DateGranularity,
+    month_names: &[String],
+) -> String {
+    let dt = file_datetime(path, source);
+    let month = format!("{:02}-{}", dt.month(), month_names[dt.month0() as usize]);
+
+    match granularity {
+        DateGranularity::Year => dt.format("%Y").to_string(),
+        DateGranularity::Month => format!("{}/{}", dt.format("%Y"), month),
+        DateGranularity::Day => format!("{}/{}/{}", dt.format("%Y"), month, dt.format("%d")),
+    }
 }
@@ -4,23 +4,28 @@
 // Tracks organization statistics: files moved, renamed, skipped, bytes processed.
 // Provides dry-run preview structures and formatting utilities for sizes and durations.
 
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 // ============================================================================
 // Organization Statistics
 // ============================================================================
 
 /// Represents a file that was skipped during organization
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct SkippedFile {
     pub path: PathBuf,
     pub reason: SkipReason,
 }
 
 /// Reasons why a file might be skipped
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
 #[allow(dead_code)] // Variants may be used in future error scenarios
 pub enum SkipReason {
     /// Failed to create destination directory
@@ -31,6 +36,24 @@ pub enum SkipReason {
     FileNotFound,
     /// Permission denied
     PermissionDenied,
+    /// Destination already exists and the conflict policy kept it
+    ConflictSkipped,
+    /// Symbolic link left alone by the symlink policy
+    Symlink,
+    /// Outside the --older-than / --newer-than window
+    FilteredByAge,
+    /// Would get the same new name as another file of its folder
+    NameCollision,
+    /// Matches a pattern of the folder's .stellarignore
+    Ignored,
+    /// Its destination folder does not exist yet (--sort-into-existing-only)
+    NoMatchingFolder,
+    /// Matches no category and `unmatched_policy = "leave"`
+    Unmatched,
+    /// Cross-device copy did not match the source (--verify-moves); source kept
+    VerificationFailed,
+    /// Already at its destination (re-run over organized folders)
+    AlreadyInPlace,
     /// Unknown error
     Other(String),
 }
@@ -42,12 +65,23 @@ impl std::fmt::Display for SkipReason {
             SkipReason::MoveFailed(e) => write!(f, "Move failed: {}", e),
             SkipReason::FileNotFound => write!(f, "File not found"),
             SkipReason::PermissionDenied => write!(f, "Permission denied"),
+            SkipReason::ConflictSkipped => write!(f, "Destination already exists"),
+            SkipReason::Symlink => write!(f, "Symbolic link (see --symlinks)"),
+            SkipReason::FilteredByAge => write!(f, "Outside the age filter"),
+            SkipReason::NameCollision => write!(f, "Same new name as another file"),
+            SkipReason::Ignored => write!(f, "Listed in .stellarignore"),
+            SkipReason::NoMatchingFolder => write!(f, "No existing folder for it"),
+            SkipReason::Unmatched => write!(f, "No category (unmatched_policy = leave)"),
+            SkipReason::VerificationFailed => {
+                write!(f, "Copy did not match the source, original kept")
+            }
+            SkipReason::AlreadyInPlace => write!(f, "Already in its folder"),
             SkipReason::Other(e) => write!(f, "{}", e),
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize)]
 pub struct OrganizationStats {
     pub files_moved: usize,
     pub files_renamed: usize,
@@ -56,7 +90,12 @@ pub struct OrganizationStats {
     pub total_bytes: u64,
     pub categories: HashMap<String, usize>,
     pub duration_ms: u64,
+    /// Time spent moving each category's files, to spot slow (cross-device) folders
+    pub category_durations: HashMap<String, u64>,
+    /// Extensions without a category (filed under Others), with their file counts
+    pub unknown_extensions: HashMap<String, usize>,
     pub skipped_files: Vec<SkippedFile>,
+    #[serde(skip)]
     start_time: Option<Instant>,
 }
 
@@ -80,10 +119,37 @@ impl OrganizationStats {
         *self.categories.entry(category.to_string()).or_insert(0) += 1;
     }
 
+    pub fn add_category_duration(&mut self, category: &str, elapsed: Duration) {
+        *self
+            .category_durations
+            .entry(category.to_string())
+            .or_insert(0) += elapsed.as_millis() as u64;
+    }
+
     pub fn add_renamed(&mut self) {
         self.files_renamed += 1;
     }
 
+    pub fn add_duplicate(&mut self) {
+        self.duplicates_found += 1;
+    }
+
+    /// Fold in the counters of a partial run (e.g. from a worker thread)
+    pub fn merge(&mut self, other: OrganizationStats) {
+        self.files_moved += other.files_moved;
+        self.files_renamed += other.files_renamed;
+        self.files_skipped += other.files_skipped;
+        self.duplicates_found += other.duplicates_found;
+        self.total_bytes += other.total_bytes;
+        for (category, count) in other.categories {
+            *self.categories.entry(category).or_insert(0) += count;
+        }
+        for (category, ms) in other.category_durations {
+            *self.category_durations.entry(category).or_insert(0) += ms;
+        }
+        self.skipped_files.extend(other.skipped_files);
+    }
+
     /// Add a skipped file with detailed reason
     pub fn add_skipped_with_reason(&mut self, path: PathBuf, reason: SkipReason) {
         self.files_skipped += 1;
@@ -95,36 +161,151 @@ impl OrganizationStats {
 // Dry-Run Preview
 // ============================================================================
 
+#[derive(Serialize, Default)]
 pub struct DryRunPreview {
     pub moves: Vec<PreviewMove>,
     pub total_files: usize,
     pub total_bytes: u64,
 }
 
+#[derive(Serialize)]
 pub struct PreviewMove {
     pub from: PathBuf,
     pub to: PathBuf,
+    pub size: u64,
     pub is_rename: bool,
+    /// Another file of the run is headed for the same destination
+    pub collides: bool,
 }
 
 impl DryRunPreview {
     pub fn new() -> Self {
-        Self {
-            moves: Vec::new(),
-            total_files: 0,
-            total_bytes: 0,
-        }
+        Self::default()
     }
 
     pub fn add_move(&mut self, from: PathBuf, to: PathBuf, size: u64, is_rename: bool) {
         self.moves.push(PreviewMove {
             from,
             to,
+            size,
             is_rename,
+            collides: false,
         });
         self.total_files += 1;
         self.total_bytes += size;
     }
+
+    /// Moves that share their destination with another move
+    pub fn collisions(&self) -> usize {
+        self.moves.iter().filter(|m| m.collides).count()
+    }
+}
+
+/// A file name before and after renaming (`stellar rename-preview`)
+#[derive(Serialize)]
+pub struct PlannedRename {
+    pub from: PathBuf,
+    pub to: String,
+    pub category: String,
+    /// Another file of the same category gets the same new name
+    pub collides: bool,
+}
+
+/// Files of one destination folder that would get the same new name
+pub struct RenameCollision {
+    pub category: String,
+    pub name: String,
+    pub files: Vec<PathBuf>,
+}
+
+// ============================================================================
+// Run Report
+// ============================================================================
+
+/// One line of the --report audit file
+#[derive(Serialize)]
+pub struct RunReport<'a> {
+    pub timestamp: String,
+    pub folder: &'a str,
+    #[serde(flatten)]
+    pub stats: &'a OrganizationStats,
+}
+
+/// Append the stats of a finished run to `path` as one JSON line.
+/// Unlike the undo history, the file is never trimmed or rewritten.
+pub fn append_report(path: &Path, folder: &str, stats: &OrganizationStats) -> Result<(), String> {
+    let report = RunReport {
+        timestamp: Local::now().to_rfc3339(),
+        folder,
+        stats,
+    };
+    let line = serde_json::to_string(&report).map_err(|e| format!("Cannot write report: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Cannot open report {}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Cannot write report {}: {}", path.display(), e))
+}
+
+// ============================================================================
+// Folder Report
+// ============================================================================
+
+/// Read-only summary of a folder's composition
+#[derive(Default)]
+pub struct FolderReport {
+    pub categories: HashMap<String, CategoryTally>,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub no_extension: usize,
+    pub oldest: Option<DateTime<Local>>,
+    pub newest: Option<DateTime<Local>>,
+}
+
+#[derive(Default)]
+pub struct CategoryTally {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl FolderReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, category: &str, path: &Path) {
+        let size = self.track(path);
+        let tally = self.categories.entry(category.to_string()).or_default();
+        tally.files += 1;
+        tally.bytes += size;
+    }
+
+    pub fn add_extensionless(&mut self, path: &Path) {
+        self.track(path);
+        self.no_extension += 1;
+    }
+
+    /// Update totals and date range, returning the file size
+    fn track(&mut self, path: &Path) -> u64 {
+        let metadata = path.metadata().ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+
+        if let Some(modified) = metadata.and_then(|m| m.modified().ok()) {
+            let dt: DateTime<Local> = modified.into();
+            if self.oldest.is_none_or(|o| dt < o) {
+                self.oldest = Some(dt);
+            }
+            if self.newest.is_none_or(|n| dt > n) {
+                self.newest = Some(dt);
+            }
+        }
+
+        self.total_files += 1;
+        self.total_bytes += size;
+        size
+    }
 }
 
 // ============================================================================
@@ -144,6 +325,64 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Parse an age like 12h, 30d or 2w (a bare number means days)
+pub fn parse_age(text: &str) -> Result<TimeDelta, String> {
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let value: i64 = number.parse().map_err(|_| {
+        format!(
+            "invalid age '{}': expected a number like 12h, 30d or 2w",
+            text
+        )
+    })?;
+
+    match unit.trim().to_lowercase().as_str() {
+        "h" => Ok(TimeDelta::hours(value)),
+        "" | "d" => Ok(TimeDelta::days(value)),
+        "w" => Ok(TimeDelta::weeks(value)),
+        other => Err(format!(
+            "invalid age '{}': unknown unit '{}' (use h, d or w)",
+            text, other
+        )),
+    }
+}
+
+/// Parse a human-readable size like 500KB, 10MB, 1.5GB or 42 (bytes), 1024-based
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => {
+            return Err(format!(
+                "invalid size '{}': unknown unit '{}' (use B, KB, MB or GB)",
+                text,
+                unit.trim()
+            ))
+        }
+    };
+
+    let value: f64 = number.parse().map_err(|_| {
+        format!(
+            "invalid size '{}': expected a number like 500KB or 10MB",
+            text
+        )
+    })?;
+
+    Ok((value * multiplier as f64).round() as u64)
+}
+
 pub fn format_duration(ms: u64) -> String {
     match ms {
         m if m >= 60000 => format!("{:.1} min", m as f64 / 60000.0),
@@ -1,11 +1,28 @@
+/// A fresh, empty folder in the temp dir for one test (`stellar-<name>-<pid>`)
+#[cfg(test)]
+fn temp_root(name: &str) -> std::path::PathBuf {
+    let root = std::env::temp_dir().join(format!("stellar-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    root
+}
+
 #[cfg(test)]
 mod renamer_tests {
+    use crate::config::RenameConfig;
+    use crate::modes::DateSource;
     use crate::renamer::rename_file;
     use std::path::Path;
 
     fn slugify_via_rename(name: &str) -> String {
         let path = Path::new(name);
-        rename_file(path, &crate::renamer::RenameMode::Clean)
+        rename_file(
+            path,
+            &crate::renamer::RenameMode::Clean,
+            "Documents",
+            DateSource::Modified,
+            &RenameConfig::default(),
+        )
     }
 
     #[test]
@@ -33,9 +50,760 @@ mod renamer_tests {
 
     #[test]
     fn test_remove_copy_suffixes() {
-        assert_eq!(slugify_via_rename("rapport-1"), "rapport");
         assert_eq!(slugify_via_rename("fichier-copy"), "fichier");
+        assert_eq!(slugify_via_rename("file-copy"), "file");
         assert_eq!(slugify_via_rename("document-copie"), "document");
+        assert_eq!(slugify_via_rename("photo-copy-2"), "photo");
+        assert_eq!(slugify_via_rename("document (1)"), "document");
+    }
+
+    #[test]
+    fn test_strip_os_dedup_markers() {
+        use crate::renamer::strip_os_dedup_markers;
+
+        // Browsers
+        assert_eq!(strip_os_dedup_markers("report (1)"), "report");
+        assert_eq!(strip_os_dedup_markers("report (12)"), "report");
+        // macOS Finder
+        assert_eq!(strip_os_dedup_markers("photo copy"), "photo");
+        assert_eq!(strip_os_dedup_markers("photo copy 2"), "photo");
+        assert_eq!(strip_os_dedup_markers("photo copie 3"), "photo");
+        // Windows Explorer
+        assert_eq!(strip_os_dedup_markers("report - Copy"), "report");
+        assert_eq!(strip_os_dedup_markers("report - Copy (2)"), "report");
+        // Real names are left alone
+        assert_eq!(strip_os_dedup_markers("Copy"), "Copy");
+        assert_eq!(strip_os_dedup_markers("(1)"), "(1)");
+        assert_eq!(strip_os_dedup_markers("photocopy"), "photocopy");
+        assert_eq!(strip_os_dedup_markers("blade runner 2"), "blade runner 2");
+    }
+
+    #[test]
+    fn test_os_dedup_markers_before_slugify() {
+        assert_eq!(slugify_via_rename("report (1).pdf"), "report.pdf");
+        assert_eq!(slugify_via_rename("photo copy 2.jpg"), "photo.jpg");
+        assert_eq!(
+            slugify_via_rename("Rapport FINAL - Copy.docx"),
+            "rapport-final.docx"
+        );
+    }
+
+    #[test]
+    fn test_apply_template() {
+        use crate::renamer::apply_template;
+
+        let options = RenameConfig::default();
+        let renamed = apply_template(
+            Path::new("Rapport FINAL (1).PDF"),
+            "{category}_{name}.{ext}",
+            "Documents",
+            DateSource::Modified,
+            &options,
+        );
+        assert_eq!(renamed, "Documents_rapport-final.pdf");
+
+        let renamed = apply_template(
+            Path::new("notes"),
+            "{name}.{ext}",
+            "Others",
+            DateSource::Modified,
+            &options,
+        );
+        assert_eq!(renamed, "notes");
+
+        let renamed = apply_template(
+            Path::new("a.txt"),
+            "{name}-{nope}.{ext}",
+            "Docs",
+            DateSource::Modified,
+            &options,
+        );
+        assert_eq!(renamed, "a-{nope}.txt");
+    }
+
+    #[test]
+    fn test_unknown_placeholders() {
+        use crate::renamer::unknown_placeholders;
+
+        assert!(unknown_placeholders("{date}_{category}_{name}.{ext}").is_empty());
+        assert_eq!(
+            unknown_placeholders("{name}-{foo}-{foo}-{bar}"),
+            vec!["foo", "bar"]
+        );
+    }
+
+    #[test]
+    fn test_keeps_real_trailing_digit() {
+        assert_eq!(slugify_via_rename("blade-runner-2"), "blade-runner-2");
+        assert_eq!(slugify_via_rename("rapport-1"), "rapport-1");
+        assert_eq!(slugify_via_rename("(2)"), "2");
+    }
+
+    #[test]
+    fn test_strip_numeric_suffixes_opt_in() {
+        let options = RenameConfig {
+            strip_numeric_suffixes: true,
+            ..RenameConfig::default()
+        };
+        let rename = |name| {
+            rename_file(
+                Path::new(name),
+                &crate::renamer::RenameMode::Clean,
+                "Documents",
+                DateSource::Modified,
+                &options,
+            )
+        };
+        assert_eq!(rename("rapport-1"), "rapport");
+        assert_eq!(rename("blade-runner-2"), "blade-runner");
+    }
+
+    #[test]
+    fn test_custom_strip_suffixes() {
+        let options = RenameConfig {
+            strip_suffixes: vec!["kopie".to_string()],
+            ..RenameConfig::default()
+        };
+        let rename = |name| {
+            rename_file(
+                Path::new(name),
+                &crate::renamer::RenameMode::Clean,
+                "Documents",
+                DateSource::Modified,
+                &options,
+            )
+        };
+        assert_eq!(rename("bericht-kopie"), "bericht");
+        assert_eq!(rename("bericht-copy"), "bericht-copy");
+    }
+
+    #[test]
+    fn test_snake_case() {
+        let options = RenameConfig::default();
+        let rename = |name| {
+            rename_file(
+                Path::new(name),
+                &crate::renamer::RenameMode::SnakeCase,
+                "Documents",
+                DateSource::Modified,
+                &options,
+            )
+        };
+        assert_eq!(rename("My File Name.pdf"), "my_file_name.pdf");
+        assert_eq!(rename("élève café.txt"), "eleve_cafe.txt");
+        assert_eq!(rename("rapport-copy.doc"), "rapport.doc");
+    }
+
+    #[test]
+    fn test_title_case() {
+        let options = RenameConfig::default();
+        let rename = |name| {
+            rename_file(
+                Path::new(name),
+                &crate::renamer::RenameMode::TitleCase,
+                "Documents",
+                DateSource::Modified,
+                &options,
+            )
+        };
+        assert_eq!(rename("my_file_name.PDF"), "My File Name.pdf");
+        assert_eq!(rename("élève café.txt"), "Eleve Cafe.txt");
+        assert_eq!(rename("rapport-copie.doc"), "Rapport.doc");
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::stats::parse_size;
+
+    #[test]
+    fn test_parse_size_units() {
+        assert_eq!(parse_size("42"), Ok(42));
+        assert_eq!(parse_size("500KB"), Ok(500 * 1024));
+        assert_eq!(parse_size("10mb"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1GB"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_size("1.5 KB"), Ok(1536));
+    }
+
+    #[test]
+    fn test_merge_adds_category_durations() {
+        use crate::stats::OrganizationStats;
+        use std::time::Duration;
+
+        let mut total = OrganizationStats::new();
+        total.add_category_duration("Videos", Duration::from_millis(1500));
+        let mut worker = OrganizationStats::default();
+        worker.add_category_duration("Videos", Duration::from_millis(500));
+        worker.add_category_duration("Images", Duration::from_millis(20));
+        total.merge(worker);
+
+        assert_eq!(total.category_durations["Videos"], 2000);
+        assert_eq!(total.category_durations["Images"], 20);
+    }
+
+    #[test]
+    fn test_parse_size_rejects_malformed() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("1.2.3MB").is_err());
+    }
+
+    #[test]
+    fn test_append_report_writes_one_line_per_run() {
+        use crate::stats::{append_report, OrganizationStats, SkipReason};
+        use std::path::PathBuf;
+
+        let path =
+            std::env::temp_dir().join(format!("stellar-report-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut stats = OrganizationStats::new();
+        stats.add_file("Documents", 10);
+        stats.add_skipped_with_reason(
+            PathBuf::from("/tmp/locked.pdf"),
+            SkipReason::PermissionDenied,
+        );
+        append_report(&path, "/tmp/inbox", &stats).unwrap();
+        append_report(&path, "/tmp/other", &OrganizationStats::new()).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["folder"], "/tmp/inbox");
+        assert_eq!(lines[0]["files_moved"], 1);
+        assert_eq!(lines[0]["categories"]["Documents"], 1);
+        assert_eq!(lines[0]["skipped_files"][0]["path"], "/tmp/locked.pdf");
+        assert_eq!(
+            lines[0]["skipped_files"][0]["reason"]["kind"],
+            "permission_denied"
+        );
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[1]["folder"], "/tmp/other");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use crate::config::resolve_folder;
+    use std::collections::HashMap;
+
+    fn categories() -> HashMap<String, Vec<String>> {
+        HashMap::from([
+            (
+                "Archives".to_string(),
+                vec!["zip".to_string(), "iso".to_string()],
+            ),
+            ("Documents".to_string(), vec!["pdf".to_string()]),
+        ])
+    }
+
+    #[test]
+    fn test_override_wins_over_category() {
+        let overrides = HashMap::from([
+            ("iso".to_string(), "Disk Images".to_string()),
+            (".torrent".to_string(), "Torrents".to_string()),
+        ]);
+
+        assert_eq!(
+            resolve_folder(&categories(), &overrides, "ISO"),
+            "Disk Images"
+        );
+        assert_eq!(
+            resolve_folder(&categories(), &overrides, "torrent"),
+            "Torrents"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_overrides_resolve_to_the_first_key() {
+        use crate::config::duplicate_overrides;
+
+        let overrides = || {
+            HashMap::from([
+                ("torrent".to_string(), "Downloads".to_string()),
+                (".torrent".to_string(), "Torrents".to_string()),
+                ("iso".to_string(), "Disk Images".to_string()),
+            ])
+        };
+
+        // ".torrent" sorts before "torrent", whatever order each new map iterates in
+        for _ in 0..10 {
+            assert_eq!(
+                resolve_folder(&categories(), &overrides(), "torrent"),
+                "Torrents"
+            );
+        }
+        let overrides = overrides();
+        assert_eq!(
+            duplicate_overrides(&overrides),
+            vec![(
+                "torrent".to_string(),
+                vec![
+                    (".torrent".to_string(), "Torrents".to_string()),
+                    ("torrent".to_string(), "Downloads".to_string()),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_extensions_lists_every_category() {
+        use crate::config::duplicate_extensions;
+
+        let mut categories = categories();
+        categories.insert("Work".into(), vec!["PDF".into(), "key".into()]);
+
+        assert_eq!(
+            duplicate_extensions(&categories),
+            vec![(
+                "pdf".to_string(),
+                vec!["Documents".to_string(), "Work".to_string()]
+            )]
+        );
+        assert!(duplicate_extensions(&self::categories()).is_empty());
+    }
+
+    #[test]
+    fn test_find_category_is_stable_for_shared_extension() {
+        use crate::config::find_category;
+
+        // Each map gets its own hash seed, so iteration order varies between them
+        for _ in 0..10 {
+            let mut categories = categories();
+            categories.insert("Work".into(), vec!["pdf".into()]);
+            categories.insert("Archive".into(), vec!["pdf".into()]);
+            assert_eq!(find_category(&categories, "pdf"), Some("Archive".into()));
+        }
+    }
+
+    #[test]
+    fn test_rules_parse_and_reject_bad_values() {
+        use crate::config::{compile_rules, Config};
+
+        let mut toml_str = include_str!("../stellar.toml").to_string();
+        toml_str.push_str(
+            r#"
+[[rules]]
+folder = "Invoices"
+name = "invoice-*"
+
+[[rules]]
+folder = "Old"
+older_than = "30d"
+max_size = "10MB"
+"#,
+        );
+        let config: Config = toml::from_str(&toml_str).unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(compile_rules(&config.rules).unwrap().len(), 2);
+
+        let round_trip: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(round_trip.rules.len(), 2);
+
+        let mut bad = config.rules.clone();
+        bad[1].max_size = Some("10XB".into());
+        let err = compile_rules(&bad).err().unwrap();
+        assert!(err.starts_with("rule 2 (Old)"), "{}", err);
+    }
+
+    #[test]
+    fn test_folder_preferences_round_trip() {
+        use crate::config::{Config, FolderPreference};
+
+        let mut config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        config.folder_preferences.insert(
+            "/home/me/Pictures".into(),
+            FolderPreference {
+                organization_mode: 1,
+                rename_mode: 2,
+                recursive: true,
+            },
+        );
+
+        let saved = toml::to_string_pretty(&config).unwrap();
+        let loaded: Config = toml::from_str(&saved).unwrap();
+        let pref = loaded.folder_preferences["/home/me/Pictures"];
+
+        assert_eq!(pref.organization_mode, 1);
+        assert_eq!(pref.rename_mode, 2);
+        assert!(pref.recursive);
+    }
+
+    #[test]
+    fn test_no_override_falls_back_to_categories() {
+        let overrides = HashMap::from([("iso".to_string(), "Disk Images".to_string())]);
+
+        assert_eq!(resolve_folder(&categories(), &overrides, "zip"), "Archives");
+        assert_eq!(
+            resolve_folder(&categories(), &overrides, "pdf"),
+            "Documents"
+        );
+        assert_eq!(resolve_folder(&categories(), &overrides, "xyz"), "Others");
+    }
+
+    #[test]
+    fn test_month_names_fall_back_to_english() {
+        use crate::config::DateConfig;
+
+        let french = DateConfig {
+            month_names: [
+                "janvier",
+                "fevrier",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "aout",
+                "septembre",
+                "octobre",
+                "novembre",
+                "decembre",
+            ]
+            .map(String::from)
+            .to_vec(),
+        };
+        assert!(french.check().is_ok());
+        assert_eq!(french.months()[7], "aout");
+
+        let short = DateConfig {
+            month_names: vec!["janvier".to_string(); 11],
+        };
+        assert!(short.check().is_err());
+        assert_eq!(short.months()[0], "january");
+
+        let mut slash = french.clone();
+        slash.month_names[0] = "jan/vier".to_string();
+        assert!(slash.check().is_err());
+    }
+
+    #[test]
+    fn test_history_retention_from_config() {
+        use crate::config::HistoryConfig;
+        use crate::history::Retention;
+
+        let config: HistoryConfig =
+            toml::from_str("max_operations = 200\nmax_age = \"90d\"").unwrap();
+        assert!(config.check().is_ok());
+        let retention = Retention::from_config(&config);
+        assert_eq!(retention.max_operations, Some(200));
+        assert_eq!(retention.max_age, Some(chrono::TimeDelta::days(90)));
+
+        let invalid: HistoryConfig = toml::from_str("max_age = \"soon\"").unwrap();
+        assert!(invalid.check().is_err());
+        assert!(Retention::from_config(&invalid).max_age.is_none());
+
+        let unset = HistoryConfig::default();
+        assert!(unset.check().is_ok());
+        assert!(Retention::from_config(&unset).max_operations.is_none());
+    }
+
+    #[test]
+    fn test_add_and_remove_category() {
+        use crate::config::{add_to_category, parse_extensions, remove_category};
+
+        let mut cats = categories();
+        let exts = parse_extensions("PDF, .docx,,xlsx");
+        assert_eq!(exts, vec!["pdf", "docx", "xlsx"]);
+
+        // New category
+        assert_eq!(add_to_category(&mut cats, "Work", &exts), exts);
+        assert_eq!(cats["Work"], exts);
+
+        // Merged into the existing one, whatever the case
+        let added = add_to_category(&mut cats, "documents", &parse_extensions("pdf,odt"));
+        assert_eq!(added, vec!["odt"]);
+        assert_eq!(cats["Documents"], vec!["pdf", "odt"]);
+        assert!(!cats.contains_key("documents"));
+
+        let (name, removed) = remove_category(&mut cats, "WORK").unwrap();
+        assert_eq!(name, "Work");
+        assert_eq!(removed, exts);
+        assert!(remove_category(&mut cats, "Work").is_err());
+    }
+
+    #[test]
+    fn test_saving_edits_only_what_changed() {
+        use super::temp_root;
+        use crate::config::{
+            save_categories_in, save_folder_preference_in, Config, FolderPreference,
+        };
+
+        let dir = temp_root("config-edit");
+        let path = dir.join("stellar.toml");
+        std::fs::write(
+            &path,
+            "# my setup\n[protected]\nsystem = []\nuser = []\ndev = []\n\n\
+             [categories]\nGlobal = [\"pdf\"] # keep me\nOld = [\"txt\"]\n",
+        )
+        .unwrap();
+
+        let pref = FolderPreference {
+            organization_mode: 2,
+            rename_mode: 1,
+            recursive: true,
+        };
+        save_folder_preference_in(&path, "/home/me/Downloads", &pref).unwrap();
+
+        let mut cats = categories();
+        cats.insert("Global".to_string(), vec!["pdf".to_string()]);
+        save_categories_in(&path, &cats, &["Archives".to_string(), "Old".to_string()]).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("# my setup"));
+        assert!(written.contains("# keep me"));
+
+        let config: Config = toml::from_str(&written).unwrap();
+        assert_eq!(config.categories["Global"], vec!["pdf"]);
+        assert_eq!(config.categories["Archives"], vec!["zip", "iso"]);
+        assert!(!config.categories.contains_key("Old"));
+        // Only the named categories are written
+        assert!(!config.categories.contains_key("Documents"));
+        let saved = &config.folder_preferences["/home/me/Downloads"];
+        assert_eq!(saved.organization_mode, 2);
+        assert_eq!(saved.rename_mode, 1);
+        assert!(saved.recursive);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+#[cfg(test)]
+mod duplicates_tests {
+    use super::temp_root;
+    use crate::duplicates::{plan_removal, DuplicateGroup};
+    use crate::modes::KeepRule;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_similar_rejects_removal_options() {
+        use crate::Cli;
+        use clap::Parser;
+
+        let parse = |args: &[&str]| {
+            let mut argv = vec!["stellar", "duplicates", "/photos", "--similar"];
+            argv.extend_from_slice(args);
+            Cli::try_parse_from(argv)
+        };
+
+        assert!(parse(&[]).is_ok());
+        assert!(parse(&["--dry-run", "--threshold", "8"]).is_ok());
+        for args in [
+            &["--delete"][..],
+            &["--delete", "--permanent"],
+            &["--keep", "oldest"],
+            &["--sort-by", "count"],
+        ] {
+            assert!(parse(args).is_err(), "{:?} accepted", args);
+        }
+    }
+
+    #[test]
+    fn test_json_duplicates_reject_removal_options() {
+        use crate::Cli;
+        use clap::Parser;
+
+        let parse = |args: &[&str]| {
+            let mut argv = vec!["stellar", "duplicates", "/photos", "--format", "json"];
+            argv.extend_from_slice(args);
+            Cli::try_parse_from(argv)
+        };
+
+        assert!(parse(&["--keep", "oldest"]).is_ok());
+        assert!(parse(&["--delete"]).is_err());
+        assert!(parse(&["--delete", "--permanent"]).is_err());
+    }
+
+    #[test]
+    fn test_hash_file_same_digest_for_every_read_path() {
+        use crate::duplicates::{hash_file, HashOptions};
+
+        let root = temp_root("hash");
+        let empty = root.join("empty");
+        let large = root.join("large.bin");
+        std::fs::write(&empty, b"").unwrap();
+        let content: Vec<u8> = (0..17 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&large, &content).unwrap();
+
+        let mapped = HashOptions::default();
+        let buffered = HashOptions {
+            buffer_size: 4096,
+            mmap: false,
+        };
+
+        let (hash, size) = hash_file(&empty, &mapped).unwrap();
+        assert_eq!(size, 0);
+        assert_eq!(
+            hash,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash_file(&large, &mapped).unwrap(),
+            hash_file(&large, &buffered).unwrap()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_plan_removal_keeps_first_and_counts_bytes() {
+        let groups = vec![
+            DuplicateGroup {
+                files: vec![
+                    PathBuf::from("a.jpg"),
+                    PathBuf::from("b.jpg"),
+                    PathBuf::from("c.jpg"),
+                ],
+                size: 100,
+            },
+            DuplicateGroup {
+                files: vec![PathBuf::from("x.pdf"), PathBuf::from("y.pdf")],
+                size: 7,
+            },
+        ];
+
+        let plans = plan_removal(&groups, KeepRule::Shallowest);
+
+        assert_eq!(plans.len(), 2);
+        assert_eq!(plans[0].kept, PathBuf::from("a.jpg"));
+        assert_eq!(plans[0].removed.len(), 2);
+        assert_eq!(plans[0].bytes_freed, 200);
+        assert_eq!(plans[1].bytes_freed, 7);
+    }
+
+    #[test]
+    fn test_order_groups_biggest_first() {
+        use crate::duplicates::order_groups;
+        use crate::modes::GroupOrder;
+
+        let group = |name: &str, copies: usize, size: u64| DuplicateGroup {
+            files: (0..copies)
+                .map(|i| PathBuf::from(format!("{}{}", name, i)))
+                .collect(),
+            size,
+        };
+        // wasted: a = 3 x 10, b = 1 x 50, c = 1 x 20
+        let mut groups = vec![group("c", 2, 20), group("a", 4, 10), group("b", 2, 50)];
+        let names = |groups: &[DuplicateGroup]| -> Vec<String> {
+            groups
+                .iter()
+                .map(|g| g.files[0].display().to_string())
+                .collect()
+        };
+
+        order_groups(&mut groups, GroupOrder::Wasted);
+        assert_eq!(names(&groups), ["b0", "a0", "c0"]);
+        order_groups(&mut groups, GroupOrder::Size);
+        assert_eq!(names(&groups), ["b0", "c0", "a0"]);
+        // Same count: ties go by path
+        order_groups(&mut groups, GroupOrder::Count);
+        assert_eq!(names(&groups), ["a0", "b0", "c0"]);
+    }
+
+    #[test]
+    fn test_duplicate_report_json_has_wasted_bytes() {
+        use crate::duplicates::DuplicateReport;
+
+        let groups = vec![DuplicateGroup {
+            files: vec![PathBuf::from("/a/x.bin"), PathBuf::from("/a/y.bin")],
+            size: 50,
+        }];
+        let json = serde_json::to_value(DuplicateReport::new(&groups)).unwrap();
+
+        assert_eq!(json["wasted_bytes"], 50);
+        assert_eq!(json["duplicate_files"], 1);
+        assert_eq!(json["groups"][0]["size"], 50);
+        assert_eq!(json["groups"][0]["files"][1], "/a/y.bin");
+    }
+
+    fn group(paths: &[&str]) -> Vec<DuplicateGroup> {
+        vec![DuplicateGroup {
+            files: paths.iter().map(PathBuf::from).collect(),
+            size: 1,
+        }]
+    }
+
+    #[test]
+    fn test_keep_rule_shallowest_and_shortest_name() {
+        let groups = group(&["/dl/old/nested/photo.jpg", "/dl/photo (1).jpg"]);
+        assert_eq!(
+            plan_removal(&groups, KeepRule::Shallowest)[0].kept,
+            PathBuf::from("/dl/photo (1).jpg")
+        );
+        assert_eq!(
+            plan_removal(&groups, KeepRule::ShortestName)[0].kept,
+            PathBuf::from("/dl/old/nested/photo.jpg")
+        );
+    }
+
+    #[test]
+    fn test_find_similar_images_groups_resized_copy() {
+        use crate::duplicates::find_similar_images;
+        use image::{imageops::FilterType, RgbImage};
+        use std::fs;
+        use stellar_org::progress::NoProgress;
+
+        let dir = temp_root("similar");
+
+        // Horizontal gradient, a half-size copy, and an unrelated vertical gradient
+        let gradient = RgbImage::from_fn(64, 64, |x, _| image::Rgb([(x * 4) as u8; 3]));
+        let other = RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([if (x / 8 + y / 8) % 2 == 0 { 255 } else { 0 }; 3])
+        });
+        let paths = [
+            dir.join("a.png"),
+            dir.join("a-small.png"),
+            dir.join("b.png"),
+        ];
+        gradient.save(&paths[0]).unwrap();
+        image::imageops::resize(&gradient, 32, 32, FilterType::Triangle)
+            .save(&paths[1])
+            .unwrap();
+        other.save(&paths[2]).unwrap();
+
+        let groups = find_similar_images(&paths, 5, &NoProgress);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 2);
+        assert!(!groups[0].files.contains(&paths[2]));
+        assert_eq!(groups[0].distances.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keep_rule_oldest_and_newest() {
+        use std::fs;
+        use std::time::{Duration, SystemTime};
+
+        let dir = temp_root("keep");
+        let old = dir.join("b-old.txt");
+        let new = dir.join("a-new.txt");
+        for path in [&old, &new] {
+            fs::write(path, b"same").unwrap();
+        }
+        let past = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        let groups = vec![DuplicateGroup {
+            files: vec![new.clone(), old.clone()],
+            size: 4,
+        }];
+        assert_eq!(plan_removal(&groups, KeepRule::Oldest)[0].kept, old);
+        assert_eq!(plan_removal(&groups, KeepRule::Newest)[0].kept, new);
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
 
@@ -107,11 +875,11 @@ mod vault_crypto_tests {
 #[cfg(test)]
 mod vault_recovery_tests {
     use crate::vault::crypto::KEY_SIZE;
-    use crate::vault::recovery::RecoveryCodes;
+    use crate::vault::recovery::{RecoveryCodes, DEFAULT_CODE_GROUPS, MAX_CODE_GROUPS};
 
     #[test]
     fn test_recovery_codes_format() {
-        let codes = RecoveryCodes::generate();
+        let codes = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
 
         assert_eq!(codes.code1.len(), 14);
         assert_eq!(codes.code2.len(), 14);
@@ -119,10 +887,24 @@ mod vault_recovery_tests {
         assert_eq!(codes.code2.chars().filter(|c| *c == '-').count(), 2);
     }
 
+    #[test]
+    fn test_recovery_codes_entropy() {
+        use crate::vault::recovery::code_format;
+
+        let codes = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
+        assert_eq!(codes.entropy_bits(), 120);
+
+        // 2 x 28 characters of 5 bits exceed the 256-bit key they are hashed into
+        let codes = RecoveryCodes::generate(MAX_CODE_GROUPS);
+        assert_eq!(codes.code1.len(), code_format(MAX_CODE_GROUPS).len());
+        assert_eq!(codes.entropy_bits(), 256);
+        assert_eq!(code_format(3), "XXXX-XXXX-XXXX");
+    }
+
     #[test]
     fn test_recovery_codes_unique() {
-        let codes1 = RecoveryCodes::generate();
-        let codes2 = RecoveryCodes::generate();
+        let codes1 = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
+        let codes2 = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
 
         assert_ne!(codes1.code1, codes2.code1);
         assert_ne!(codes1.code2, codes2.code2);
@@ -130,7 +912,7 @@ mod vault_recovery_tests {
 
     #[test]
     fn test_key_encrypt_decrypt() {
-        let codes = RecoveryCodes::generate();
+        let codes = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
         let key: [u8; KEY_SIZE] = [42u8; KEY_SIZE];
 
         let encrypted = codes.encrypt_key(&key).unwrap();
@@ -141,7 +923,7 @@ mod vault_recovery_tests {
 
     #[test]
     fn test_wrong_recovery_code_fails() {
-        let codes = RecoveryCodes::generate();
+        let codes = RecoveryCodes::generate(DEFAULT_CODE_GROUPS);
         let key: [u8; KEY_SIZE] = [42u8; KEY_SIZE];
 
         let encrypted = codes.encrypt_key(&key).unwrap();
@@ -152,48 +934,773 @@ mod vault_recovery_tests {
 }
 
 #[cfg(test)]
-mod password_validation_tests {
-    use crate::vault::{validate_password, VaultError};
+mod vault_locker_tests {
+    use super::temp_root;
+    use crate::vault::locker::{
+        lock_dir, lock_file, plan_lock, shred_dir, shred_file, unlock_dir, unlock_file, LockOptions,
+    };
+    use crate::vault::VaultError;
+    use std::fs;
 
     #[test]
-    fn test_password_too_short() {
-        let result = validate_password("Short1!");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
-    }
+    fn test_shred_file_removes_file() {
+        let path = std::env::temp_dir().join(format!("stellar-shred-{}", std::process::id()));
+        fs::write(&path, b"plaintext secret").unwrap();
 
-    #[test]
-    fn test_password_no_uppercase() {
-        let result = validate_password("lowercase123!@#");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+        shred_file(&path).unwrap();
+
+        assert!(!path.exists());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_password_no_lowercase() {
-        let result = validate_password("UPPERCASE123!@#");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    fn test_shred_dir_leaves_link_targets_alone() {
+        let root = temp_root("shred-links");
+        let outside = root.join("outside");
+        let tree = root.join("tree");
+        fs::create_dir_all(outside.join("photos")).unwrap();
+        fs::create_dir_all(tree.join("sub")).unwrap();
+        fs::write(outside.join("notes.txt"), b"outside").unwrap();
+        fs::write(outside.join("photos/a.jpg"), b"photo").unwrap();
+        fs::write(tree.join("sub/secret.txt"), b"secret").unwrap();
+        std::os::unix::fs::symlink(outside.join("notes.txt"), tree.join("notes.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.join("photos"), tree.join("sub/photos")).unwrap();
+
+        shred_dir(&tree).unwrap();
+
+        assert!(!tree.exists());
+        assert_eq!(fs::read(outside.join("notes.txt")).unwrap(), b"outside");
+        assert_eq!(fs::read(outside.join("photos/a.jpg")).unwrap(), b"photo");
+
+        // A link given directly is removed, not followed
+        std::os::unix::fs::symlink(outside.join("notes.txt"), root.join("link.txt")).unwrap();
+        shred_file(&root.join("link.txt")).unwrap();
+        assert_eq!(fs::read(outside.join("notes.txt")).unwrap(), b"outside");
+
+        fs::remove_dir_all(&root).unwrap();
     }
 
     #[test]
-    fn test_password_no_digit() {
-        let result = validate_password("NoDigitsHere!@#");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    fn test_plan_lock_touches_nothing() {
+        let root = temp_root("planlock");
+        fs::create_dir_all(root.join("docs/2024")).unwrap();
+        fs::write(root.join("docs/id.pdf"), b"12345").unwrap();
+        fs::write(root.join("docs/old.pdf.stlr"), b"locked").unwrap();
+        fs::write(root.join("docs/2024/tax.pdf"), b"123").unwrap();
+
+        let options = LockOptions {
+            dest: Some(root.join("cloud")),
+            ..Default::default()
+        };
+        let plan = plan_lock(&root.join("docs"), &options, true).unwrap();
+
+        let planned: Vec<_> = plan
+            .files
+            .iter()
+            .map(|f| (f.locked.strip_prefix(&root).unwrap().to_path_buf(), f.size))
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                ("cloud/2024/tax.pdf.stlr".into(), 3),
+                ("cloud/id.pdf.stlr".into(), 5)
+            ]
+        );
+        assert_eq!(plan.skipped, 1);
+        assert!(!root.join("cloud").exists());
+        assert!(root.join("docs/id.pdf").exists());
+
+        fs::remove_dir_all(&root).unwrap();
     }
 
     #[test]
-    fn test_password_no_special() {
-        let result = validate_password("NoSpecial12345");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    fn test_lock_and_unlock_never_overwrite() {
+        let root = temp_root("lock-collide");
+        for dir in ["a", "b"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("report.txt"), dir).unwrap();
+        }
+        let options = LockOptions {
+            dest: Some(root.join("cloud")),
+            ..Default::default()
+        };
+
+        let locked = lock_file(&root.join("a/report.txt"), "password123", &options).unwrap();
+        let plan = plan_lock(&root.join("b/report.txt"), &options, false).unwrap();
+        assert!(plan.files[0].collides);
+        let second = lock_file(&root.join("b/report.txt"), "password123", &options);
+        assert!(matches!(second, Err(VaultError::AlreadyExists(_))));
+        assert_eq!(fs::read(root.join("b/report.txt")).unwrap(), b"b");
+
+        // The plaintext is back in cloud/ first: unlocking must not replace it
+        fs::write(root.join("cloud/report.txt"), b"newer edit").unwrap();
+        let unlocked = unlock_file(&locked, "password123");
+        assert!(matches!(unlocked, Err(VaultError::AlreadyExists(_))));
+        assert!(locked.exists());
+        assert_eq!(
+            fs::read(root.join("cloud/report.txt")).unwrap(),
+            b"newer edit"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
     }
 
     #[test]
-    fn test_password_common_pattern() {
-        let result = validate_password("MyPassword123!");
-        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    fn test_unlock_hidden_name_never_overwrites() {
+        let root = temp_root("hidename-collide");
+        let original = root.join("id.pdf");
+        fs::write(&original, b"secret").unwrap();
+        let options = LockOptions {
+            hide_name: true,
+            ..Default::default()
+        };
+        let locked = lock_file(&original, "password123", &options).unwrap();
+
+        fs::write(&original, b"other file").unwrap();
+        let unlocked = unlock_file(&locked, "password123");
+
+        assert!(matches!(unlocked, Err(VaultError::AlreadyExists(_))));
+        assert!(locked.exists());
+        assert_eq!(fs::read(&original).unwrap(), b"other file");
+
+        fs::remove_dir_all(&root).unwrap();
     }
 
     #[test]
-    fn test_password_valid() {
-        let result = validate_password("SecureP@ss2024!");
+    fn test_lock_hide_name_into_dest() {
+        let root = temp_root("hidename");
+        let original = root.join("passport scan.pdf");
+        fs::write(&original, b"%PDF secret").unwrap();
+
+        let options = LockOptions {
+            dest: Some(root.join("cloud")),
+            hide_name: true,
+            ..Default::default()
+        };
+        let locked = lock_file(&original, "password123", &options).unwrap();
+
+        assert_eq!(locked.parent(), Some(root.join("cloud").as_path()));
+        assert!(!locked.to_string_lossy().contains("passport"));
+        assert!(!original.exists());
+
+        let unlocked = unlock_file(&locked, "password123").unwrap();
+
+        assert_eq!(unlocked, root.join("cloud/passport scan.pdf"));
+        assert_eq!(fs::read(&unlocked).unwrap(), b"%PDF secret");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_lock_and_unlock_dir_in_place() {
+        let root = temp_root("lockdir");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), b"alpha").unwrap();
+        fs::write(root.join("sub/b.txt"), b"beta").unwrap();
+        fs::write(root.join("c.txt.stlr"), b"not really encrypted").unwrap();
+
+        let locked = lock_dir(&root, "password123", &LockOptions::default(), false).unwrap();
+
+        assert_eq!(locked.done, [root.join("a.txt.stlr")]);
+        assert_eq!(locked.skipped, 1);
+        assert!(!root.join("a.txt").exists());
+        assert!(root.join("sub/b.txt").exists());
+
+        let unlocked = unlock_dir(&root, "password123", true).unwrap();
+
+        assert_eq!(unlocked.done, [root.join("a.txt")]);
+        assert_eq!(unlocked.skipped, 1);
+        assert!(matches!(
+            unlocked.failed.as_slice(),
+            [(p, VaultError::CorruptedData)] if *p == root.join("c.txt.stlr")
+        ));
+        assert_eq!(fs::read(root.join("a.txt")).unwrap(), b"alpha");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod vault_storage_tests {
+    use super::temp_root;
+    use crate::vault::crypto::KdfProfile;
+    use crate::vault::recovery::DEFAULT_CODE_GROUPS;
+    use crate::vault::storage::SecurityLevel;
+    use crate::vault::Vault;
+    use std::fs;
+
+    const PASSWORD: &str = "Correct-Horse-42";
+
+    #[test]
+    fn test_sort_entries() {
+        use crate::vault::storage::{sort_entries, EntrySort, VaultEntry};
+        use chrono::{TimeZone, Utc};
+
+        let entry = |name: &str, size: u64, day: u32| VaultEntry {
+            id: name.to_string(),
+            name: name.to_string(),
+            size,
+            added_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            is_directory: false,
+        };
+        let mut entries = vec![
+            entry("b.pdf", 10, 3),
+            entry("C.txt", 30, 1),
+            entry("a.jpg", 10, 2),
+        ];
+        let names = |entries: &[VaultEntry]| -> Vec<String> {
+            entries.iter().map(|e| e.name.clone()).collect()
+        };
+
+        sort_entries(&mut entries, EntrySort::Name);
+        assert_eq!(names(&entries), ["a.jpg", "b.pdf", "C.txt"]);
+        sort_entries(&mut entries, EntrySort::Size);
+        assert_eq!(names(&entries), ["C.txt", "a.jpg", "b.pdf"]);
+        sort_entries(&mut entries, EntrySort::Date);
+        assert_eq!(names(&entries), ["b.pdf", "a.jpg", "C.txt"]);
+
+        let json = serde_json::to_value(&entries).unwrap();
+        assert_eq!(json[0]["name"], "b.pdf");
+        assert_eq!(json[0]["added_at"], "2024-01-03T00:00:00Z");
+    }
+
+    #[test]
+    fn test_vault_stats() {
+        let root = temp_root("vault-stats");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        let file = root.join("notes.txt");
+        fs::write(&file, b"some secret notes").unwrap();
+        let folder = root.join("scans");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("page1.txt"), b"page one").unwrap();
+        let file_entry = vault.add(&file, PASSWORD, false, false, None).unwrap();
+        let dir_entry = vault.add(&folder, PASSWORD, false, false, None).unwrap();
+
+        let stats = vault.stats(PASSWORD).unwrap();
+
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.directories, 1);
+        assert_eq!(stats.original_bytes, file_entry.size + dir_entry.size);
+        assert!(stats.data_bytes > stats.original_bytes);
+        assert_eq!(
+            stats.overhead_bytes(),
+            stats.data_bytes - stats.original_bytes
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_add_with_custom_name() {
+        use crate::vault::VaultError;
+
+        let root = temp_root("vault-as");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        for dir in ["a", "b", "c"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("scan001.pdf"), dir).unwrap();
+        }
+
+        let entry = vault
+            .add(
+                &root.join("a/scan001.pdf"),
+                PASSWORD,
+                false,
+                false,
+                Some("passport.pdf".into()),
+            )
+            .unwrap();
+        assert_eq!(entry.name, "passport.pdf");
+        vault
+            .add(&root.join("b/scan001.pdf"), PASSWORD, false, false, None)
+            .unwrap();
+
+        let taken = vault.add(
+            &root.join("c/scan001.pdf"),
+            PASSWORD,
+            false,
+            false,
+            Some("passport.pdf".into()),
+        );
+        assert!(matches!(taken, Err(VaultError::AlreadyExists(n)) if n == "passport.pdf"));
+        let invalid = vault.add(
+            &root.join("c/scan001.pdf"),
+            PASSWORD,
+            false,
+            false,
+            Some("../x.pdf".into()),
+        );
+        assert!(matches!(invalid, Err(VaultError::InvalidName(_))));
+        assert!(root.join("c/scan001.pdf").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_rename_validates_new_name() {
+        use crate::vault::VaultError;
+
+        let root = temp_root("vault-rename");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+        for name in ["a.txt", "b.txt"] {
+            fs::write(root.join(name), name).unwrap();
+            vault
+                .add(&root.join(name), PASSWORD, false, false, None)
+                .unwrap();
+        }
+
+        let renamed = vault.rename("a.txt", "notes.txt", PASSWORD).unwrap();
+        assert_eq!(renamed.name, "notes.txt");
+
+        let taken = vault.rename("notes.txt", "b.txt", PASSWORD);
+        assert!(matches!(taken, Err(VaultError::AlreadyExists(n)) if n == "b.txt"));
+        for invalid in ["../x", "/etc/x", "a/b", "", ".."] {
+            let result = vault.rename("notes.txt", invalid, PASSWORD);
+            assert!(
+                matches!(result, Err(VaultError::InvalidName(_))),
+                "{:?}",
+                invalid
+            );
+        }
+        let missing = vault.rename("gone.txt", "c.txt", PASSWORD);
+        assert!(matches!(missing, Err(VaultError::FileNotFound(_))));
+
+        let mut names: Vec<_> = vault
+            .list(PASSWORD)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["b.txt", "notes.txt"]);
+
+        let extracted = vault.extract("notes.txt", PASSWORD, &root).unwrap();
+        assert_eq!(extracted, root.join("notes.txt"));
+        assert_eq!(fs::read(&extracted).unwrap(), b"a.txt");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_find_matches_name_substring() {
+        let root = temp_root("vault-find");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Standard,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        for name in ["Taxes-2024.pdf", "taxes-2023.pdf", "passport.jpg"] {
+            let file = root.join(name);
+            fs::write(&file, name).unwrap();
+            vault.add(&file, PASSWORD, false, false, None).unwrap();
+        }
+
+        let names = |query: &str| -> Vec<String> {
+            vault
+                .find(query, PASSWORD)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect()
+        };
+        assert_eq!(names("TAXES"), ["taxes-2023.pdf", "Taxes-2024.pdf"]);
+        assert_eq!(names("port"), ["passport.jpg"]);
+        assert!(names("invoice").is_empty());
+        assert!(vault.find("taxes", "wrong password").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_vault_adds_keep_every_entry() {
+        let root = temp_root("vault-race");
+        let vault_dir = root.join("vault");
+        Vault::open(Some(vault_dir.clone()))
+            .init(
+                PASSWORD,
+                SecurityLevel::Standard,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        // Separate handles, as separate processes would have
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let file = root.join(format!("doc-{}.txt", i));
+                fs::write(&file, format!("doc {}", i)).unwrap();
+                let vault = Vault::open(Some(vault_dir.clone()));
+                std::thread::spawn(move || vault.add(&file, PASSWORD, false, false, None))
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap().unwrap();
+        }
+
+        let entries = Vault::open(Some(vault_dir)).list(PASSWORD).unwrap();
+        assert_eq!(entries.len(), 4);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_add_keep_source() {
+        let root = temp_root("vault-keep");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        let file = root.join("taxes.pdf");
+        fs::write(&file, b"2024 return").unwrap();
+        let inbox = root.join("inbox");
+        fs::create_dir_all(&inbox).unwrap();
+        fs::write(inbox.join("id.jpg"), b"id card").unwrap();
+
+        vault.add(&file, PASSWORD, false, true, None).unwrap();
+        let batch = vault
+            .add_files_in(&inbox, PASSWORD, false, true, false)
+            .unwrap();
+
+        assert_eq!(batch.added.len(), 1);
+        assert_eq!(fs::read(&file).unwrap(), b"2024 return");
+        assert!(inbox.join("id.jpg").exists());
+
+        let out = root.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let extracted = vault.extract("taxes.pdf", PASSWORD, &out).unwrap();
+        assert_eq!(fs::read(extracted).unwrap(), b"2024 return");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_recovery_codes_keep_their_length() {
+        let root = temp_root("vault-groups");
+        let vault = Vault::open(Some(root.join("vault")));
+
+        let codes = vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Standard,
+                KdfProfile::Interactive,
+                5,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(codes.code1.len(), 24);
+        assert_eq!(vault.recovery_groups().unwrap(), 5);
+
+        let new_codes = vault
+            .recover(&codes.code1, &codes.code2, "Another-Horse-43")
+            .unwrap();
+        assert_eq!(new_codes.code1.len(), 24);
+        assert_eq!(new_codes.entropy_bits(), 200);
+        assert!(vault.list("Another-Horse-43").is_ok());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_plan_add_flags_taken_names() {
+        let root = temp_root("vault-plan");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        for dir in ["a", "b"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("scan.pdf"), dir).unwrap();
+        }
+        fs::write(root.join("a/notes.txt"), b"notes").unwrap();
+        vault
+            .add(&root.join("a/notes.txt"), PASSWORD, false, true, None)
+            .unwrap();
+
+        let planned = vault
+            .plan_add(
+                PASSWORD,
+                vec![
+                    (root.join("a/scan.pdf"), "scan.pdf".into()),
+                    (root.join("b/scan.pdf"), "scan.pdf".into()),
+                    (root.join("a/notes.txt"), "notes.txt".into()),
+                    (root.join("a"), "a".into()),
+                ],
+            )
+            .unwrap();
+
+        let taken: Vec<_> = planned.iter().map(|p| (p.name.as_str(), p.taken)).collect();
+        assert_eq!(
+            taken,
+            [
+                ("scan.pdf", false),
+                ("scan.pdf", true),
+                ("notes.txt", true),
+                ("a", false)
+            ]
+        );
+        assert_eq!(planned[3].size, 6);
+        assert_eq!(vault.list(PASSWORD).unwrap().len(), 1);
+        assert!(root.join("b/scan.pdf").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_large_folders_need_allow_large() {
+        use crate::config::{Config, VaultConfig};
+        use crate::vault::commands::oversized_folder;
+
+        let config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        assert_eq!(config.vault.max_folder_bytes(), Ok(500 * 1024 * 1024));
+        let small = VaultConfig {
+            max_folder_size: "1KB".to_string(),
+        };
+        let limit = small.max_folder_bytes().unwrap();
+
+        let root = temp_root("vault-large");
+        fs::create_dir_all(root.join("videos/2024")).unwrap();
+        fs::write(root.join("videos/a.mp4"), vec![0u8; 600]).unwrap();
+        fs::write(root.join("videos/2024/b.mp4"), vec![0u8; 600]).unwrap();
+
+        assert_eq!(oversized_folder(&root.join("videos"), limit), Some(1200));
+        assert_eq!(oversized_folder(&root.join("videos/2024"), limit), None);
+        // A single file is never archived, whatever its size
+        assert_eq!(oversized_folder(&root.join("videos/a.mp4"), 10), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_without_kdf_params_uses_defaults() {
+        let root = temp_root("vault-kdf");
+        let vault = Vault::open(Some(root.clone()));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Balanced,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        // Strip the field like a meta.json written before cost profiles existed
+        let meta_path = root.join("meta.json");
+        let mut meta: serde_json::Value =
+            serde_json::from_slice(&fs::read(&meta_path).unwrap()).unwrap();
+        assert!(meta.as_object_mut().unwrap().remove("kdf").is_some());
+        fs::write(&meta_path, serde_json::to_vec(&meta).unwrap()).unwrap();
+
+        assert!(vault.list(PASSWORD).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_passwords_delay_next_attempt() {
+        use crate::vault::VaultError;
+
+        let root = temp_root("vault-backoff");
+        let vault = Vault::open(Some(root.clone()));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        for attempt in 1..=3 {
+            assert!(
+                vault.retry_delay().is_none(),
+                "attempt {} throttled",
+                attempt
+            );
+            let result = vault.list("Wrong-Password-1");
+            assert!(matches!(result, Err(VaultError::InvalidPassword)));
+        }
+        assert!(vault.retry_delay().is_some());
+
+        // The right password still works and clears the counter
+        vault.list(PASSWORD).unwrap();
+        assert!(vault.retry_delay().is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_password_does_not_undo_a_new_salt() {
+        use fs2::FileExt;
+        use std::time::Duration;
+
+        let root = temp_root("vault-meta-race");
+        let vault = Vault::open(Some(root.clone()));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        // Hold the vault lock like a running recover would
+        let lock = fs::OpenOptions::new()
+            .write(true)
+            .open(root.join("vault.lock"))
+            .unwrap();
+        FileExt::lock_exclusive(&lock).unwrap();
+
+        let reader_root = root.clone();
+        let reader = std::thread::spawn(move || {
+            Vault::open(Some(reader_root))
+                .list("Wrong-Password-1")
+                .is_err()
+        });
+        std::thread::sleep(Duration::from_millis(500));
+        // The failed attempt waits for the lock to count itself
+        assert!(!reader.is_finished());
+
+        // ...which replaces the salt before letting go
+        let meta_path = root.join("meta.json");
+        let mut meta: serde_json::Value =
+            serde_json::from_slice(&fs::read(&meta_path).unwrap()).unwrap();
+        meta["salt"] = serde_json::json!(vec![7u8; 16]);
+        fs::write(&meta_path, serde_json::to_vec(&meta).unwrap()).unwrap();
+        FileExt::unlock(&lock).unwrap();
+
+        assert!(reader.join().unwrap());
+        let after: serde_json::Value =
+            serde_json::from_slice(&fs::read(&meta_path).unwrap()).unwrap();
+        assert_eq!(after["salt"], meta["salt"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_vault_add_files_in_directory() {
+        let root = temp_root("vault-batch");
+        let vault = Vault::open(Some(root.join("vault")));
+        vault
+            .init(
+                PASSWORD,
+                SecurityLevel::Maximum,
+                KdfProfile::Interactive,
+                DEFAULT_CODE_GROUPS,
+            )
+            .unwrap();
+
+        let inbox = root.join("inbox");
+        fs::create_dir_all(inbox.join("nested")).unwrap();
+        fs::write(inbox.join("a.txt"), b"a").unwrap();
+        fs::write(inbox.join("b.txt"), b"b").unwrap();
+        fs::write(inbox.join("nested/a.txt"), b"other a").unwrap();
+        fs::write(inbox.join("nested/c.txt"), b"c").unwrap();
+
+        let batch = vault
+            .add_files_in(&inbox, PASSWORD, false, false, true)
+            .unwrap();
+
+        let mut names: Vec<_> = batch.added.iter().map(|e| e.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(batch.skipped, [inbox.join("nested/a.txt")]);
+        assert!(batch.failed.is_empty());
+        assert!(inbox.join("nested/a.txt").exists());
+        assert!(!inbox.join("b.txt").exists());
+        assert_eq!(vault.list(PASSWORD).unwrap().len(), 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod password_validation_tests {
+    use crate::vault::{validate_password, VaultError};
+
+    #[test]
+    fn test_password_too_short() {
+        let result = validate_password("Short1!");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_no_uppercase() {
+        let result = validate_password("lowercase123!@#");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_no_lowercase() {
+        let result = validate_password("UPPERCASE123!@#");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_no_digit() {
+        let result = validate_password("NoDigitsHere!@#");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_no_special() {
+        let result = validate_password("NoSpecial12345");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_common_pattern() {
+        let result = validate_password("MyPassword123!");
+        assert!(matches!(result, Err(VaultError::WeakPassword(_))));
+    }
+
+    #[test]
+    fn test_password_valid() {
+        let result = validate_password("SecureP@ss2024!");
         assert!(result.is_ok());
     }
 
@@ -206,7 +1713,7 @@ mod password_validation_tests {
 
 #[cfg(test)]
 mod modes_tests {
-    use crate::modes::{OrganizationMode, RenameMode};
+    use crate::modes::{ConflictPolicy, DateGranularity, OrganizationMode, RenameMode};
 
     #[test]
     fn test_organization_mode_from_str() {
@@ -230,18 +1737,36 @@ mod modes_tests {
     }
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     fn test_rename_mode_from_str() {
         assert!(matches!(RenameMode::from_str("clean"), Some(RenameMode::Clean)));
         assert!(matches!(RenameMode::from_str("date-prefix"), Some(RenameMode::DatePrefix)));
         assert!(matches!(RenameMode::from_str("skip"), None));
         assert!(matches!(RenameMode::from_str("none"), None));
+        assert!(matches!(
+            RenameMode::from_str("snake"),
+            Some(RenameMode::SnakeCase)
+        ));
+        assert!(matches!(
+            RenameMode::from_str("title"),
+            Some(RenameMode::TitleCase)
+        ));
     }
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     fn test_rename_mode_from_index() {
         assert!(matches!(RenameMode::from_index(0), Some(RenameMode::Clean)));
         assert!(matches!(RenameMode::from_index(1), Some(RenameMode::DatePrefix)));
         assert!(matches!(RenameMode::from_index(2), None)); // Skip returns None
+        assert!(matches!(
+            RenameMode::from_index(3),
+            Some(RenameMode::SnakeCase)
+        ));
+        assert!(matches!(
+            RenameMode::from_index(4),
+            Some(RenameMode::TitleCase)
+        ));
     }
 
     #[test]
@@ -256,5 +1781,2296 @@ mod modes_tests {
         assert_eq!(format!("{}", RenameMode::Clean), "Clean");
         assert_eq!(format!("{}", RenameMode::DatePrefix), "Date prefix");
         assert_eq!(format!("{}", RenameMode::Skip), "Skip");
+        assert_eq!(format!("{}", RenameMode::SnakeCase), "Snake case");
+        assert_eq!(format!("{}", RenameMode::TitleCase), "Title case");
+    }
+
+    #[test]
+    fn test_date_granularity_index_roundtrip() {
+        for g in [
+            DateGranularity::Year,
+            DateGranularity::Month,
+            DateGranularity::Day,
+        ] {
+            assert_eq!(DateGranularity::from_index(g.to_index()), g);
+        }
+        assert_eq!(DateGranularity::from_index(99), DateGranularity::Month);
+    }
+
+    #[test]
+    fn test_conflict_policy_from_str() {
+        assert_eq!(ConflictPolicy::from_str("rename"), ConflictPolicy::Rename);
+        assert_eq!(ConflictPolicy::from_str("skip"), ConflictPolicy::Skip);
+        assert_eq!(
+            ConflictPolicy::from_str("overwrite"),
+            ConflictPolicy::Overwrite
+        );
+        assert_eq!(
+            ConflictPolicy::from_str("keep-newer"),
+            ConflictPolicy::KeepNewer
+        );
+        assert_eq!(ConflictPolicy::from_str("invalid"), ConflictPolicy::Rename);
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::temp_root;
+    use crate::archive::{archive_folders, top_level_folders};
+    use crate::history::{undo_operations, FileMove, Operation};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_top_level_folders() {
+        let files_map: HashMap<String, Vec<PathBuf>> = HashMap::from([
+            ("Documents/2024".to_string(), Vec::new()),
+            ("Documents/2025".to_string(), Vec::new()),
+            ("Images".to_string(), Vec::new()),
+            (String::new(), Vec::new()),
+        ]);
+
+        assert_eq!(top_level_folders(&files_map), ["Documents", "Images"]);
+    }
+
+    #[test]
+    fn test_undo_unpacks_removed_archive() {
+        let root = temp_root("archive");
+        fs::create_dir_all(root.join("2024")).unwrap();
+        fs::write(root.join("2024/report.pdf"), b"report").unwrap();
+        // An older archive is kept; the new one gets a suffix
+        fs::write(root.join("2024.tar.gz"), b"old").unwrap();
+
+        let result = archive_folders(&root, &["2024".to_string()], true);
+
+        assert!(result.errors.is_empty());
+        assert_eq!(result.archived.len(), 1);
+        assert!(result.archived[0].removed);
+        assert!(result.archived[0].archive.ends_with("2024-1.tar.gz"));
+        assert!(!root.join("2024").exists());
+
+        let undo = undo_operations(&[Operation {
+            timestamp: "1".into(),
+            folder: root.to_string_lossy().to_string(),
+            moves: vec![FileMove {
+                from: root.join("report.pdf").to_string_lossy().to_string(),
+                to: root.join("2024/report.pdf").to_string_lossy().to_string(),
+                is_rename: false,
+            }],
+            archives: result.archived,
+        }]);
+
+        assert_eq!((undo.restored, undo.failed), (1, 0));
+        assert_eq!(fs::read(root.join("report.pdf")).unwrap(), b"report");
+        assert!(!root.join("2024-1.tar.gz").exists());
+        assert_eq!(fs::read(root.join("2024.tar.gz")).unwrap(), b"old");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::temp_root;
+    use crate::history::{undo_operations, FileMove, Operation};
+    use std::fs;
+
+    fn operation(timestamp: &str, from: &std::path::Path, to: &std::path::Path) -> Operation {
+        Operation {
+            timestamp: timestamp.to_string(),
+            folder: from.parent().unwrap().to_string_lossy().to_string(),
+            moves: vec![FileMove {
+                from: from.to_string_lossy().to_string(),
+                to: to.to_string_lossy().to_string(),
+                is_rename: false,
+            }],
+            archives: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_undo_operations_continues_past_failures() {
+        let root = temp_root("undo");
+        let sorted = root.join("Documents");
+        fs::create_dir_all(&sorted).unwrap();
+        fs::write(sorted.join("a.txt"), b"a").unwrap();
+
+        // Newest first: the missing file fails, the older operation still runs
+        let operations = vec![
+            operation("2", &root.join("missing.txt"), &sorted.join("missing.txt")),
+            operation("1", &root.join("a.txt"), &sorted.join("a.txt")),
+        ];
+        let result = undo_operations(&operations);
+
+        assert_eq!(result.restored, 1);
+        assert_eq!(result.failed, 1);
+        assert_eq!(result.operations.len(), 2);
+        assert_eq!(result.operations[0].failed, 1);
+        assert_eq!(result.operations[1].restored, 1);
+        assert!(root.join("a.txt").exists());
+        // Empty category folder is cleaned up once everything is reverted
+        assert!(!sorted.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_undo_stops_at_no_history_runs() {
+        use crate::history::undo_batch;
+        use std::path::Path;
+
+        let recorded = |timestamp: &str| {
+            operation(
+                timestamp,
+                &Path::new("/in").join(timestamp),
+                &Path::new("/in/Documents").join(timestamp),
+            )
+        };
+        let unrecorded = |timestamp: &str| Operation {
+            timestamp: timestamp.to_string(),
+            folder: "/tmp/scratch".to_string(),
+            moves: Vec::new(),
+            archives: Vec::new(),
+        };
+
+        // No operations recorded at all
+        assert!(matches!(undo_batch(&[]), (0, None)));
+
+        // A --no-history run on top: nothing is undone, the run is reported
+        let history = [unrecorded("3"), recorded("2"), recorded("1")];
+        let (batch, marker) = undo_batch(&history);
+        assert_eq!(batch, 0);
+        assert_eq!(marker.unwrap().timestamp, "3");
+
+        // Further back, it ends the batch instead of being skipped over
+        let history = [recorded("4"), recorded("3"), unrecorded("2"), recorded("1")];
+        let (batch, marker) = undo_batch(&history);
+        assert_eq!(batch, 2);
+        assert_eq!(marker.unwrap().timestamp, "2");
+
+        assert!(matches!(undo_batch(&history[..2]), (2, None)));
+    }
+
+    /// An operation on `day` of January 2026 in `folder`
+    fn operation_at(day: u32, folder: &std::path::Path) -> Operation {
+        let mut op = operation(
+            &format!("2026-01-{:02} 10:00:00", day),
+            &folder.join(format!("{}.txt", day)),
+            &folder.join(format!("Documents/{}.txt", day)),
+        );
+        op.folder = crate::history::absolute_path(&folder.to_string_lossy());
+        op
+    }
+
+    fn timestamps(operations: &[Operation]) -> Vec<&str> {
+        operations.iter().map(|op| &op.timestamp[8..10]).collect()
+    }
+
+    #[test]
+    fn test_take_last_removes_newest_operations() {
+        use crate::history::{get_last_operations_in, store_operation_in, take_last_operations_in};
+
+        let root = temp_root("history-take");
+        let dir = root.join("history");
+        for day in 1..=3 {
+            store_operation_in(&dir, operation_at(day, &root)).unwrap();
+        }
+
+        let taken = take_last_operations_in(&dir, 2, None).unwrap();
+        assert_eq!(timestamps(&taken), ["03", "02"]);
+        assert_eq!(timestamps(&get_last_operations_in(&dir, 10, None)), ["01"]);
+
+        let taken = take_last_operations_in(&dir, 5, None).unwrap();
+        assert_eq!(timestamps(&taken), ["01"]);
+        assert!(take_last_operations_in(&dir, 1, None).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_folder_history_includes_subfolders_only() {
+        use crate::history::{get_last_operations_in, store_operation_in, take_last_operations_in};
+
+        let root = temp_root("history-folders");
+        let dir = root.join("history");
+        let folder = root.join("inbox");
+        for path in [folder.join("sub"), root.join("inbox-other")] {
+            fs::create_dir_all(path).unwrap();
+        }
+        store_operation_in(&dir, operation_at(1, &folder)).unwrap();
+        store_operation_in(&dir, operation_at(2, &folder.join("sub"))).unwrap();
+        store_operation_in(&dir, operation_at(3, &root.join("inbox-other"))).unwrap();
+
+        // A sibling sharing the name as a prefix is not a subfolder
+        let in_folder = get_last_operations_in(&dir, 10, Some(&folder.to_string_lossy()));
+        assert_eq!(timestamps(&in_folder), ["01", "02"]);
+        let slash = format!("{}/", folder.to_string_lossy());
+        assert_eq!(get_last_operations_in(&dir, 10, Some(&slash)).len(), 2);
+
+        let taken = take_last_operations_in(&dir, 1, Some(&folder.to_string_lossy())).unwrap();
+        assert_eq!(timestamps(&taken), ["02"]);
+        assert_eq!(
+            timestamps(&get_last_operations_in(&dir, 10, None)),
+            ["01", "03"]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_prune_drops_old_and_undoable_operations() {
+        use crate::history::{get_last_operations_in, prune_operations_in, store_operation_in};
+        use chrono::{Local, TimeDelta};
+
+        let root = temp_root("history-prune");
+        let dir = root.join("history");
+        fs::create_dir_all(root.join("Documents")).unwrap();
+        fs::write(root.join("Documents/kept.txt"), b"kept").unwrap();
+
+        let days_ago = |days: i64, name: &str| {
+            let mut op = operation(
+                &(Local::now() - TimeDelta::days(days))
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                &root.join(name),
+                &root.join("Documents").join(name),
+            );
+            op.folder = name.to_string();
+            op
+        };
+        let marker = Operation {
+            moves: Vec::new(),
+            ..days_ago(1, "no-history")
+        };
+        for op in [
+            days_ago(40, "kept.txt"),
+            days_ago(20, "kept.txt"),
+            days_ago(10, "gone.txt"),
+            marker,
+            days_ago(0, "kept.txt"),
+        ] {
+            store_operation_in(&dir, op).unwrap();
+        }
+        let folders = |dir: &std::path::Path| -> Vec<String> {
+            get_last_operations_in(dir, 10, None)
+                .into_iter()
+                .map(|op| op.folder)
+                .collect()
+        };
+
+        // Only what is past the retention window goes
+        assert_eq!(
+            prune_operations_in(&dir, Some(TimeDelta::days(30)), false),
+            Ok(1)
+        );
+        assert_eq!(
+            folders(&dir),
+            ["kept.txt", "gone.txt", "no-history", "kept.txt"]
+        );
+
+        // Runs whose files are all gone, but not --no-history markers
+        assert_eq!(prune_operations_in(&dir, None, true), Ok(1));
+        assert_eq!(folders(&dir), ["kept.txt", "no-history", "kept.txt"]);
+        assert_eq!(
+            prune_operations_in(&dir, Some(TimeDelta::days(30)), true),
+            Ok(0)
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "sqlite-history")]
+    #[test]
+    fn test_sqlite_history_imports_json_once() {
+        use crate::history::{get_last_operations_in, store_operation_in, take_last_operations_in};
+
+        let root = temp_root("history-import");
+        let dir = root.join("history");
+        fs::create_dir_all(&dir).unwrap();
+        let history = serde_json::json!({
+            "operations": [operation_at(1, &root), operation_at(2, &root)],
+        });
+        fs::write(dir.join("history.json"), history.to_string()).unwrap();
+
+        // Carried over when the database is created, moves included
+        let imported = get_last_operations_in(&dir, 10, None);
+        assert_eq!(timestamps(&imported), ["01", "02"]);
+        assert_eq!(imported[1].moves[0].to, operation_at(2, &root).moves[0].to);
+
+        // ...and not again once it exists
+        take_last_operations_in(&dir, 2, None).unwrap();
+        store_operation_in(&dir, operation_at(3, &root)).unwrap();
+        assert_eq!(timestamps(&get_last_operations_in(&dir, 10, None)), ["03"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_run_left_in_journal() {
+        use crate::history::{pending_runs_in, Journal};
+
+        let root = temp_root("journal");
+        let dir = root.join("journal");
+        let _ = fs::remove_dir_all(&root);
+        let op = operation("1", &root.join("a.txt"), &root.join("Documents/a.txt"));
+
+        let journal = Journal::begin_in(&dir, &root.to_string_lossy()).unwrap();
+        journal.append(&op.moves[0]);
+        // A live run is not pending
+        assert!(pending_runs_in(&dir).is_empty());
+
+        // Dropped without finish(), as when the process is killed
+        drop(journal);
+        let runs = pending_runs_in(&dir);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].moves.len(), 1);
+        assert!(runs[0].moves[0].to.ends_with("a.txt"));
+        runs[0].discard();
+        assert!(pending_runs_in(&dir).is_empty());
+
+        // Finished runs and runs that moved nothing leave no journal behind
+        Journal::begin_in(&dir, &root.to_string_lossy())
+            .unwrap()
+            .finish();
+        drop(Journal::begin_in(&dir, &root.to_string_lossy()).unwrap());
+        assert!(pending_runs_in(&dir).is_empty());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_run_recorded_or_undone() {
+        use crate::history::{get_last_operations_in, pending_runs_in, Journal};
+
+        let root = temp_root("journal-recover");
+        let dir = root.join("journal");
+        let history = root.join("history");
+        let sorted = root.join("Documents");
+        fs::create_dir_all(&sorted).unwrap();
+
+        // A run killed after moving one file
+        let interrupted = |name: &str| {
+            fs::write(sorted.join(name), name).unwrap();
+            let op = operation("1", &root.join(name), &sorted.join(name));
+            let journal = Journal::begin_in(&dir, &root.to_string_lossy()).unwrap();
+            journal.append(&op.moves[0]);
+            drop(journal);
+        };
+
+        // Recorded: in the history under its own time, files left in place
+        interrupted("a.txt");
+        let runs = pending_runs_in(&dir);
+        runs[0].record_in(&history).unwrap();
+        let recorded = get_last_operations_in(&history, 10, None);
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].timestamp, runs[0].timestamp);
+        assert!(recorded[0].moves[0].to.ends_with("a.txt"));
+        assert!(sorted.join("a.txt").exists());
+        assert!(pending_runs_in(&dir).is_empty());
+
+        // Undone: the file goes back, nothing is added to the history
+        interrupted("b.txt");
+        let runs = pending_runs_in(&dir);
+        let result = runs[0].undo();
+        assert_eq!((result.restored, result.failed), (1, 0));
+        assert!(root.join("b.txt").exists());
+        assert!(!sorted.join("b.txt").exists());
+        assert_eq!(get_last_operations_in(&history, 10, None).len(), 1);
+        assert!(pending_runs_in(&dir).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_journal_kept_when_history_write_fails() {
+        use crate::history::{pending_runs_in, Journal};
+
+        let root = temp_root("journal-unsaved");
+        let dir = root.join("journal");
+        let op = operation("1", &root.join("a.txt"), &root.join("Documents/a.txt"));
+
+        // The history write failed: the journal is the only undo record left
+        let journal = Journal::begin_in(&dir, &root.to_string_lossy()).unwrap();
+        journal.append(&op.moves[0]);
+        crate::ui::finish_journal(Some(journal), Err("disk full".into()));
+        assert_eq!(pending_runs_in(&dir).len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+        let journal = Journal::begin_in(&dir, &root.to_string_lossy()).unwrap();
+        journal.append(&op.moves[0]);
+        crate::ui::finish_journal(Some(journal), Ok(()));
+        assert!(pending_runs_in(&dir).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_preview_undo_sorts_moves() {
+        use crate::history::preview_undo;
+
+        let root = temp_root("undo-preview");
+        let sorted = root.join("Documents");
+        fs::create_dir_all(&sorted).unwrap();
+        fs::write(sorted.join("a.txt"), b"a").unwrap();
+        fs::write(sorted.join("b.txt"), b"b").unwrap();
+        // b.txt's original path was reused since the move
+        fs::write(root.join("b.txt"), b"new b").unwrap();
+
+        let mut op = operation("1", &root.join("a.txt"), &sorted.join("a.txt"));
+        for name in ["b.txt", "gone.txt"] {
+            op.moves
+                .extend(operation("1", &root.join(name), &sorted.join(name)).moves);
+        }
+        let preview = preview_undo(&op);
+
+        let names = |moves: &[FileMove]| -> Vec<String> {
+            moves
+                .iter()
+                .map(|mv| {
+                    let from = std::path::Path::new(&mv.from);
+                    from.file_name().unwrap().to_string_lossy().to_string()
+                })
+                .collect()
+        };
+        assert_eq!(names(&preview.restorable), ["a.txt"]);
+        assert_eq!(names(&preview.blocked), ["b.txt"]);
+        assert_eq!(names(&preview.missing), ["gone.txt"]);
+        // Nothing was touched
+        assert!(sorted.join("a.txt").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_run_subfolder_is_recorded_and_undone() {
+        use crate::config::Config;
+        use crate::organizer::{move_files, OrganizeOptions};
+        use std::collections::HashMap;
+        use stellar_org::progress::NoProgress;
+
+        let root = temp_root("run-dir");
+        let original = root.join("a.pdf");
+        fs::write(&original, b"a").unwrap();
+
+        let config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        let mut options = OrganizeOptions::new(&config, None);
+        options.run_subfolder = Some("2024-01-15-1430".into());
+        let files_map = HashMap::from([("Documents".to_string(), vec![original.clone()])]);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+        let moves = result.moves;
+        // Counted under the category, not the run subfolder
+        assert_eq!(result.stats.categories["Documents"], 1);
+        assert!(!result.stats.categories.contains_key("2024-01-15-1430"));
+
+        let arrived = root.join("Documents/2024-01-15-1430/a.pdf");
+        assert!(arrived.exists());
+        assert_eq!(moves[0].to, arrived.to_string_lossy());
+
+        let result = undo_operations(&[Operation {
+            timestamp: "1".into(),
+            folder: root.to_string_lossy().to_string(),
+            moves,
+            archives: Vec::new(),
+        }]);
+        assert_eq!(result.restored, 1);
+        assert!(original.exists());
+        assert!(!root.join("Documents").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_flatten_into_a_dot_dot_root_records_the_move() {
+        use crate::config::Config;
+        use crate::organizer::{move_files, OrganizeOptions};
+        use std::collections::HashMap;
+        use stellar_org::progress::NoProgress;
+
+        let root = temp_root("flatten-dotdot");
+        let inner = root.join("sub/inner");
+        fs::create_dir_all(&inner).unwrap();
+        let original = inner.join("y.pdf");
+        fs::write(&original, b"y").unwrap();
+
+        // A root ending in `..` has no file name
+        let dotdot = inner.join("..");
+        let config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        let options = OrganizeOptions::new(&config, None);
+        let files_map = HashMap::from([(String::new(), vec![original.clone()])]);
+        let result =
+            move_files(&dotdot.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(result.moves.len(), 1);
+        assert_eq!(result.stats.files_moved, 1);
+        assert_eq!(result.stats.categories["Flattened"], 1);
+        assert!(root.join("sub/y.pdf").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_undo_restores_original_name_after_suffixed_rename() {
+        use crate::config::Config;
+        use crate::organizer::{move_files, OrganizeOptions};
+        use crate::renamer::RenameMode;
+        use std::collections::HashMap;
+        use stellar_org::progress::NoProgress;
+
+        let root = temp_root("undo-rename");
+        let sorted = root.join("Documents");
+        fs::create_dir_all(&sorted).unwrap();
+        fs::write(sorted.join("report-final.txt"), b"already there").unwrap();
+        let original = root.join("Report FINAL.txt");
+        fs::write(&original, b"new").unwrap();
+
+        let config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        let options = OrganizeOptions::new(&config, Some(RenameMode::Clean));
+        let files_map = HashMap::from([("Documents".to_string(), vec![original.clone()])]);
+        let moves = move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress)
+            .unwrap()
+            .moves;
+
+        assert_eq!(moves.len(), 1);
+        assert!(moves[0].is_rename);
+        assert!(moves[0].to.ends_with("report-final-1.txt"));
+
+        let result = undo_operations(&[Operation {
+            timestamp: "1".into(),
+            folder: root.to_string_lossy().to_string(),
+            moves,
+            archives: Vec::new(),
+        }]);
+
+        assert_eq!(result.restored, 1);
+        assert_eq!(fs::read(&original).unwrap(), b"new");
+        assert_eq!(
+            fs::read(sorted.join("report-final.txt")).unwrap(),
+            b"already there"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_absolute_path_survives_missing_file() {
+        use crate::history::absolute_path;
+
+        let dir = std::env::temp_dir();
+        let canonical_dir = fs::canonicalize(&dir).unwrap();
+        let missing = dir.join("stellar-never-created.txt");
+
+        assert_eq!(
+            absolute_path(&missing.to_string_lossy()),
+            canonical_dir
+                .join("stellar-never-created.txt")
+                .to_string_lossy()
+        );
+        assert!(std::path::Path::new(&absolute_path("relative/file.txt")).is_absolute());
+    }
+
+    #[test]
+    fn test_is_in_folder_matches_canonical_prefix() {
+        use crate::history::is_in_folder;
+
+        let dir = temp_root("scope");
+        fs::create_dir_all(dir.join("Documents")).unwrap();
+        let dir_str = dir.to_string_lossy().to_string();
+
+        assert!(is_in_folder(&format!("{}/", dir_str), &dir_str));
+        assert!(is_in_folder(&format!("{}/Documents", dir_str), &dir_str));
+        assert!(!is_in_folder(&format!("{}-other", dir_str), &dir_str));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod organizer_tests {
+    use super::temp_root;
+    use crate::config::Config;
+    use crate::modes::SymlinkPolicy;
+    use crate::organizer::{move_files, OrganizeOptions};
+    use crate::renamer::RenameMode;
+    use std::collections::HashMap;
+    use std::fs;
+    use stellar_org::progress::NoProgress;
+
+    fn default_config() -> Config {
+        toml::from_str(include_str!("../stellar.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_file_cap_flags_runs_over_the_limit() {
+        use crate::file_cap_warning;
+
+        let files_map = HashMap::from([
+            (
+                "Documents".to_string(),
+                vec!["a.pdf".into(), "b.txt".into(), "c.doc".into()],
+            ),
+            ("Images".to_string(), vec!["d.png".into(), "e.jpg".into()]),
+            ("Others".to_string(), Vec::new()),
+        ]);
+
+        // Counted across all categories
+        let warning = file_cap_warning(&files_map, Some(2)).unwrap();
+        assert!(warning.contains("Found 5 files"), "{}", warning);
+        assert!(warning.contains("--max-files 2"), "{}", warning);
+        assert!(file_cap_warning(&files_map, Some(4)).is_some());
+
+        assert!(file_cap_warning(&files_map, Some(5)).is_none());
+        assert!(file_cap_warning(&files_map, None).is_none());
+    }
+
+    #[test]
+    fn test_move_files_reports_bytes_per_file() {
+        use crate::organizer::total_size;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use stellar_org::progress::ProgressReporter;
+
+        #[derive(Default)]
+        struct Recorder {
+            files: AtomicU64,
+            bytes: AtomicU64,
+        }
+
+        impl ProgressReporter for Recorder {
+            fn inc(&self, _n: u64) {}
+            fn inc_file(&self, bytes: u64) {
+                self.files.fetch_add(1, Ordering::Relaxed);
+                self.bytes.fetch_add(bytes, Ordering::Relaxed);
+            }
+            fn finish(&self) {}
+        }
+
+        let root = temp_root("bytes");
+        fs::write(root.join("big.txt"), vec![b'x'; 5000]).unwrap();
+        fs::write(root.join("small.txt"), b"abc").unwrap();
+        let files_map = HashMap::from([(
+            "Documents".to_string(),
+            vec![
+                root.join("big.txt"),
+                root.join("small.txt"),
+                root.join("gone.txt"),
+            ],
+        )]);
+        assert_eq!(total_size(&files_map), 5003);
+
+        let config = default_config();
+        let options = OrganizeOptions::new(&config, None);
+        let recorder = Recorder::default();
+        move_files(&root.to_string_lossy(), &files_map, &options, &recorder).unwrap();
+
+        assert_eq!(recorder.files.load(Ordering::Relaxed), 3);
+        assert_eq!(recorder.bytes.load(Ordering::Relaxed), 5003);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_move_files_fails_on_missing_source() {
+        use crate::organizer::OrganizeError;
+
+        let root = temp_root("missing").join("gone");
+        let config = default_config();
+        let options = OrganizeOptions::new(&config, None);
+        let files_map = HashMap::from([("Documents".to_string(), vec![root.join("a.txt")])]);
+
+        let result = move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress);
+
+        assert!(matches!(result, Err(OrganizeError::NotADirectory(p)) if p == root));
+    }
+
+    #[test]
+    fn test_move_files_reports_touched_dirs() {
+        use crate::history::cleanup_empty_folders;
+
+        let root = temp_root("touched");
+        fs::create_dir_all(root.join("inbox/old")).unwrap();
+        fs::create_dir_all(root.join("untouched")).unwrap();
+        fs::write(root.join("inbox/old/a.txt"), b"a").unwrap();
+        fs::write(root.join("b.txt"), b"b").unwrap();
+        let files_map = HashMap::from([(
+            "Documents".to_string(),
+            vec![root.join("inbox/old/a.txt"), root.join("b.txt")],
+        )]);
+
+        let config = default_config();
+        let options = OrganizeOptions::new(&config, None);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(
+            result.touched_dirs,
+            vec![root.clone(), root.join("inbox/old")]
+        );
+
+        cleanup_empty_folders(&result.touched_dirs, Some(&root));
+        assert!(!root.join("inbox").exists());
+        assert!(root.join("untouched").exists());
+        assert!(root.join("Documents/a.txt").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cross_device_move_falls_back_to_copy_and_delete() {
+        use stellar_org::filesystem::{self, FsOp, MockFileSystem};
+
+        // The source folder must exist on disk; its files only live in the mock
+        let root = temp_root("exdev");
+        let src = root.join("report.pdf");
+        let dest = root.join("Documents").join("report.pdf");
+
+        let mock = MockFileSystem::new();
+        mock.add_file(&src, 42);
+        mock.fail(FsOp::Rename, &src, filesystem::cross_device_error());
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        options.fs = &mock;
+        let files_map = HashMap::from([("Documents".to_string(), vec![src.clone()])]);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(result.stats.files_moved, 1);
+        assert_eq!(result.moves.len(), 1);
+        let calls = mock.calls();
+        assert!(calls.contains(&(FsOp::Copy, src.clone())));
+        assert!(calls.contains(&(FsOp::RemoveFile, src.clone())));
+        assert!(mock.is_file(&dest));
+        assert!(!mock.is_file(&src));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_verify_moves_checks_cross_device_copies() {
+        use crate::stats::SkipReason;
+        use stellar_org::filesystem::{self, FsOp, MockFileSystem};
+
+        // The mock copies nothing on disk: what sits at the destination on
+        // disk plays the copy, and is what gets hashed against the source
+        let root = temp_root("verify");
+        let config = default_config();
+
+        for (copied, verified) in [(&b"report"[..], true), (&b"rep0rt"[..], false)] {
+            let src = root.join("report.pdf");
+            let dest = root.join("Documents").join("report.pdf");
+            fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            fs::write(&src, b"report").unwrap();
+            fs::write(&dest, copied).unwrap();
+
+            let mock = MockFileSystem::new();
+            mock.add_file(&src, 6);
+            mock.fail(FsOp::Rename, &src, filesystem::cross_device_error());
+
+            let mut options = OrganizeOptions::new(&config, None);
+            options.fs = &mock;
+            options.verify_moves = true;
+            let files_map = HashMap::from([("Documents".to_string(), vec![src.clone()])]);
+            let result =
+                move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+            assert!(mock.calls().contains(&(FsOp::Copy, src.clone())));
+            if verified {
+                assert_eq!(result.stats.files_moved, 1);
+                assert!(mock.is_file(&dest));
+                assert!(!mock.is_file(&src));
+            } else {
+                assert_eq!(result.stats.files_moved, 0);
+                assert!(result.moves.is_empty());
+                assert!(matches!(
+                    result.stats.skipped_files[0].reason,
+                    SkipReason::VerificationFailed
+                ));
+                // The bad copy is removed and the source left alone
+                assert!(mock.calls().contains(&(FsOp::RemoveFile, dest.clone())));
+                assert!(!mock.is_file(&dest));
+                assert!(mock.is_file(&src));
+            }
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cross_device_copy_keeps_file_times() {
+        use filetime::FileTime;
+        use stellar_org::filesystem::{FileSystem, RealFileSystem};
+
+        let root = temp_root("times");
+        let album = root.join("album");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(album.join("photo.jpg"), b"jpeg").unwrap();
+
+        let taken = FileTime::from_unix_time(1_500_000_000, 0);
+        let opened = FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_times(album.join("photo.jpg"), opened, taken).unwrap();
+        filetime::set_file_times(&album, opened, taken).unwrap();
+
+        let copy = root.join("copy");
+        RealFileSystem.copy(&album, &copy).unwrap();
+
+        for path in [copy.clone(), copy.join("photo.jpg")] {
+            let metadata = fs::metadata(&path).unwrap();
+            assert_eq!(FileTime::from_last_modification_time(&metadata), taken);
+            assert_eq!(FileTime::from_last_access_time(&metadata), opened);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_permission_denied_move_is_skipped() {
+        use crate::stats::SkipReason;
+        use stellar_org::filesystem::{FsOp, MockFileSystem};
+
+        let root = temp_root("denied");
+        let src = root.join("photo.jpg");
+
+        let mock = MockFileSystem::new();
+        mock.add_file(&src, 7);
+        mock.fail(
+            FsOp::Rename,
+            &src,
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        options.fs = &mock;
+        let files_map = HashMap::from([("Images".to_string(), vec![src.clone()])]);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(result.stats.files_moved, 0);
+        assert!(result.moves.is_empty());
+        assert_eq!(result.stats.skipped_files.len(), 1);
+        assert!(matches!(
+            result.stats.skipped_files[0].reason,
+            SkipReason::PermissionDenied
+        ));
+        assert!(!mock.calls().iter().any(|(op, _)| *op == FsOp::Copy));
+        assert!(mock.is_file(&src));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_conflict_suffix_finds_next_free_name() {
+        use crate::modes::ConflictSuffix;
+        use crate::organizer::resolve_conflict;
+        use std::collections::HashSet;
+        use std::path::Path;
+        use stellar_org::filesystem::MockFileSystem;
+
+        let dir = Path::new("/docs");
+        let today = chrono::Local::now().format("%Y%m%d").to_string();
+        let cases = [
+            (ConflictSuffix::DashNumber, "report-1.pdf", "report-2.pdf"),
+            (
+                ConflictSuffix::ParenNumber,
+                "report (1).pdf",
+                "report (2).pdf",
+            ),
+            (
+                ConflictSuffix::UnderscoreNumber,
+                "report_1.pdf",
+                "report_2.pdf",
+            ),
+            (
+                ConflictSuffix::Timestamp,
+                &format!("report-{}.pdf", today),
+                &format!("report-{}-1.pdf", today),
+            ),
+        ];
+
+        for (suffix, first, second) in cases {
+            let mock = MockFileSystem::new();
+            mock.add_file(dir.join("report.pdf"), 1);
+            let mut reserved = HashSet::new();
+
+            let free = resolve_conflict(&mock, &dir.join("report.pdf"), suffix, &reserved);
+            assert_eq!(free, dir.join(first), "{}", suffix);
+
+            // The first candidate is taken by a planned move: the next one is used
+            reserved.insert(dir.join(first));
+            let free = resolve_conflict(&mock, &dir.join("report.pdf"), suffix, &reserved);
+            assert_eq!(free, dir.join(second), "{}", suffix);
+        }
+
+        // A free name is kept as is, whatever the suffix
+        let mock = MockFileSystem::new();
+        let path = dir.join("notes.txt");
+        let free = resolve_conflict(&mock, &path, ConflictSuffix::Timestamp, &HashSet::new());
+        assert_eq!(free, path);
+    }
+
+    #[test]
+    fn test_moves_follow_a_stable_order() {
+        use std::path::Path;
+
+        let base = temp_root("order");
+        let names = [
+            "song.mp3",
+            "b.pdf",
+            "a.jpg",
+            "notes.txt",
+            "c.jpg",
+            "a.pdf",
+            "clip.mp4",
+            "z.png",
+        ];
+
+        let config = default_config();
+        let run = |root: &Path, jobs: usize| -> Vec<(String, String)> {
+            fs::create_dir_all(root).unwrap();
+            let mut files_map: HashMap<String, Vec<_>> = HashMap::new();
+            for name in names {
+                fs::write(root.join(name), name).unwrap();
+                let ext = name.rsplit('.').next().unwrap();
+                let category =
+                    crate::config::resolve_folder(&config.categories, &HashMap::new(), ext);
+                files_map.entry(category).or_default().push(root.join(name));
+            }
+            let mut options = OrganizeOptions::new(&config, None);
+            options.jobs = jobs;
+            let result =
+                move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+            let relative = |p: &str| {
+                Path::new(p)
+                    .strip_prefix(root)
+                    .unwrap()
+                    .display()
+                    .to_string()
+            };
+            result
+                .moves
+                .iter()
+                .map(|mv| (relative(&mv.from), relative(&mv.to)))
+                .collect()
+        };
+
+        let first = run(&base.join("one"), 1);
+        assert_eq!(first, run(&base.join("two"), 1));
+        assert_eq!(first, run(&base.join("three"), 4));
+
+        // Categories by name, files by path within each
+        let mut expected = first.clone();
+        expected.sort_by(|a, b| {
+            let folder = |p: &str| Path::new(p).parent().unwrap().to_path_buf();
+            folder(&a.1).cmp(&folder(&b.1)).then(a.0.cmp(&b.0))
+        });
+        assert_eq!(first, expected);
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_conflict_policies_on_existing_destination() {
+        use crate::modes::ConflictPolicy;
+        use crate::stats::SkipReason;
+        use filetime::FileTime;
+
+        let old = FileTime::from_unix_time(1_500_000_000, 0);
+        let existing_time = FileTime::from_unix_time(1_550_000_000, 0);
+        let new = FileTime::from_unix_time(1_600_000_000, 0);
+        let config = default_config();
+
+        // (policy, source mtime, what Documents/ holds afterwards, source moved)
+        let cases = [
+            (
+                ConflictPolicy::Rename,
+                new,
+                vec![("report-1.pdf", "source"), ("report.pdf", "existing")],
+                true,
+            ),
+            (
+                ConflictPolicy::Skip,
+                new,
+                vec![("report.pdf", "existing")],
+                false,
+            ),
+            (
+                ConflictPolicy::Overwrite,
+                old,
+                vec![("report.pdf", "source")],
+                true,
+            ),
+            (
+                ConflictPolicy::KeepNewer,
+                new,
+                vec![("report.pdf", "source")],
+                true,
+            ),
+            (
+                ConflictPolicy::KeepNewer,
+                old,
+                vec![("report.pdf", "existing")],
+                false,
+            ),
+        ];
+
+        for (policy, source_time, expected, moved) in cases {
+            let root = temp_root("conflict-policy");
+            let sorted = root.join("Documents");
+            fs::create_dir_all(&sorted).unwrap();
+            fs::write(sorted.join("report.pdf"), "existing").unwrap();
+            filetime::set_file_mtime(sorted.join("report.pdf"), existing_time).unwrap();
+            let src = root.join("report.pdf");
+            fs::write(&src, "source").unwrap();
+            filetime::set_file_mtime(&src, source_time).unwrap();
+
+            let mut options = OrganizeOptions::new(&config, None);
+            options.conflict_policy = policy;
+            let files_map = HashMap::from([("Documents".to_string(), vec![src.clone()])]);
+            let result =
+                move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+            let mut found: Vec<(String, String)> = fs::read_dir(&sorted)
+                .unwrap()
+                .flatten()
+                .map(|e| {
+                    let content = fs::read_to_string(e.path()).unwrap();
+                    (e.file_name().to_string_lossy().to_string(), content)
+                })
+                .collect();
+            found.sort();
+            let expected: Vec<(String, String)> = expected
+                .into_iter()
+                .map(|(name, content)| (name.to_string(), content.to_string()))
+                .collect();
+            assert_eq!(found, expected, "{:?}", policy);
+
+            assert_eq!(src.exists(), !moved, "{:?}", policy);
+            if !moved {
+                assert!(matches!(
+                    result.stats.skipped_files[0].reason,
+                    SkipReason::ConflictSkipped
+                ));
+            }
+        }
+
+        fs::remove_dir_all(temp_root("conflict-policy")).unwrap();
+    }
+
+    #[test]
+    fn test_runs_never_share_a_run_subfolder() {
+        use crate::organizer::run_subfolder_name;
+
+        // Down to the second: 2024-01-15-143005
+        assert_eq!(run_subfolder_name().len(), "2024-01-15-143005".len());
+
+        let root = temp_root("run-dir-twice");
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        options.run_subfolder = Some("2024-01-15-143005".into());
+
+        for (name, run) in [
+            ("a.pdf", "2024-01-15-143005"),
+            ("b.pdf", "2024-01-15-143005-2"),
+            ("c.pdf", "2024-01-15-143005-3"),
+        ] {
+            let file = root.join(name);
+            fs::write(&file, name).unwrap();
+            let files_map = HashMap::from([("Documents".to_string(), vec![file])]);
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+            assert!(root.join("Documents").join(run).join(name).exists());
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_template_category_is_the_destination_folder() {
+        use crate::organizer::preview_renames;
+        use std::path::PathBuf;
+
+        let mut config = default_config();
+        config
+            .overrides
+            .insert("torrent".to_string(), "Torrents".to_string());
+        let options = OrganizeOptions::new(
+            &config,
+            Some(RenameMode::Template("{category}_{name}.{ext}".to_string())),
+        );
+        let files_map = HashMap::from([
+            // Put in Images by a rule or by sniffing, whatever the extension says
+            ("Images".to_string(), vec![PathBuf::from("/in/scan.pdf")]),
+            (
+                "Documents/2024".to_string(),
+                vec![PathBuf::from("/in/report.pdf")],
+            ),
+            (
+                "2024/03-march".to_string(),
+                vec![PathBuf::from("/in/linux.torrent")],
+            ),
+        ]);
+
+        let mut names: Vec<_> = preview_renames(&files_map, &options)
+            .into_iter()
+            .map(|r| r.to)
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "Documents_report.pdf",
+                "Images_scan.pdf",
+                "Torrents_linux.torrent"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preview_renames_flags_collisions_per_category() {
+        use crate::organizer::preview_renames;
+        use std::path::PathBuf;
+
+        let config = default_config();
+        let options = OrganizeOptions::new(&config, Some(RenameMode::Clean));
+        let files_map = HashMap::from([
+            (
+                "Documents".to_string(),
+                vec![
+                    PathBuf::from("/in/Élève.pdf"),
+                    PathBuf::from("/in/eleve.pdf"),
+                    PathBuf::from("/in/Notes 2024.txt"),
+                ],
+            ),
+            ("Images".to_string(), vec![PathBuf::from("/in/eleve.jpg")]),
+            (
+                "Archives".to_string(),
+                vec![PathBuf::from("/in/notes-2024.txt")],
+            ),
+        ]);
+
+        let renames = preview_renames(&files_map, &options);
+        let summary: Vec<_> = renames
+            .iter()
+            .map(|r| (r.category.as_str(), r.to.as_str(), r.collides))
+            .collect();
+
+        // Same new name in another category is not a collision
+        assert_eq!(
+            summary,
+            [
+                ("Archives", "notes-2024.txt", false),
+                ("Documents", "notes-2024.txt", false),
+                ("Documents", "eleve.pdf", true),
+                ("Documents", "eleve.pdf", true),
+                ("Images", "eleve.jpg", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_collisions_can_be_skipped_or_renamed() {
+        use crate::organizer::{generate_dry_run_preview, rename_collisions, skip_collisions};
+        use std::path::{Path, PathBuf};
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, Some(RenameMode::Clean));
+        let mut files_map = HashMap::from([(
+            "Documents".to_string(),
+            vec![
+                PathBuf::from("/in/Élève.pdf"),
+                PathBuf::from("/in/eleve.pdf"),
+                PathBuf::from("/in/ÉLÈVE.pdf"),
+                PathBuf::from("/in/other.pdf"),
+            ],
+        )]);
+
+        let preview = generate_dry_run_preview("/in", &files_map, &options);
+        assert_eq!(preview.collisions(), 3);
+
+        let collisions = rename_collisions(&files_map, &options);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].name, "eleve.pdf");
+        assert_eq!(collisions[0].files.len(), 3);
+
+        // A picked name takes the file out of the collision
+        options
+            .names
+            .insert(PathBuf::from("/in/eleve.pdf"), "eleve-copy.pdf".into());
+        let collisions = rename_collisions(&files_map, &options);
+        assert_eq!(collisions[0].files.len(), 2);
+        let preview = generate_dry_run_preview("/in", &files_map, &options);
+        assert!(preview
+            .moves
+            .iter()
+            .any(|m| m.to == Path::new("/in/Documents/eleve-copy.pdf") && !m.collides));
+
+        // Skipping keeps the first file of each name
+        let skipped = skip_collisions(&mut files_map, &collisions);
+        assert_eq!(skipped, [PathBuf::from("/in/Élève.pdf")]);
+        assert_eq!(files_map["Documents"].len(), 3);
+        assert!(rename_collisions(&files_map, &options).is_empty());
+    }
+
+    #[test]
+    fn test_existing_only_leaves_files_without_a_folder() {
+        use crate::organizer::generate_dry_run_preview;
+        use crate::stats::SkipReason;
+
+        let root = temp_root("existing");
+        fs::create_dir_all(root.join("Documents")).unwrap();
+        fs::write(root.join("a.pdf"), b"a").unwrap();
+        fs::write(root.join("b.jpg"), b"b").unwrap();
+        let files_map = HashMap::from([
+            ("Documents".to_string(), vec![root.join("a.pdf")]),
+            ("Images".to_string(), vec![root.join("b.jpg")]),
+        ]);
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        options.existing_only = true;
+        let preview = generate_dry_run_preview(&root.to_string_lossy(), &files_map, &options);
+        assert_eq!(preview.total_files, 1);
+
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+        assert!(root.join("Documents/a.pdf").exists());
+        assert!(root.join("b.jpg").exists());
+        assert!(!root.join("Images").exists());
+        assert_eq!(result.stats.skipped_files.len(), 1);
+        assert!(matches!(
+            result.stats.skipped_files[0].reason,
+            SkipReason::NoMatchingFolder
+        ));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_dedupe_rerun_keeps_organized_files() {
+        use crate::modes::OrganizationMode;
+        use crate::scanner::ScanOptions;
+
+        let root = temp_root("dedupe-rerun");
+        fs::write(root.join("notes.txt"), b"only copy").unwrap();
+        let root_str = root.to_string_lossy();
+        let config = default_config();
+        let scan_options = ScanOptions::new(&config);
+
+        let files_map = crate::scan_files(&root_str, &scan_options, OrganizationMode::Date, false);
+        let mut options = OrganizeOptions::new(&config, None);
+        move_files(&root_str, &files_map, &options, &NoProgress).unwrap();
+
+        // The recursive re-run finds each file already at its destination
+        options.dedupe_on_move = true;
+        let files_map = crate::scan_files(&root_str, &scan_options, OrganizationMode::Date, true);
+        let organized = files_map.values().flatten().next().unwrap().clone();
+        let result = move_files(&root_str, &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(result.stats.duplicates_found, 0);
+        assert_eq!(result.stats.files_moved, 0);
+        assert_eq!(fs::read(&organized).unwrap(), b"only copy");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_excluded_files_stay_in_place() {
+        use crate::organizer::exclude_files;
+
+        let root = temp_root("review");
+        for name in ["a.pdf", "b.pdf", "c.jpg"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+        let mut files_map = HashMap::from([
+            (
+                "Documents".to_string(),
+                vec![root.join("a.pdf"), root.join("b.pdf")],
+            ),
+            ("Images".to_string(), vec![root.join("c.jpg")]),
+        ]);
+
+        exclude_files(&mut files_map, &[root.join("b.pdf"), root.join("c.jpg")]);
+        assert_eq!(files_map.len(), 1);
+
+        let config = default_config();
+        let options = OrganizeOptions::new(&config, None);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+        assert_eq!(result.moves.len(), 1);
+        assert!(root.join("Documents/a.pdf").exists());
+        assert!(root.join("b.pdf").exists());
+        assert!(root.join("c.jpg").exists());
+        assert!(!root.join("Images").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_moves_never_share_a_destination() {
+        let root = temp_root("jobs");
+
+        // All of these clean-rename to report.txt
+        let names = [
+            "report.txt",
+            "report (1).txt",
+            "report copy.txt",
+            "Report.txt",
+        ];
+        let files: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let path = root.join(name);
+                fs::write(&path, name).unwrap();
+                path
+            })
+            .collect();
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, Some(RenameMode::Clean));
+        options.jobs = 4;
+        let files_map = HashMap::from([("Documents".to_string(), files)]);
+
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+        assert_eq!(result.stats.files_moved, names.len());
+        assert_eq!(result.moves.len(), names.len());
+        assert_eq!(
+            fs::read_dir(root.join("Documents")).unwrap().count(),
+            names.len()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_flatten_moves_nested_files_to_root() {
+        use crate::history::cleanup_empty_folders;
+        use crate::scanner::{scan_flatten, ScanOptions};
+
+        let root = temp_root("flatten");
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+        fs::create_dir_all(root.join("Images")).unwrap();
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("top.txt"), b"top").unwrap();
+        fs::write(root.join("a").join("notes.txt"), b"a").unwrap();
+        fs::write(root.join("a").join("b").join("notes.txt"), b"b").unwrap();
+        fs::write(root.join("Images").join("cat.png"), b"png").unwrap();
+        fs::write(root.join("node_modules").join("dep.js"), b"js").unwrap();
+
+        let config = default_config();
+        let files = scan_flatten(&root.to_string_lossy(), &ScanOptions::new(&config));
+        assert_eq!(files.len(), 3);
+
+        let options = OrganizeOptions::new(&config, None);
+        let files_map = HashMap::from([(String::new(), files)]);
+        let result =
+            move_files(&root.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+        assert_eq!(result.stats.files_moved, 3);
+        assert!(root.join("notes.txt").exists());
+        assert!(root.join("notes-1.txt").exists());
+        assert!(root.join("cat.png").exists());
+
+        let emptied: Vec<_> = result
+            .moves
+            .iter()
+            .map(|mv| {
+                std::path::Path::new(&mv.from)
+                    .parent()
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        cleanup_empty_folders(&emptied, Some(&root));
+        assert!(!root.join("a").exists());
+        assert!(!root.join("Images").exists());
+        assert!(root.join("node_modules").join("dep.js").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() {
+        let root = temp_root("links");
+        let outside = root.join("outside");
+        let inbox = root.join("inbox");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&inbox).unwrap();
+        let target = outside.join("secret.txt");
+        fs::write(&target, b"keep me").unwrap();
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        let link = inbox.join("secret.txt");
+        let moved = inbox.join("Documents").join("secret.txt");
+        let organize = |options: &OrganizeOptions| {
+            let files_map = HashMap::from([("Documents".to_string(), vec![link.clone()])]);
+            move_files(&inbox.to_string_lossy(), &files_map, options, &NoProgress).unwrap()
+        };
+
+        // Skip (default): the link stays and is reported
+        std::os::unix::fs::symlink("../outside/secret.txt", &link).unwrap();
+        let result = organize(&options);
+        assert_eq!(result.stats.files_skipped, 1);
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        // Move-link: the relative link still resolves from its new folder
+        options.symlinks = SymlinkPolicy::MoveLink;
+        assert_eq!(organize(&options).stats.files_moved, 1);
+        assert!(fs::symlink_metadata(&moved)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read(&moved).unwrap(), b"keep me");
+        fs::remove_file(&moved).unwrap();
+
+        // Follow: a real copy replaces the link, the target is untouched
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        options.symlinks = SymlinkPolicy::Follow;
+        assert_eq!(organize(&options).stats.files_moved, 1);
+        assert!(!fs::symlink_metadata(&moved)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert_eq!(fs::read(&target).unwrap(), b"keep me");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies_undo_round_trip() {
+        use crate::history::{absolute_path, undo_operations, FileMove, Operation};
+
+        let root = temp_root("links-undo");
+        let outside = root.join("outside");
+        let inbox = root.join("inbox");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&inbox).unwrap();
+        let target = outside.join("secret.txt");
+        fs::write(&target, b"keep me").unwrap();
+        let link = inbox.join("secret.txt");
+
+        let config = default_config();
+        let mut options = OrganizeOptions::new(&config, None);
+        for policy in [
+            SymlinkPolicy::Skip,
+            SymlinkPolicy::MoveLink,
+            SymlinkPolicy::Follow,
+        ] {
+            let _ = fs::remove_file(&link);
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+            options.symlinks = policy;
+            let files_map = HashMap::from([("Documents".to_string(), vec![link.clone()])]);
+            let result =
+                move_files(&inbox.to_string_lossy(), &files_map, &options, &NoProgress).unwrap();
+
+            // Stored the way the history stores them
+            let moves = result
+                .moves
+                .iter()
+                .map(|mv| FileMove {
+                    from: absolute_path(&mv.from),
+                    to: absolute_path(&mv.to),
+                    is_rename: mv.is_rename,
+                })
+                .collect();
+            let undo = undo_operations(&[Operation {
+                timestamp: "1".to_string(),
+                folder: absolute_path(&inbox.to_string_lossy()),
+                moves,
+                archives: Vec::new(),
+            }]);
+
+            assert_eq!(undo.failed, 0, "{:?}", policy);
+            assert_eq!(fs::read(&target).unwrap(), b"keep me", "{:?}", policy);
+            assert_eq!(fs::read(&link).unwrap(), b"keep me", "{:?}", policy);
+            let moved = inbox.join("Documents/secret.txt");
+            assert!(fs::symlink_metadata(moved).is_err(), "{:?}", policy);
+            // Only --symlinks follow turned the link into a copy
+            let is_link = fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink();
+            assert_eq!(is_link, policy != SymlinkPolicy::Follow, "{:?}", policy);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod scanner_tests {
+    use super::temp_root;
+    use crate::config::Config;
+    use crate::scanner::{scan_by_category, ScanOptions};
+    use std::fs;
+
+    fn default_config() -> Config {
+        toml::from_str(include_str!("../stellar.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_date_source_picks_creation_or_modification_time() {
+        use crate::modes::DateSource;
+        use crate::renamer::{rename_file, RenameMode};
+        use crate::scanner::{scan_by_date, scan_hybrid};
+        use chrono::{DateTime, Datelike, Local, TimeZone};
+        use filetime::FileTime;
+
+        let root = temp_root("date-source");
+        let path = root.join("notes.txt");
+        fs::write(&path, b"x").unwrap();
+        let modified = Local.with_ymd_and_hms(2019, 5, 10, 12, 0, 0).unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(modified.timestamp(), 0)).unwrap();
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        let root_str = root.to_string_lossy();
+
+        // Modified: the current behavior, everywhere dates are read
+        options.date_source = DateSource::Modified;
+        assert!(scan_by_date(&root_str, &options).contains_key("2019/05-may"));
+        assert!(scan_hybrid(&root_str, &options).contains_key("Documents/2019"));
+        let renamed = rename_file(
+            &path,
+            &RenameMode::DatePrefix,
+            "Documents",
+            DateSource::Modified,
+            &config.rename,
+        );
+        assert_eq!(renamed, "2019-05-10-notes.txt");
+
+        // Created: the creation time where the file system has one, else mtime
+        let created: DateTime<Local> = fs::metadata(&path)
+            .unwrap()
+            .created()
+            .map(DateTime::from)
+            .unwrap_or(modified);
+        options.date_source = DateSource::Created;
+        let expected = format!(
+            "{}/{:02}-{}",
+            created.year(),
+            created.month(),
+            config.date.month_names[created.month0() as usize]
+        );
+        let files = scan_by_date(&root_str, &options);
+        assert!(files.contains_key(&expected), "{:?}", files.keys());
+        let renamed = rename_file(
+            &path,
+            &RenameMode::DatePrefix,
+            "Documents",
+            DateSource::Created,
+            &config.rename,
+        );
+        assert_eq!(renamed, format!("{}-notes.txt", created.format("%Y-%m-%d")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_date_granularity_shapes_date_and_hybrid_folders() {
+        use crate::modes::{DateGranularity, DateSource};
+        use crate::scanner::{scan_by_date, scan_hybrid};
+        use chrono::{Local, TimeZone};
+        use filetime::FileTime;
+
+        let root = temp_root("granularity");
+        let path = root.join("notes.txt");
+        fs::write(&path, b"x").unwrap();
+        let modified = Local.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(modified.timestamp(), 0)).unwrap();
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        options.date_source = DateSource::Modified;
+        let root_str = root.to_string_lossy();
+        let folders = |options: &ScanOptions| {
+            let date: Vec<_> = scan_by_date(&root_str, options).into_keys().collect();
+            let hybrid: Vec<_> = scan_hybrid(&root_str, options).into_keys().collect();
+            (date, hybrid)
+        };
+
+        // Unset: month folders for the date mode, year folders for hybrid
+        assert_eq!(
+            folders(&options),
+            (
+                vec!["2024/01-january".into()],
+                vec!["Documents/2024".into()]
+            )
+        );
+
+        for (granularity, date) in [
+            (DateGranularity::Year, "2024"),
+            (DateGranularity::Month, "2024/01-january"),
+            (DateGranularity::Day, "2024/01-january/15"),
+        ] {
+            options.date_granularity = Some(granularity);
+            assert_eq!(
+                folders(&options),
+                (vec![date.to_string()], vec![format!("Documents/{}", date)])
+            );
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scan_by_date_uses_configured_month_names() {
+        use crate::scanner::scan_by_date;
+        use chrono::{Datelike, Local};
+
+        let root = temp_root("months");
+        fs::write(root.join("note.txt"), b"x").unwrap();
+
+        let mut config = default_config();
+        config.date.month_names = (1..=12).map(|m| format!("mois{}", m)).collect();
+        let options = ScanOptions::new(&config);
+
+        let files = scan_by_date(&root.to_string_lossy(), &options);
+
+        let now = Local::now();
+        let expected = format!("{}/{:02}-mois{}", now.year(), now.month(), now.month());
+        assert!(files.contains_key(&expected), "{:?}", files.keys());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_looks_organized() {
+        use crate::scanner::looks_organized;
+
+        let root = temp_root("organized");
+        for dir in ["Documents", "images", "2024"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("new.pdf"), b"x").unwrap();
+        let config = default_config();
+        let path = root.to_string_lossy().to_string();
+
+        assert!(looks_organized(&path, &config.categories));
+
+        // Several loose files: a regular folder that happens to have category folders
+        for name in ["a.txt", "b.txt"] {
+            fs::write(root.join(name), b"x").unwrap();
+        }
+        assert!(!looks_organized(&path, &config.categories));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_include_hidden_still_skips_junk() {
+        let root = temp_root("hidden");
+        for name in [".zshrc.bak", ".stellar.lock", "._.DS_Store", "notes.txt"] {
+            fs::write(root.join(name), b"x").unwrap();
+        }
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        let count = |options: &ScanOptions| -> usize {
+            scan_by_category(&root.to_string_lossy(), options)
+                .values()
+                .map(Vec::len)
+                .sum()
+        };
+
+        assert_eq!(count(&options), 1);
+        options.include_hidden = true;
+        assert_eq!(count(&options), 2);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_with_folders_moves_plain_subfolders_only() {
+        let root = temp_root("folders");
+        for dir in ["extracted", "Documents", "repo", ".cache"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("repo").join("Cargo.toml"), b"").unwrap();
+        fs::write(root.join("notes.txt"), b"x").unwrap();
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        options.with_folders = true;
+        let grouped = scan_by_category(&root.to_string_lossy(), &options);
+
+        assert_eq!(grouped["Folders"], vec![root.join("extracted")]);
+        assert_eq!(grouped["Documents"], vec![root.join("notes.txt")]);
+        assert_eq!(grouped.len(), 2);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_extensions_are_counted() {
+        let root = temp_root("unknown");
+        for name in ["a.xyz", "b.XYZ", "c.abc", "d.pdf", "README"] {
+            fs::write(root.join(name), b"x").unwrap();
+        }
+
+        let config = default_config();
+        let options = ScanOptions::new(&config);
+        let grouped = scan_by_category(&root.to_string_lossy(), &options);
+        let unknown = options.unknown_extensions.take();
+
+        assert_eq!(grouped["Others"].len(), 4);
+        assert_eq!(unknown.len(), 2);
+        assert_eq!(unknown["xyz"], 2);
+        assert_eq!(unknown["abc"], 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_sniff_category_trusts_magic_over_extension() {
+        use crate::scanner::sniff_category;
+
+        let root = temp_root("sniff");
+        fs::write(root.join("photo.txt"), b"\x89PNG\r\n\x1a\n0000IHDR").unwrap();
+        fs::write(root.join("scan"), b"%PDF-1.7\n").unwrap();
+        fs::write(root.join("report.docx"), b"PK\x03\x04rest-of-zip").unwrap();
+        fs::write(root.join("notes.txt"), b"plain text").unwrap();
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        let folder_of = |options: &ScanOptions, name: &str| -> Option<String> {
+            scan_by_category(&root.to_string_lossy(), options)
+                .into_iter()
+                .find(|(_, files)| files.iter().any(|f| f.ends_with(name)))
+                .map(|(folder, _)| folder)
+        };
+
+        assert_eq!(
+            folder_of(&options, "photo.txt").as_deref(),
+            Some("Documents")
+        );
+        assert_eq!(folder_of(&options, "scan").as_deref(), Some("Others"));
+
+        options.sniff = true;
+        assert_eq!(folder_of(&options, "photo.txt").as_deref(), Some("Images"));
+        assert_eq!(folder_of(&options, "scan").as_deref(), Some("Documents"));
+        assert_eq!(
+            folder_of(&options, "report.docx").as_deref(),
+            Some("Documents")
+        );
+        assert_eq!(
+            folder_of(&options, "notes.txt").as_deref(),
+            Some("Documents")
+        );
+        assert_eq!(sniff_category(&root.join("notes.txt"), &options), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extensionless_files_are_classified() {
+        use crate::scanner::scan_recursive;
+
+        let root = temp_root("noext");
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::write(root.join("LICENSE"), b"MIT").unwrap();
+        fs::write(root.join("data"), b"\x00\x01").unwrap();
+        fs::write(root.join("nested").join("README"), b"hi").unwrap();
+        let root_str = root.to_string_lossy();
+
+        let mut config = default_config();
+        let files = scan_by_category(&root_str, &ScanOptions::new(&config));
+        assert_eq!(files.get("Others").map(Vec::len), Some(2));
+
+        config.preferences.no_extension_folder = Some("Misc".into());
+        let options = ScanOptions::new(&config);
+        assert_eq!(
+            scan_by_category(&root_str, &options)
+                .get("Misc")
+                .map(Vec::len),
+            Some(2)
+        );
+        assert_eq!(
+            scan_recursive(&root_str, &options, 0)
+                .get("Misc")
+                .map(Vec::len),
+            Some(1)
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unmatched_policy_routes_unknown_extensions() {
+        use crate::modes::UnmatchedPolicy;
+        use crate::scanner::scan_hybrid;
+
+        let root = temp_root("unmatched");
+        for name in ["a.pdf", "b.xyz", "c.XYZ", "d.qqq"] {
+            fs::write(root.join(name), b"x").unwrap();
+        }
+        let root_str = root.to_string_lossy();
+
+        let mut config = default_config();
+        let mut folders = |policy: &str| -> Vec<(String, usize)> {
+            config.preferences.unmatched_policy = toml::from_str::<crate::config::Preferences>(
+                &format!("unmatched_policy = \"{}\"", policy),
+            )
+            .unwrap()
+            .unmatched_policy;
+            let options = ScanOptions::new(&config);
+            let mut folders: Vec<(String, usize)> = scan_by_category(&root_str, &options)
+                .into_iter()
+                .map(|(folder, files)| (folder, files.len()))
+                .collect();
+            folders.sort();
+            folders.push(("left".into(), options.left_unmatched.take().len()));
+            folders
+        };
+
+        let expect = |pairs: &[(&str, usize)]| -> Vec<(String, usize)> {
+            pairs.iter().map(|(f, n)| (f.to_string(), *n)).collect()
+        };
+        assert_eq!(
+            folders("others"),
+            expect(&[("Documents", 1), ("Others", 3), ("left", 0)])
+        );
+        assert_eq!(folders("leave"), expect(&[("Documents", 1), ("left", 3)]));
+        assert_eq!(
+            folders("by-extension"),
+            expect(&[("Documents", 1), ("qqq", 1), ("xyz", 2), ("left", 0)])
+        );
+        assert_eq!(
+            folders("Unsorted"),
+            expect(&[("Documents", 1), ("Unsorted", 3), ("left", 0)])
+        );
+
+        let options = ScanOptions::new(&config);
+        assert_eq!(
+            *options.unmatched,
+            UnmatchedPolicy::Folder("Unsorted".into())
+        );
+        let hybrid = scan_hybrid(&root_str, &options);
+        assert!(hybrid.keys().any(|k| k.starts_with("Unsorted/")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_by_extension_folders_are_not_rescanned() {
+        use crate::config::Preferences;
+        use crate::scanner::scan_recursive;
+
+        let root = temp_root("by-extension");
+        for dir in ["xyz", "QQQ", "misc"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        // Left by a previous run
+        fs::write(root.join("xyz/b.xyz"), b"x").unwrap();
+        fs::write(root.join("QQQ/c.qqq"), b"x").unwrap();
+        // Named after an extension, but not only holding that extension
+        fs::write(root.join("misc/d.xyz"), b"x").unwrap();
+        fs::write(root.join("misc/e.misc"), b"x").unwrap();
+
+        let mut config = default_config();
+        config.preferences.unmatched_policy =
+            toml::from_str::<Preferences>("unmatched_policy = \"by-extension\"")
+                .unwrap()
+                .unmatched_policy;
+        let options = ScanOptions::new(&config);
+        let mut found: Vec<_> = scan_recursive(&root.to_string_lossy(), &options, 0)
+            .into_values()
+            .flatten()
+            .map(|p| p.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        found.sort();
+
+        assert_eq!(
+            found,
+            [std::path::PathBuf::from("misc/d.xyz"), "misc/e.misc".into()]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_unmatched_policy_rejects_paths() {
+        use crate::config::Preferences;
+
+        for folder in ["../x", "/etc/x", "a/b", "a\\\\b", ".."] {
+            let parsed =
+                toml::from_str::<Preferences>(&format!("unmatched_policy = \"{}\"", folder));
+            assert!(parsed.is_err(), "{}", folder);
+        }
+        assert!(toml::from_str::<Preferences>("unmatched_policy = \"Unsorted\"").is_ok());
+    }
+
+    #[test]
+    fn test_extension_case_does_not_change_category() {
+        use crate::config::find_category;
+
+        let root = temp_root("case");
+        for name in ["PHOTO.JPEG", "photo.jpeg", "Photo.Jpeg"] {
+            fs::write(root.join(name), b"x").unwrap();
+        }
+
+        let mut config = default_config();
+        let files = scan_by_category(&root.to_string_lossy(), &ScanOptions::new(&config));
+        assert_eq!(files.len(), 1);
+        assert_eq!(files.get("Images").map(Vec::len), Some(3));
+
+        // Upper-case entries in the config match too
+        config.categories.insert("Scans".into(), vec!["DJI".into()]);
+        assert_eq!(
+            find_category(&config.categories, "dji"),
+            Some("Scans".into())
+        );
+        assert_eq!(
+            find_category(&config.categories, "Dji"),
+            Some("Scans".into())
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_age_filter_window() {
+        use crate::stats::parse_age;
+        use chrono::TimeDelta;
+        use std::time::{Duration, SystemTime};
+
+        assert_eq!(parse_age("12h"), Ok(TimeDelta::hours(12)));
+        assert_eq!(parse_age("30d"), Ok(TimeDelta::days(30)));
+        assert_eq!(parse_age("30"), Ok(TimeDelta::days(30)));
+        assert_eq!(parse_age("2W"), Ok(TimeDelta::weeks(2)));
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("d").is_err());
+
+        let root = temp_root("age");
+        let day = Duration::from_secs(24 * 3600);
+        for (name, days_old) in [("fresh.txt", 0), ("week.txt", 7), ("old.txt", 60)] {
+            let path = root.join(name);
+            fs::write(&path, b"x").unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() - day * days_old)
+                .unwrap();
+        }
+
+        let mut config = default_config();
+        config.preferences.date_source = crate::modes::DateSource::Modified;
+        let mut options = ScanOptions::new(&config);
+        let mut scan = |older: Option<TimeDelta>, newer: Option<TimeDelta>| {
+            options.older_than = older;
+            options.newer_than = newer;
+            let kept: usize = scan_by_category(&root.to_string_lossy(), &options)
+                .values()
+                .map(Vec::len)
+                .sum();
+            (kept, options.filtered_by_age.take().len())
+        };
+
+        assert_eq!(scan(Some(TimeDelta::days(30)), None), (1, 2));
+        assert_eq!(scan(None, Some(TimeDelta::days(3))), (1, 2));
+        assert_eq!(
+            scan(Some(TimeDelta::days(3)), Some(TimeDelta::days(30))),
+            (1, 2)
+        );
+        assert_eq!(scan(None, None), (3, 0));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rules_first_match_wins_then_categories() {
+        use crate::config::Rule;
+        use crate::scanner::scan_by_rules;
+
+        let root = temp_root("rules");
+        fs::write(root.join("Invoice-2024.pdf"), b"small").unwrap();
+        fs::write(root.join("manual.pdf"), vec![0u8; 4096]).unwrap();
+        fs::write(root.join("song.mp3"), b"x").unwrap();
+
+        let mut config = default_config();
+        config.rules = vec![
+            Rule {
+                folder: "Finance/Invoices".into(),
+                name: Some("invoice-*".into()),
+                ..Default::default()
+            },
+            Rule {
+                folder: "Big PDFs".into(),
+                extensions: vec![".PDF".into()],
+                min_size: Some("1KB".into()),
+                ..Default::default()
+            },
+            // Never reached for the invoice: the first rule already matched
+            Rule {
+                folder: "All PDFs".into(),
+                extensions: vec!["pdf".into()],
+                ..Default::default()
+            },
+        ];
+        let options = ScanOptions::new(&config);
+        let files = scan_by_rules(&root.to_string_lossy(), &options);
+        let folder_of = |name: &str| {
+            files
+                .iter()
+                .find(|(_, paths)| paths.iter().any(|p| p.ends_with(name)))
+                .map(|(folder, _)| folder.as_str())
+        };
+
+        assert_eq!(folder_of("Invoice-2024.pdf"), Some("Finance/Invoices"));
+        assert_eq!(folder_of("manual.pdf"), Some("Big PDFs"));
+        assert_eq!(folder_of("song.mp3"), Some("Audio"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_max_depth_limits_recursive_scan() {
+        use crate::scanner::scan_recursive;
+
+        let root = temp_root("depth");
+        let mut dir = root.clone();
+        for level in ["one", "two", "three"] {
+            dir = dir.join(level);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("{}.txt", level)), b"x").unwrap();
+        }
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        let mut count = |max_depth: Option<usize>| -> usize {
+            options.max_depth = max_depth;
+            scan_recursive(&root.to_string_lossy(), &options, 0)
+                .values()
+                .map(Vec::len)
+                .sum()
+        };
+
+        assert_eq!(count(Some(0)), 0);
+        assert_eq!(count(Some(1)), 1);
+        assert_eq!(count(Some(2)), 2);
+        assert_eq!(count(None), 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_stellarignore_filters_flat_and_recursive_scans() {
+        use crate::scanner::{scan_recursive, IgnoreList};
+
+        let root = temp_root("ignore");
+        fs::create_dir_all(root.join("keep/drafts")).unwrap();
+        fs::create_dir_all(root.join("work")).unwrap();
+        for file in [
+            "a.pdf",
+            "b.tmp",
+            "todo.txt",
+            "keep/c.pdf",
+            "keep/d.tmp",
+            "keep/todo.txt",
+            "keep/drafts/e.pdf",
+            "work/f.pdf",
+        ] {
+            fs::write(root.join(file), b"x").unwrap();
+        }
+        fs::write(
+            root.join(".stellarignore"),
+            "# scratch files\n*.tmp\n/todo.txt\ndrafts/\nwork\n[invalid\n",
+        )
+        .unwrap();
+
+        let config = default_config();
+        let mut options = ScanOptions::new(&config);
+        options.include_hidden = true;
+        let names =
+            |files: std::collections::HashMap<String, Vec<std::path::PathBuf>>| -> Vec<String> {
+                let mut names: Vec<String> = files
+                    .values()
+                    .flatten()
+                    .map(|p| p.strip_prefix(&root).unwrap().display().to_string())
+                    .collect();
+                names.sort();
+                names
+            };
+
+        // The ignore file itself is never organized, even with hidden files
+        let root_str = root.to_string_lossy();
+        assert_eq!(names(scan_by_category(&root_str, &options)), ["a.pdf"]);
+        assert_eq!(
+            names(scan_recursive(&root_str, &options, 0)),
+            ["keep/c.pdf", "keep/todo.txt"]
+        );
+
+        let mut ignored = options.ignored.take();
+        ignored.sort();
+        assert_eq!(
+            ignored,
+            [
+                root.join("b.tmp"),
+                root.join("keep/d.tmp"),
+                root.join("keep/drafts"),
+                root.join("todo.txt"),
+                root.join("work"),
+            ]
+        );
+        assert_eq!(IgnoreList::load(&root).invalid, ["[invalid"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_project_indicators_merge_config_with_defaults() {
+        use crate::scanner::{is_project_folder, project_indicators};
+
+        let mut config = default_config();
+        config.protected.project_indicators = vec!["Makefile".into(), ".git".into()];
+        let indicators = project_indicators(&config.protected);
+
+        assert!(indicators.contains(&"Cargo.toml".to_string()));
+        assert!(indicators.contains(&"Makefile".to_string()));
+        assert_eq!(indicators.iter().filter(|i| *i == ".git").count(), 1);
+
+        let root = temp_root("project");
+        fs::write(root.join("Makefile"), b"all:").unwrap();
+        let root_str = root.to_string_lossy();
+
+        assert!(is_project_folder(&root_str, &indicators));
+        assert!(!is_project_folder(
+            &root_str,
+            &project_indicators(&default_config().protected)
+        ));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod lock_tests {
+    use super::temp_root;
+    use crate::lock::FolderLock;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_acquire_timeout_waits_for_release() {
+        let root = temp_root("lockwait");
+        let folder = root.to_string_lossy().to_string();
+
+        let held = FolderLock::acquire(&folder).unwrap();
+        assert!(FolderLock::acquire(&folder).is_err());
+        assert!(FolderLock::acquire_timeout(&folder, Duration::from_millis(100)).is_err());
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(held);
+        });
+        assert!(FolderLock::acquire_timeout(&folder, Duration::from_secs(5)).is_ok());
+        releaser.join().unwrap();
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod watcher_tests {
+    use super::temp_root;
+    use crate::config::WatchConfig;
+    use crate::watcher::{is_download_in_progress, new_file_folder, PendingFiles};
+    use fs2::FileExt;
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_new_files_go_where_a_scan_puts_them() {
+        use crate::config::Config;
+        use crate::organizer::OrganizeOptions;
+
+        let mut config: Config = toml::from_str(include_str!("../stellar.toml")).unwrap();
+        let options = OrganizeOptions::new(&config, None);
+        assert_eq!(
+            new_file_folder(Path::new("a.PDF"), &options).as_deref(),
+            Some("Documents")
+        );
+        assert_eq!(
+            new_file_folder(Path::new("LICENSE"), &options).as_deref(),
+            Some("Others")
+        );
+
+        config.preferences.no_extension_folder = Some("Misc".into());
+        let options = OrganizeOptions::new(&config, None);
+        assert_eq!(
+            new_file_folder(Path::new("LICENSE"), &options).as_deref(),
+            Some("Misc")
+        );
+    }
+
+    #[test]
+    fn test_in_progress_downloads_are_ignored() {
+        let ignore = WatchConfig::default().ignore_extensions;
+
+        assert!(is_download_in_progress(
+            Path::new("/tmp/movie.mkv.part"),
+            &ignore
+        ));
+        assert!(is_download_in_progress(
+            Path::new("/tmp/report.pdf.CRDOWNLOAD"),
+            &ignore
+        ));
+        assert!(!is_download_in_progress(
+            Path::new("/tmp/report.pdf"),
+            &ignore
+        ));
+        assert!(!is_download_in_progress(Path::new("/tmp/README"), &ignore));
+    }
+
+    #[test]
+    fn test_pending_files_wait_for_a_stable_unlocked_file() {
+        let root = temp_root("watch");
+        let photo = root.join("photo.jpg");
+        let video = root.join("video.mkv");
+        let gone = root.join("gone.pdf");
+        for path in [&photo, &video, &gone] {
+            fs::write(path, b"data").unwrap();
+        }
+        let interval = Duration::from_millis(20);
+        let mut pending = PendingFiles::new(interval);
+
+        for path in [&photo, &video, &gone] {
+            pending.add(path.clone());
+        }
+        // Nothing is checked before a full interval
+        assert!(pending.check().ready.is_empty());
+
+        // Another handle holding an exclusive lock (a browser still writing)
+        let writer = File::open(&video).unwrap();
+        FileExt::lock_exclusive(&writer).unwrap();
+        fs::remove_file(&gone).unwrap();
+        sleep(interval);
+
+        let checked = pending.check();
+        assert_eq!(checked.ready, vec![photo.clone()]);
+        assert_eq!(checked.busy, vec![video.clone()]);
+        assert!(!pending.contains(&photo));
+        assert!(!pending.contains(&gone));
+
+        // Still growing once unlocked: one more interval, reported only once
+        FileExt::unlock(&writer).unwrap();
+        fs::write(&video, b"more data").unwrap();
+        sleep(interval);
+        let checked = pending.check();
+        assert!(checked.ready.is_empty() && checked.busy.is_empty());
+        sleep(interval);
+        assert_eq!(pending.check().ready, vec![video.clone()]);
+        assert!(!pending.contains(&video));
+
+        fs::remove_dir_all(&root).unwrap();
     }
 }
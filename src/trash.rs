@@ -0,0 +1,195 @@
+// Stellar - Trash Module
+// @musem23
+//
+// Recoverable deletion for duplicate removal.
+// Each removal batch is moved to ~/.local/share/stellar/trash/<timestamp>/
+// with a manifest.json recording original paths, so it can be restored later.
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use crate::history;
+use crate::organizer;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub original: String,
+    pub stored: String,
+    pub size: u64,
+}
+
+/// One removal batch, stored in its own timestamped folder
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashBatch {
+    pub id: String,
+    pub timestamp: String,
+    pub entries: Vec<TrashEntry>,
+}
+
+pub struct RestoreResult {
+    pub batch_id: String,
+    pub restored: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+/// A batch being filled; the folder is only created when the first file arrives
+pub struct TrashSession {
+    dir: PathBuf,
+    batch: TrashBatch,
+}
+
+impl Default for TrashSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrashSession {
+    pub fn new() -> Self {
+        let now = Local::now();
+        let id = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+
+        Self {
+            dir: get_trash_dir().join(&id),
+            batch: TrashBatch {
+                id,
+                timestamp: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+                entries: Vec::new(),
+            },
+        }
+    }
+
+    /// Move a file into the batch and update the manifest
+    pub fn add(&mut self, path: &Path) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create trash folder: {}", e))?;
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stored = format!("{}-{}", self.batch.entries.len() + 1, name);
+        let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+        let original = history::absolute_path(&path.to_string_lossy());
+
+        organizer::move_file_with_fallback(path, &self.dir.join(&stored))
+            .map_err(|e| format!("Failed to trash {}: {}", path.display(), e))?;
+
+        self.batch.entries.push(TrashEntry {
+            original,
+            stored,
+            size,
+        });
+
+        save_manifest(&self.dir, &self.batch)
+    }
+}
+
+/// All trash batches, oldest first
+pub fn list_batches() -> Vec<TrashBatch> {
+    let entries = match fs::read_dir(get_trash_dir()) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut batches: Vec<TrashBatch> = entries
+        .flatten()
+        .filter_map(|e| fs::read_to_string(e.path().join(MANIFEST_FILE)).ok())
+        .filter_map(|c| serde_json::from_str(&c).ok())
+        .collect();
+
+    batches.sort_by(|a, b| a.id.cmp(&b.id));
+    batches
+}
+
+/// Restore a batch (the most recent one if no id is given) to its original paths.
+/// Files whose original path is taken again stay in the trash.
+pub fn restore_batch(id: Option<&str>) -> Result<RestoreResult, String> {
+    let batches = list_batches();
+    let batch = match id {
+        Some(id) => batches.into_iter().find(|b| b.id == id),
+        None => batches.into_iter().last(),
+    }
+    .ok_or_else(|| match id {
+        Some(id) => format!("No trash batch named {}", id),
+        None => "Trash is empty.".to_string(),
+    })?;
+
+    let dir = get_trash_dir().join(&batch.id);
+    let mut remaining = Vec::new();
+    let mut result = RestoreResult {
+        batch_id: batch.id.clone(),
+        restored: 0,
+        failed: 0,
+        errors: Vec::new(),
+    };
+
+    for entry in &batch.entries {
+        match restore_entry(&dir, entry) {
+            Ok(_) => result.restored += 1,
+            Err(e) => {
+                result.errors.push(e);
+                result.failed += 1;
+                remaining.push(entry.clone());
+            }
+        }
+    }
+
+    if remaining.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+    } else {
+        save_manifest(
+            &dir,
+            &TrashBatch {
+                entries: remaining,
+                ..batch
+            },
+        )?;
+    }
+
+    Ok(result)
+}
+
+// ============================================================================
+// Private helpers
+// ============================================================================
+
+fn get_trash_dir() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home)
+        .join(".local")
+        .join("share")
+        .join("stellar")
+        .join("trash")
+}
+
+fn save_manifest(dir: &Path, batch: &TrashBatch) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(batch).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| format!("Failed to write: {}", e))
+}
+
+fn restore_entry(dir: &Path, entry: &TrashEntry) -> Result<(), String> {
+    let from = dir.join(&entry.stored);
+    let to = PathBuf::from(&entry.original);
+
+    if !from.exists() {
+        return Err(format!("Missing from trash: {}", entry.original));
+    }
+    if to.exists() {
+        return Err(format!("Already exists: {}", entry.original));
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Cannot create directory: {}", e))?;
+    }
+
+    organizer::move_file_with_fallback(&from, &to)
+        .map_err(|e| format!("Failed to restore {}: {}", entry.original, e))
+}
@@ -5,23 +5,128 @@
 // Provides menus, prompts, progress bars, and styled output messages.
 // All user-facing text and formatting is centralized here.
 
-use console::{style, Term};
-use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
+use console::{style, StyledObject, Term};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::time::Duration;
 
-use crate::duplicates::DuplicateGroup;
-use crate::history::Operation;
-use crate::stats::{format_duration, format_size, DryRunPreview, OrganizationStats, SkippedFile};
+use crate::archive::ArchivedFolder;
+use crate::config;
+use crate::duplicates::{DuplicateGroup, RemovalPlan, RemovalResult, SimilarGroup};
+use crate::history::{FileMove, Journal, Operation, UndoPreview, UndoResult};
+use crate::organizer::{self, MoveResult, OrganizeError, OrganizeOptions};
+use crate::progress::ByteProgress;
+use crate::stats::{
+    format_duration, format_size, DryRunPreview, FolderReport, OrganizationStats, PlannedRename,
+    RenameCollision, SkippedFile,
+};
+use crate::trash::{RestoreResult, TrashBatch};
+
+// ============================================================================
+// Verbosity
+// ============================================================================
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+static NO_BANNER: AtomicBool = AtomicBool::new(false);
+static PROGRESS_UNIT: AtomicU8 = AtomicU8::new(ProgressUnit::Auto as u8);
+
+/// Below this total, `ProgressUnit::Auto` counts files rather than bytes
+const BYTE_PROGRESS_MIN: u64 = 100 * 1024 * 1024;
+
+/// Quiet mode hides status messages, progress bars and statistics.
+/// Errors and explicitly requested reports (dry-run, stats, JSON) still print.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Answer yes to every confirmation without reading stdin (for scripts)
+pub fn set_assume_yes(yes: bool) {
+    ASSUME_YES.store(yes, Ordering::Relaxed);
+}
+
+fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+/// What the organize progress bar counts
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUnit {
+    /// Bytes when the files add up to 100MB or more, files otherwise
+    Auto,
+    Files,
+    Bytes,
+}
+
+pub fn set_progress_unit(unit: ProgressUnit) {
+    PROGRESS_UNIT.store(unit as u8, Ordering::Relaxed);
+}
+
+fn progress_unit() -> ProgressUnit {
+    match PROGRESS_UNIT.load(Ordering::Relaxed) {
+        x if x == ProgressUnit::Files as u8 => ProgressUnit::Files,
+        x if x == ProgressUnit::Bytes as u8 => ProgressUnit::Bytes,
+        _ => ProgressUnit::Auto,
+    }
+}
+
+/// When styled output carries ANSI colors
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colors on a terminal, unless NO_COLOR is set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Turn the colors of every `style` call on or off, for stdout and stderr
+pub fn set_color_mode(mode: ColorMode) {
+    let (stdout, stderr) = match mode {
+        ColorMode::Always => (true, true),
+        ColorMode::Never => (false, false),
+        ColorMode::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            (
+                !no_color && Term::stdout().is_term(),
+                !no_color && Term::stderr().is_term(),
+            )
+        }
+    };
+    console::set_colors_enabled(stdout);
+    console::set_colors_enabled_stderr(stderr);
+}
 
 // ============================================================================
 // Banner & Main Menu
 // ============================================================================
 
+/// Skip the interactive banner entirely (--no-banner)
+pub fn set_no_banner(no_banner: bool) {
+    NO_BANNER.store(no_banner, Ordering::Relaxed);
+}
+
+/// Clears the screen and draws the colored logo on a terminal.
+/// Piped or dumb output gets one plain line, and --no-banner nothing at all.
 pub fn print_banner() {
-    let _ = Term::stdout().clear_screen();
+    if NO_BANNER.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let term = Term::stdout();
+    if !term.is_term() || is_dumb_terminal() {
+        println!("Stellar - Organize your files in a snap");
+        return;
+    }
+
+    let _ = term.clear_screen();
 
     let seed = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -44,6 +149,10 @@ pub fn print_banner() {
     println!("  {}\n", style("Organize your files in a snap").dim());
 }
 
+fn is_dumb_terminal() -> bool {
+    std::env::var("TERM").is_ok_and(|t| t == "dumb")
+}
+
 fn apply_color(text: &str, color_idx: usize) -> console::StyledObject<&str> {
     match color_idx {
         0 => style(text).yellow().bold(),
@@ -133,9 +242,21 @@ pub fn select_rename_mode(default: usize) -> Option<usize> {
         ("[~]", "Clean (lowercase, dashes, no duplicates)", "green"),
         ("[@]", "Date prefix (2024-01-15-filename.pdf)", "cyan"),
         ("[-]", "Skip renaming", "yellow"),
+        ("[_]", "Snake case (my_file_name.pdf)", "green"),
+        ("[T]", "Title case (My File Name.pdf)", "green"),
         ("[<]", "Back", "dim"),
     ];
-    select_with_back("Select rename mode", &options, default, 3)
+    select_with_back("Select rename mode", &options, default, 5)
+}
+
+pub fn select_date_granularity(default: usize) -> Option<usize> {
+    let options = [
+        ("[Y]", "Year (2024)", "green"),
+        ("[M]", "Month (2024/01-january)", "cyan"),
+        ("[D]", "Day (2024/01-january/15)", "magenta"),
+        ("[<]", "Back", "dim"),
+    ];
+    select_with_back("Select date granularity", &options, default, 3)
 }
 
 pub fn select_default_organization_mode(current: usize) -> Option<usize> {
@@ -151,10 +272,18 @@ pub fn select_default_rename_mode(current: usize) -> Option<usize> {
 // ============================================================================
 
 pub fn print_preview(files_map: &HashMap<String, Vec<PathBuf>>) {
+    if is_quiet() {
+        return;
+    }
+
     println!("\n{}\n", style("Organization preview:").bold());
 
     let mut total = 0;
-    for (category, files) in files_map {
+    let mut sorted: Vec<_> = files_map.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+    for (category, files) in sorted {
+        // Flattening uses an empty folder name for the destination root
+        let category = if category.is_empty() { "." } else { category };
         println!(
             "  {} {} {}",
             style("[/]").cyan(),
@@ -170,13 +299,21 @@ pub fn print_preview(files_map: &HashMap<String, Vec<PathBuf>>) {
     );
 }
 
-pub fn print_dry_run_preview(preview: &DryRunPreview) {
+/// Moves listed by a dry-run unless --preview-limit says otherwise
+pub const DEFAULT_PREVIEW_LIMIT: usize = 20;
+
+/// Moves visible at once in the --interactive-review list (it scrolls)
+const REVIEW_PAGE_SIZE: usize = 15;
+
+/// Print the planned moves, at most `limit` of them (0 = all)
+pub fn print_dry_run_preview(preview: &DryRunPreview, limit: usize) {
+    let limit = if limit == 0 { usize::MAX } else { limit };
     println!(
         "\n{}\n",
         style("Dry-run preview (no changes made):").bold().yellow()
     );
 
-    for (i, mv) in preview.moves.iter().take(20).enumerate() {
+    for (i, mv) in preview.moves.iter().take(limit).enumerate() {
         let from_name = mv.from.file_name().unwrap().to_string_lossy();
         let to_folder = mv
             .to
@@ -191,23 +328,29 @@ pub fn print_dry_run_preview(preview: &DryRunPreview) {
         } else {
             style("")
         };
+        let collision_tag = if mv.collides {
+            style(" (collision)").red().bold()
+        } else {
+            style("")
+        };
 
         println!(
-            "  {} {} {} {}/{} {}",
+            "  {} {} {} {}/{} {}{}",
             style(format!("{:>3}.", i + 1)).dim(),
             style(&from_name).red(),
             style("->").dim(),
             style(&to_folder).cyan(),
             style(&to_name).green(),
-            rename_tag
+            rename_tag,
+            collision_tag
         );
     }
 
-    if preview.moves.len() > 20 {
+    if preview.moves.len() > limit {
         println!(
             "\n  {} {}",
             style("...").dim(),
-            style(format!("and {} more files", preview.moves.len() - 20)).dim()
+            style(format!("and {} more files", preview.moves.len() - limit)).dim()
         );
     }
 
@@ -217,9 +360,151 @@ pub fn print_dry_run_preview(preview: &DryRunPreview) {
         style(preview.total_files).green(),
         style(format_size(preview.total_bytes)).cyan()
     );
+    let collisions = preview.collisions();
+    if collisions > 0 {
+        println!(
+            "  {} {} files share a destination with another file \
+             (organizing asks whether to suffix, skip or rename them)\n",
+            style("[!]").yellow(),
+            style(collisions).yellow()
+        );
+    }
+}
+
+/// Every planned move ticked; returns the files the user unticked (kept in
+/// place), None on cancel. --yes keeps the whole batch.
+pub fn review_moves(preview: &DryRunPreview) -> Option<Vec<PathBuf>> {
+    if assume_yes() {
+        return Some(Vec::new());
+    }
+
+    let items: Vec<String> = preview
+        .moves
+        .iter()
+        .map(|mv| {
+            let folder = mv
+                .to
+                .parent()
+                .and_then(Path::file_name)
+                .unwrap_or_default()
+                .to_string_lossy();
+            let name = mv.to.file_name().unwrap_or_default().to_string_lossy();
+            let collision = if mv.collides { " (collision)" } else { "" };
+            format!(
+                "{} {} {}/{}{}",
+                mv.from.file_name().unwrap_or_default().to_string_lossy(),
+                style("->").dim(),
+                style(folder).cyan(),
+                style(name).green(),
+                style(collision).red().bold()
+            )
+        })
+        .collect();
+
+    let selected = MultiSelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Files to organize (space to untick, enter to confirm)")
+        .items(&items)
+        .defaults(&vec![true; items.len()])
+        .max_length(REVIEW_PAGE_SIZE)
+        .interact_opt()
+        .ok()??;
+
+    Some(
+        preview
+            .moves
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !selected.contains(i))
+            .map(|(_, mv)| mv.from.clone())
+            .collect(),
+    )
+}
+
+/// Before/after names of `stellar rename-preview`; files whose name does not
+/// change are only counted
+pub fn print_rename_preview(renames: &[PlannedRename]) {
+    println!(
+        "\n{}\n",
+        style("Rename preview (no changes made):").bold().yellow()
+    );
+
+    let mut category = None;
+    for rename in renames {
+        let from = rename
+            .from
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        if from == rename.to.as_str() && !rename.collides {
+            continue;
+        }
+        if category != Some(&rename.category) {
+            category = Some(&rename.category);
+            let name = if rename.category.is_empty() {
+                "."
+            } else {
+                &rename.category
+            };
+            println!("  {} {}", style("[/]").cyan(), style(name).bold());
+        }
+
+        let collision = if rename.collides {
+            style(" (collision)").red().bold()
+        } else {
+            style("")
+        };
+        println!(
+            "      {} {} {}{}",
+            style(&from).dim(),
+            style("->").dim(),
+            style(&rename.to).green(),
+            collision
+        );
+    }
+
+    let changed = renames
+        .iter()
+        .filter(|r| {
+            r.from
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy() != r.to)
+        })
+        .count();
+    let collisions = renames.iter().filter(|r| r.collides).count();
+    println!(
+        "\n  {} {} renamed, {} unchanged",
+        style("Total:").bold(),
+        style(changed).green(),
+        renames.len() - changed
+    );
+    if collisions > 0 {
+        println!(
+            "  {} {} files share a new name with another file of their category \
+             (organizing would add a suffix)",
+            style("[!]").yellow(),
+            style(collisions).red()
+        );
+    }
+    println!();
 }
 
+/// Print a value as pretty JSON on stdout (for scripting)
+pub fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => print_error(&format!("Failed to serialize output: {}", e)),
+    }
+}
+
+/// Categories that took at least this long are listed as slow (at most SLOW_CATEGORIES_SHOWN)
+const SLOW_CATEGORY_MS: u64 = 1000;
+const SLOW_CATEGORIES_SHOWN: usize = 3;
+
 pub fn print_statistics(stats: &OrganizationStats) {
+    if is_quiet() {
+        return;
+    }
+
     let sep = style("=".repeat(50)).dim();
     println!("\n{}", sep);
     println!("{}\n", style("  Organization Statistics").bold().cyan());
@@ -281,6 +566,127 @@ pub fn print_statistics(stats: &OrganizationStats) {
         }
     }
 
+    if !stats.unknown_extensions.is_empty() {
+        println!(
+            "\n  {} {}",
+            style("[?]").yellow(),
+            unknown_extensions_summary(&stats.unknown_extensions)
+        );
+    }
+
+    let mut slow: Vec<_> = stats
+        .category_durations
+        .iter()
+        .filter(|(_, ms)| **ms >= SLOW_CATEGORY_MS)
+        .collect();
+    if !slow.is_empty() {
+        slow.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        println!("\n  {}", style("Slowest categories:").bold());
+        for (cat, ms) in slow.into_iter().take(SLOW_CATEGORIES_SHOWN) {
+            println!(
+                "    {} {} ({})",
+                style("[T]").dim(),
+                if cat.is_empty() { "." } else { cat },
+                style(format_duration(*ms)).yellow()
+            );
+        }
+    }
+
+    println!("{}\n", sep);
+}
+
+/// Per-folder counts and totals after organizing several folders in one run
+pub fn print_combined_summary(organized: &[(String, OrganizationStats)], failed: &[String]) {
+    if is_quiet() {
+        return;
+    }
+
+    let sep = style("=".repeat(50)).dim();
+    println!("\n{}", sep);
+    println!("{}\n", style("  Combined Summary").bold().cyan());
+
+    for (folder, stats) in organized {
+        println!(
+            "  {} {}: {} moved, {} skipped ({})",
+            style("[>]").green(),
+            folder,
+            style(stats.files_moved).green(),
+            stats.files_skipped,
+            format_size(stats.total_bytes)
+        );
+    }
+    for folder in failed {
+        println!(
+            "  {} {}: {}",
+            style("[-]").yellow(),
+            folder,
+            style("not organized").yellow()
+        );
+    }
+
+    let moved: usize = organized.iter().map(|(_, s)| s.files_moved).sum();
+    let skipped: usize = organized.iter().map(|(_, s)| s.files_skipped).sum();
+    let bytes: u64 = organized.iter().map(|(_, s)| s.total_bytes).sum();
+    println!(
+        "\n  {} {} files moved, {} skipped, {} across {} folder(s)",
+        style("[#]").blue(),
+        style(moved).green().bold(),
+        skipped,
+        style(format_size(bytes)).blue(),
+        organized.len()
+    );
+    println!("{}\n", sep);
+}
+
+pub fn print_folder_report(folder: &str, report: &FolderReport) {
+    let sep = style("=".repeat(50)).dim();
+    println!("\n{}", sep);
+    println!("{}", style("  Folder Report").bold().cyan());
+    println!("  {}\n", style(folder).dim());
+
+    let mut sorted: Vec<_> = report.categories.iter().collect();
+    sorted.sort_by(|a, b| b.1.files.cmp(&a.1.files).then_with(|| a.0.cmp(b.0)));
+
+    println!(
+        "  {}",
+        style(format!("{:<20} {:>8} {:>12}", "CATEGORY", "FILES", "SIZE")).bold()
+    );
+    for (cat, tally) in sorted {
+        println!(
+            "  {:<20} {:>8} {:>12}",
+            cat,
+            tally.files,
+            format_size(tally.bytes)
+        );
+    }
+    if report.no_extension > 0 {
+        println!(
+            "  {}",
+            style(format!(
+                "{:<20} {:>8}",
+                "(no extension)", report.no_extension
+            ))
+            .dim()
+        );
+    }
+
+    println!(
+        "\n  {} {} files ({})",
+        style("Total:").bold(),
+        style(report.total_files).green(),
+        style(format_size(report.total_bytes)).cyan()
+    );
+
+    if let (Some(oldest), Some(newest)) = (report.oldest, report.newest) {
+        println!(
+            "  {} {} {} {}",
+            style("[T]").dim(),
+            style(oldest.format("%Y-%m-%d")).cyan(),
+            style("->").dim(),
+            style(newest.format("%Y-%m-%d")).cyan()
+        );
+    }
+
     println!("{}\n", sep);
 }
 
@@ -322,10 +728,7 @@ pub fn print_duplicates(groups: &[DuplicateGroup]) {
     }
 
     let total_dupes: usize = groups.iter().map(|g| g.files.len() - 1).sum();
-    let wasted: u64 = groups
-        .iter()
-        .map(|g| g.size * (g.files.len() as u64 - 1))
-        .sum();
+    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted_bytes).sum();
 
     println!("\n{}\n", style("Duplicate files found:").bold().yellow());
 
@@ -355,13 +758,91 @@ pub fn print_duplicates(groups: &[DuplicateGroup]) {
     );
 }
 
+pub fn print_similar_images(groups: &[SimilarGroup]) {
+    if groups.is_empty() {
+        print_info("No similar images found.");
+        return;
+    }
+
+    println!("\n{}\n", style("Similar images found:").bold().yellow());
+
+    for (i, group) in groups.iter().enumerate() {
+        println!(
+            "  {} {}",
+            style(format!("Group {}:", i + 1)).bold(),
+            style(format!("{} images", group.files.len())).cyan()
+        );
+        for (j, file) in group.files.iter().enumerate() {
+            println!(
+                "    {} {}",
+                style(format!("{}.", j + 1)).dim(),
+                file.display()
+            );
+        }
+        for (a, b, distance) in &group.distances {
+            println!(
+                "      {} {} <-> {}: {}/64 bits differ",
+                style("~").dim(),
+                a + 1,
+                b + 1,
+                style(distance).yellow()
+            );
+        }
+        println!();
+    }
+
+    print_info("Lower distances mean closer matches (0 = visually identical).");
+}
+
+pub fn print_removal_plan(plans: &[RemovalPlan]) {
+    println!("\n{}\n", style("Duplicate removal preview:").bold().cyan());
+
+    for plan in plans {
+        println!("  {} {}", style("[K]").green(), plan.kept.display());
+        for file in &plan.removed {
+            println!("  {} {}", style("[D]").red(), file.display());
+        }
+        println!();
+    }
+
+    let removed: usize = plans.iter().map(|p| p.removed.len()).sum();
+    let freed: u64 = plans.iter().map(|p| p.bytes_freed).sum();
+    println!(
+        "  {} {} files would be removed, freeing {}\n",
+        style("Summary:").bold(),
+        style(removed).red(),
+        style(format_size(freed)).green()
+    );
+}
+
+pub fn print_removal_result(result: &RemovalResult, use_trash: bool) {
+    for error in &result.errors {
+        print_error(error);
+    }
+
+    print_success(&format!(
+        "Removed {} duplicate files, freed {}",
+        result.removed,
+        format_size(result.bytes_freed)
+    ));
+    if use_trash && result.removed > 0 {
+        print_info("Restore them with `stellar trash restore`.");
+    }
+}
+
 pub fn select_duplicates_action() -> Option<usize> {
     let options = [
-        ("[x]", "Remove all duplicates (keep first)", "red"),
-        ("[?]", "Review each group", "yellow"),
+        (
+            "[T]",
+            "Move duplicates to trash (keep [K], restorable)",
+            "yellow",
+        ),
+        ("[x]", "Delete duplicates permanently (keep [K])", "red"),
+        ("[?]", "Review each group (move to trash)", "yellow"),
+        ("[!]", "Review each group (delete permanently)", "red"),
         ("[<]", "Cancel", "dim"),
     ];
-    select_with_back("What do you want to do with duplicates?", &options, 2, 2)
+    select_with_back("What do you want to do with duplicates?", &options, 4, 4)
 }
 
 pub fn select_file_to_keep(files: &[PathBuf]) -> Option<usize> {
@@ -378,34 +859,279 @@ pub fn select_file_to_keep(files: &[PathBuf]) -> Option<usize> {
         .ok()
 }
 
+// ============================================================================
+// Trash
+// ============================================================================
+
+pub fn print_trash(batches: &[TrashBatch]) {
+    if batches.is_empty() {
+        print_info("Trash is empty.");
+        return;
+    }
+
+    println!("\n{}\n", style("Trash:").bold());
+    for batch in batches.iter().rev() {
+        let size: u64 = batch.entries.iter().map(|e| e.size).sum();
+        println!(
+            "  {} {} ({} files, {})",
+            style(&batch.id).cyan(),
+            style(&batch.timestamp).dim(),
+            style(batch.entries.len()).green(),
+            format_size(size)
+        );
+        for entry in &batch.entries {
+            println!("      {}", style(&entry.original).dim());
+        }
+    }
+    println!();
+}
+
+pub fn print_restore_result(result: &RestoreResult) {
+    if result.failed == 0 {
+        print_success(&format!(
+            "Restored {} files from trash batch {}",
+            result.restored, result.batch_id
+        ));
+    } else {
+        print_warning(&format!(
+            "Restored {} files, {} left in trash batch {}",
+            result.restored, result.failed, result.batch_id
+        ));
+        for error in &result.errors {
+            print_error(error);
+        }
+    }
+}
+
 // ============================================================================
 // History
 // ============================================================================
 
+/// Renamed files listed under each history entry
+const HISTORY_RENAMES_SHOWN: usize = 5;
+
 pub fn print_history(operations: &[Operation]) {
+    let operations: Vec<&Operation> = operations.iter().filter(|op| !op.is_unrecorded()).collect();
     if operations.is_empty() {
         print_info("No operations in history.");
         return;
     }
 
+    let name = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string())
+    };
+
     println!("\n{}\n", style("Recent operations:").bold());
     for (i, op) in operations.iter().rev().enumerate() {
+        let renamed: Vec<_> = op.moves.iter().filter(|mv| mv.is_rename).collect();
+        let renamed_note = if renamed.is_empty() {
+            String::new()
+        } else {
+            format!(", {} renamed", style(renamed.len()).yellow())
+        };
+
         println!(
-            "  {} {} - {} ({} files)",
+            "  {} {} - {} ({} files{})",
             style(format!("{}.", i + 1)).dim(),
             style(&op.timestamp).cyan(),
             style(&op.folder).bold(),
-            style(op.moves.len()).green()
+            style(op.moves.len()).green(),
+            renamed_note
         );
+
+        for record in &op.archives {
+            println!(
+                "       {} {}{}",
+                style("[A]").cyan(),
+                name(&record.archive),
+                if record.removed {
+                    " (folder removed)"
+                } else {
+                    ""
+                }
+            );
+        }
+
+        for mv in renamed.iter().take(HISTORY_RENAMES_SHOWN) {
+            println!(
+                "       {} {} -> {}",
+                style("[R]").yellow(),
+                name(&mv.from),
+                style(name(&mv.to)).yellow()
+            );
+        }
+        if renamed.len() > HISTORY_RENAMES_SHOWN {
+            println!(
+                "       {}",
+                style(format!(
+                    "... and {} more",
+                    renamed.len() - HISTORY_RENAMES_SHOWN
+                ))
+                .dim()
+            );
+        }
+    }
+    println!();
+}
+
+pub fn print_archived(archived: &[ArchivedFolder]) {
+    for record in archived {
+        let note = if record.removed {
+            " (folder removed)"
+        } else {
+            ""
+        };
+        print_success(&format!(
+            "Archived {} -> {}{}",
+            record.folder, record.archive, note
+        ));
     }
+}
+
+/// Files listed per group of an undo preview
+const UNDO_PREVIEW_SHOWN: usize = 20;
+
+pub fn print_undo_preview(preview: &UndoPreview) {
     println!();
+    print_undo_moves(style("[<]").green(), &preview.restorable, |mv| &mv.from, "");
+    print_undo_moves(
+        style("[?]").red(),
+        &preview.missing,
+        |mv| &mv.to,
+        " (missing)",
+    );
+    print_undo_moves(
+        style("[!]").yellow(),
+        &preview.blocked,
+        |mv| &mv.from,
+        " (already exists)",
+    );
+
+    println!(
+        "\n  {} {} to restore, {} missing, {} blocked\n",
+        style("Summary:").bold(),
+        style(preview.restorable.len()).green(),
+        style(preview.missing.len()).red(),
+        style(preview.blocked.len()).yellow()
+    );
+}
+
+fn print_undo_moves(
+    marker: StyledObject<&str>,
+    moves: &[FileMove],
+    path: fn(&FileMove) -> &str,
+    note: &str,
+) {
+    for mv in moves.iter().take(UNDO_PREVIEW_SHOWN) {
+        println!("  {} {}{}", marker, path(mv), style(note).dim());
+    }
+    if moves.len() > UNDO_PREVIEW_SHOWN {
+        println!(
+            "      {}",
+            style(format!("... and {} more", moves.len() - UNDO_PREVIEW_SHOWN)).dim()
+        );
+    }
+}
+
+/// What to do with a run that was interrupted: 0 = record it in history,
+/// 1 = undo it now, None = decide later. --yes records it (nothing moves).
+pub fn select_pending_run_action() -> Option<usize> {
+    if assume_yes() {
+        return Some(0);
+    }
+
+    let options = [
+        format!("{} Record it in history", style("[+]").green()),
+        format!("{} Undo it now", style("[<]").yellow()),
+        format!("{} Decide later", style("[-]").dim()),
+    ];
+    Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("What should be done with it?")
+        .items(&options)
+        .default(0)
+        .interact_opt()
+        .ok()
+        .flatten()
+        .filter(|&choice| choice < 2)
+}
+
+pub fn print_undo_result(result: &UndoResult) {
+    if result.operations.len() > 1 {
+        for op in &result.operations {
+            println!(
+                "  {} {} - {} ({} restored, {} failed)",
+                style("[<]").dim(),
+                style(&op.timestamp).cyan(),
+                style(&op.folder).bold(),
+                style(op.restored).green(),
+                style(op.failed).red()
+            );
+        }
+    }
+
+    if result.failed == 0 {
+        if result.operations.len() > 1 {
+            print_success(&format!(
+                "Successfully restored {} files across {} operations",
+                result.restored,
+                result.operations.len()
+            ));
+        } else {
+            print_success(&format!(
+                "Successfully restored {} files from {}",
+                result.restored, result.operation_time
+            ));
+        }
+    } else {
+        print_warning(&format!(
+            "Restored {} files, {} failed",
+            result.restored, result.failed
+        ));
+        for error in &result.errors {
+            print_error(error);
+        }
+    }
+
+    if let Some(reason) = &result.stopped_by {
+        print_info(reason);
+    }
+}
+
+/// Start the crash journal of a run; without one the run still happens
+pub fn start_journal(source_dir: &str) -> Option<Journal> {
+    Journal::begin(source_dir)
+        .map_err(|e| print_warning(&format!("{}; an interrupted run could not be undone", e)))
+        .ok()
+}
+
+/// Drop the journal once the run is in history. When recording failed the
+/// journal is kept, so the next start offers to record or undo the run.
+pub fn finish_journal(journal: Option<Journal>, recorded: Result<(), String>) {
+    match recorded {
+        Ok(()) => {
+            if let Some(journal) = journal {
+                journal.finish();
+            }
+        }
+        Err(e) => print_error(&format!(
+            "Could not save this run to history: {}. Stellar will offer to record or undo it on its next start.",
+            e
+        )),
+    }
 }
 
 // ============================================================================
 // Settings Menu
 // ============================================================================
 
-pub fn select_settings_menu(org_mode: usize, rename_mode: usize) -> Option<usize> {
+pub fn select_settings_menu(
+    org_mode: usize,
+    rename_mode: usize,
+    granularity: Option<usize>,
+) -> Option<usize> {
     let org_label = match org_mode {
         0 => style("Category").green(),
         1 => style("Date").cyan(),
@@ -416,8 +1142,16 @@ pub fn select_settings_menu(org_mode: usize, rename_mode: usize) -> Option<usize
         0 => style("Clean").green(),
         1 => style("Date prefix").cyan(),
         2 => style("Skip").yellow(),
+        3 => style("Snake case").green(),
+        4 => style("Title case").green(),
         _ => style("Clean").green(),
     };
+    let granularity_label = match granularity {
+        Some(0) => style("Year").green(),
+        Some(1) => style("Month").cyan(),
+        Some(2) => style("Day").magenta(),
+        _ => style("Default").dim(),
+    };
 
     let options = vec![
         format!("{} View categories", style("[#]").cyan()),
@@ -430,6 +1164,11 @@ pub fn select_settings_menu(org_mode: usize, rename_mode: usize) -> Option<usize
             org_label
         ),
         format!("{} Rename mode: {}", style("[R]").magenta(), rename_label),
+        format!(
+            "{} Date granularity: {}",
+            style("[D]").magenta(),
+            granularity_label
+        ),
         format!("{} Save changes", style("[S]").green().bold()),
         format!("{} Back", style("[<]").dim()),
     ];
@@ -481,6 +1220,21 @@ pub fn select_category(categories: &HashMap<String, Vec<String>>) -> Option<Stri
     }
 }
 
+/// "5 files had unknown extensions (.xyz, .abc)", most frequent first
+pub fn unknown_extensions_summary(unknown: &HashMap<String, usize>) -> String {
+    let mut sorted: Vec<_> = unknown.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let files: usize = unknown.values().sum();
+    let extensions: Vec<String> = sorted.iter().map(|(ext, _)| format!(".{}", ext)).collect();
+    format!(
+        "{} file{} had unknown extensions ({})",
+        files,
+        if files == 1 { "" } else { "s" },
+        extensions.join(", ")
+    )
+}
+
 pub fn input_category_name() -> Option<String> {
     Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Category name (empty to cancel)")
@@ -497,6 +1251,18 @@ pub fn input_text(prompt: &str) -> Option<String> {
         .ok()
 }
 
+/// Text that may be left empty, in which case None is returned
+pub fn input_optional(prompt: &str) -> Option<String> {
+    let input: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()
+        .ok()?;
+
+    let input = input.trim();
+    (!input.is_empty()).then(|| input.to_string())
+}
+
 pub fn input_extensions() -> Option<Vec<String>> {
     let input: String = Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Extensions (comma separated, e.g.: pdf, doc, txt)")
@@ -508,11 +1274,7 @@ pub fn input_extensions() -> Option<Vec<String>> {
         return None;
     }
 
-    let exts: Vec<String> = input
-        .split(',')
-        .map(|s| s.trim().to_lowercase().trim_start_matches('.').to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
+    let exts = config::parse_extensions(&input);
 
     if exts.is_empty() {
         None
@@ -526,6 +1288,10 @@ pub fn input_extensions() -> Option<Vec<String>> {
 // ============================================================================
 
 pub fn confirm(message: &str) -> bool {
+    if assume_yes() {
+        return true;
+    }
+
     Confirm::with_theme(&ColorfulTheme::default())
         .with_prompt(message)
         .default(true)
@@ -534,6 +1300,10 @@ pub fn confirm(message: &str) -> bool {
 }
 
 pub fn confirm_with_default(message: &str, default: bool) -> bool {
+    if assume_yes() {
+        return true;
+    }
+
     Confirm::with_theme(&ColorfulTheme::default())
         .with_prompt(message)
         .default(default)
@@ -566,20 +1336,31 @@ pub fn prompt_after_action() -> bool {
     choice == 0
 }
 
+// Status messages go to stderr so stdout stays clean for piped output
+
 pub fn print_success(msg: &str) {
-    println!("\n{} {}", style("[+]").green().bold(), style(msg).green());
+    if is_quiet() {
+        return;
+    }
+    eprintln!("\n{} {}", style("[+]").green().bold(), style(msg).green());
 }
 
 pub fn print_error(msg: &str) {
-    println!("\n{} {}", style("[!]").red().bold(), style(msg).red());
+    eprintln!("\n{} {}", style("[!]").red().bold(), style(msg).red());
 }
 
 pub fn print_info(msg: &str) {
-    println!("\n{} {}", style("[i]").blue().bold(), msg);
+    if is_quiet() {
+        return;
+    }
+    eprintln!("\n{} {}", style("[i]").blue().bold(), msg);
 }
 
 pub fn print_warning(msg: &str) {
-    println!("\n{} {}", style("[!]").yellow().bold(), style(msg).yellow());
+    if is_quiet() {
+        return;
+    }
+    eprintln!("\n{} {}", style("[!]").yellow().bold(), style(msg).yellow());
 }
 
 // ============================================================================
@@ -739,11 +1520,164 @@ pub fn select_from_list(prompt: &str, items: &[&str]) -> Option<usize> {
     }
 }
 
+// ============================================================================
+// Rename Collisions
+// ============================================================================
+
+/// Warn about files headed for the same name in the same folder and let the
+/// user suffix them (the default, and what --yes does), skip all but the first
+/// of each group, or pick new names. Returns the skipped files, None on cancel.
+pub fn settle_rename_collisions(
+    files_map: &mut HashMap<String, Vec<PathBuf>>,
+    options: &mut OrganizeOptions,
+) -> Option<Vec<PathBuf>> {
+    let collisions = organizer::rename_collisions(files_map, options);
+    if collisions.is_empty() {
+        return Some(Vec::new());
+    }
+
+    print_rename_collisions(&collisions);
+    match select_collision_action()? {
+        0 => Some(Vec::new()),
+        1 => Some(organizer::skip_collisions(files_map, &collisions)),
+        _ => {
+            for collision in &collisions {
+                for path in collision.files.iter().skip(1) {
+                    if let Some(name) = input_collision_name(path, &collision.name) {
+                        options.names.insert(path.clone(), name);
+                    }
+                }
+            }
+            Some(Vec::new())
+        }
+    }
+}
+
+fn print_rename_collisions(collisions: &[RenameCollision]) {
+    if is_quiet() {
+        return;
+    }
+
+    print_warning("Some files would get the same name in their folder:");
+    for collision in collisions {
+        let folder = if collision.category.is_empty() {
+            "."
+        } else {
+            &collision.category
+        };
+        println!(
+            "  {} {}/{}",
+            style("[/]").cyan(),
+            style(folder).bold(),
+            style(&collision.name).green()
+        );
+        for file in &collision.files {
+            println!("      {}", style(file.display()).dim());
+        }
+    }
+    println!();
+}
+
+/// 0 = suffix the later files, 1 = skip them, 2 = pick new names, None = cancel.
+/// Without a terminal to ask on, the files are suffixed as before.
+fn select_collision_action() -> Option<usize> {
+    if assume_yes() || !console::user_attended() {
+        return Some(0);
+    }
+
+    let options = [
+        format!("{} Proceed, numbering the duplicates", style("[+]").green()),
+        format!(
+            "{} Skip all but the first file of each name",
+            style("[-]").yellow()
+        ),
+        format!("{} Pick new names", style("[~]").cyan()),
+        format!("{} Cancel", style("[<]").dim()),
+    ];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("How should these files be named?")
+        .items(&options)
+        .default(0)
+        .interact_opt()
+        .ok()??;
+
+    (selection < 3).then_some(selection)
+}
+
+/// New name for a file that collides with `taken`; empty keeps the numbered name
+fn input_collision_name(path: &Path, taken: &str) -> Option<String> {
+    let taken = taken.to_string();
+    let name: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "New name for {} (empty to number it)",
+            path.display()
+        ))
+        .allow_empty(true)
+        .validate_with(move |input: &String| -> Result<(), &str> {
+            if input.contains(['/', '\\']) {
+                Err("A name cannot contain a path separator")
+            } else if *input == taken {
+                Err("This name is the one that collides")
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()
+        .ok()?;
+
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
 // ============================================================================
 // Progress Indicators
 // ============================================================================
 
+/// Run organizer::move_files behind an "Organizing files..." progress bar
+pub fn move_files_with_progress(
+    source_dir: &str,
+    files_map: &HashMap<String, Vec<PathBuf>>,
+    options: &OrganizeOptions,
+) -> Result<MoveResult, OrganizeError> {
+    let use_bytes = match progress_unit() {
+        ProgressUnit::Files => false,
+        ProgressUnit::Bytes => true,
+        ProgressUnit::Auto => !is_quiet() && organizer::total_size(files_map) >= BYTE_PROGRESS_MIN,
+    };
+
+    if use_bytes {
+        let total = organizer::total_size(files_map);
+        let progress = ByteProgress(create_byte_progress_bar(total, "Organizing files..."));
+        return organizer::move_files(source_dir, files_map, options, &progress);
+    }
+
+    let total: usize = files_map.values().map(Vec::len).sum();
+    let progress = create_progress_bar(total as u64, "Organizing files...");
+    organizer::move_files(source_dir, files_map, options, &progress)
+}
+
+/// Progress bar sized in bytes, with throughput-based ETA
+fn create_byte_progress_bar(total_bytes: u64, message: &str) -> ProgressBar {
+    if is_quiet() {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new(total_bytes);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb.set_message(message.to_string());
+    pb
+}
+
 pub fn create_progress_bar(total: u64, message: &str) -> ProgressBar {
+    if is_quiet() {
+        return ProgressBar::hidden();
+    }
+
     let pb = ProgressBar::new(total);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -756,6 +1690,10 @@ pub fn create_progress_bar(total: u64, message: &str) -> ProgressBar {
 }
 
 pub fn create_spinner(message: &str) -> ProgressBar {
+    if is_quiet() {
+        return ProgressBar::hidden();
+    }
+
     let pb = ProgressBar::new_spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
@@ -4,25 +4,68 @@
 // CLI command handlers for vault operations.
 // Extracted from main.rs for better separation of concerns.
 
-use std::path::PathBuf;
-
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use crate::config;
+use crate::organizer;
 use crate::ui;
-use crate::vault::storage::SecurityLevel;
-use crate::vault::{self, Vault};
+use crate::vault::crypto::{KdfProfile, KEY_SIZE};
+use crate::vault::locker::LockOptions;
+use crate::vault::recovery::{self, RecoveryCodes, DEFAULT_CODE_GROUPS, MAX_CODE_GROUPS};
+use crate::vault::storage::{self, EntrySort, SecurityLevel, VaultEntry};
+use crate::vault::{self, locker, Vault};
 
 /// Vault CLI subcommands
 #[derive(Debug, Clone)]
 pub enum VaultAction {
-    Init { level: SecurityLevel },
-    Add { files: Vec<String> },
-    List,
-    Extract { name: String, dest: String },
-    Destroy { name: String },
+    Init {
+        level: SecurityLevel,
+        profile: KdfProfile,
+        recovery_groups: usize,
+    },
+    Add {
+        files: Vec<String>,
+        shred: bool,
+        keep: bool,
+        name: Option<String>,
+        per_file: bool,
+        recursive: bool,
+        dry_run: bool,
+        allow_large: bool,
+    },
+    List {
+        sort: EntrySort,
+        json: bool,
+    },
+    Find {
+        query: String,
+    },
+    Stats,
+    Extract {
+        name: String,
+        dest: String,
+    },
+    Open {
+        name: String,
+        timeout_minutes: u64,
+    },
+    Rename {
+        old_name: String,
+        new_name: String,
+    },
+    Destroy {
+        name: String,
+    },
     Recover,
 }
 
-/// Lock a single file with password (encrypt in place)
-pub fn run_lock(file: &str, keep: bool) {
+/// Lock a file, or every file of a directory, with password (encrypt in place).
+/// With `dry_run`, only list what would be locked.
+pub fn run_lock(file: &str, options: &LockOptions, recursive: bool, dry_run: bool) {
     let path = match resolve_path(file) {
         Some(p) => PathBuf::from(p),
         None => {
@@ -31,6 +74,11 @@ pub fn run_lock(file: &str, keep: bool) {
         }
     };
 
+    if dry_run {
+        preview_lock(&path, options, recursive);
+        return;
+    }
+
     let password = match prompt_password("Password: ") {
         Some(p) => p,
         None => return,
@@ -51,10 +99,20 @@ pub fn run_lock(file: &str, keep: bool) {
         return;
     }
 
-    match vault::lock_file(&path, &password, keep) {
+    if path.is_dir() {
+        match vault::lock_dir(&path, &password, options, recursive) {
+            Ok(result) => print_dir_result("Locked", "already locked", &result),
+            Err(e) => ui::print_error(&format!("{}", e)),
+        }
+        return;
+    }
+
+    match vault::lock_file(&path, &password, options) {
         Ok(vault_path) => {
             ui::print_success(&format!("Locked: {}", vault_path.display()));
-            if !keep {
+            if !options.keep_original && options.shred {
+                ui::print_info("Original file shredded");
+            } else if !options.keep_original {
                 ui::print_info("Original file removed");
             }
         }
@@ -62,8 +120,8 @@ pub fn run_lock(file: &str, keep: bool) {
     }
 }
 
-/// Unlock a .stlr file (decrypt)
-pub fn run_unlock(file: &str) {
+/// Unlock a .stlr file, or every .stlr file of a directory (decrypt)
+pub fn run_unlock(file: &str, recursive: bool) {
     let path = match resolve_path(file) {
         Some(p) => PathBuf::from(p),
         None => {
@@ -77,6 +135,14 @@ pub fn run_unlock(file: &str) {
         None => return,
     };
 
+    if path.is_dir() {
+        match vault::unlock_dir(&path, &password, recursive) {
+            Ok(result) => print_dir_result("Unlocked", "not .stlr", &result),
+            Err(e) => ui::print_error(&format!("{}", e)),
+        }
+        return;
+    }
+
     match vault::unlock_file(&path, &password) {
         Ok(original_path) => {
             ui::print_success(&format!("Unlocked: {}", original_path.display()));
@@ -90,16 +156,44 @@ pub fn run_vault(action: VaultAction) {
     let vault = Vault::open(None);
 
     match action {
-        VaultAction::Init { level } => init_vault(&vault, level),
-        VaultAction::Add { files } => add_to_vault(&vault, files),
-        VaultAction::List => list_vault(&vault),
+        VaultAction::Init {
+            level,
+            profile,
+            recovery_groups,
+        } => init_vault(&vault, level, profile, recovery_groups),
+        VaultAction::Add {
+            files,
+            shred,
+            keep,
+            name,
+            per_file,
+            recursive,
+            dry_run,
+            allow_large,
+        } => {
+            if dry_run {
+                preview_vault_add(&vault, files, shred, keep, name, per_file, recursive)
+            } else if per_file {
+                add_files_to_vault(&vault, files, shred, keep, recursive)
+            } else {
+                add_to_vault(&vault, files, shred, keep, name, allow_large)
+            }
+        }
+        VaultAction::List { sort, json } => list_vault(&vault, sort, json),
+        VaultAction::Find { query } => find_in_vault(&vault, &query),
+        VaultAction::Stats => vault_stats(&vault),
         VaultAction::Extract { name, dest } => extract_from_vault(&vault, &name, &dest),
+        VaultAction::Open {
+            name,
+            timeout_minutes,
+        } => open_from_vault(&vault, &name, timeout_minutes),
+        VaultAction::Rename { old_name, new_name } => rename_in_vault(&vault, &old_name, &new_name),
         VaultAction::Destroy { name } => destroy_in_vault(&vault, &name),
         VaultAction::Recover => recover_vault(&vault),
     }
 }
 
-fn init_vault(vault: &Vault, level: SecurityLevel) {
+fn init_vault(vault: &Vault, level: SecurityLevel, profile: KdfProfile, recovery_groups: usize) {
     if vault.is_initialized() {
         ui::print_error("Vault already initialized");
         return;
@@ -119,7 +213,7 @@ fn init_vault(vault: &Vault, level: SecurityLevel) {
         }
     }
 
-    match vault.init(&password, level) {
+    match vault.init(&password, level, profile, recovery_groups) {
         Ok(Some(codes)) => {
             ui::print_success("Vault initialized!");
             println!();
@@ -128,6 +222,7 @@ fn init_vault(vault: &Vault, level: SecurityLevel) {
             println!("   Code 1: {}", codes.code1);
             println!("   Code 2: {}", codes.code2);
             println!();
+            print_recovery_strength(&codes);
             ui::print_warning("Both codes are required for recovery.");
             ui::print_warning("These codes will NOT be shown again.");
         }
@@ -138,8 +233,70 @@ fn init_vault(vault: &Vault, level: SecurityLevel) {
     }
 }
 
-fn add_to_vault(vault: &Vault, files: Vec<String>) {
-    let password = match prompt_password("Vault password: ") {
+/// Folders over [vault] max_folder_size are refused unless `allow_large`
+fn add_to_vault(
+    vault: &Vault,
+    files: Vec<String>,
+    shred: bool,
+    keep: bool,
+    name: Option<String>,
+    allow_large: bool,
+) {
+    if name.is_some() && files.len() > 1 {
+        ui::print_error("--as needs a single file");
+        return;
+    }
+
+    // Checked before the password: a refused folder costs no key derivation
+    let limit = max_folder_bytes();
+    let mut paths = Vec::new();
+    for file in files {
+        let path = match resolve_path(&file) {
+            Some(p) => PathBuf::from(p),
+            None => {
+                ui::print_error(&format!("Invalid path: {}", file));
+                continue;
+            }
+        };
+
+        if let Some(size) = oversized_folder(&path, limit) {
+            if !allow_large {
+                ui::print_error(&format!(
+                    "{} is about {}, over the {} vault.max_folder_size limit \
+                     (folders are archived in memory). Use --allow-large to add it anyway, \
+                     or --per-file to store its files one by one.",
+                    file,
+                    format_size(size),
+                    format_size(limit)
+                ));
+                continue;
+            }
+            ui::print_warning(&format!("{} is about {}", file, format_size(size)));
+        }
+        paths.push((file, path));
+    }
+    if paths.is_empty() {
+        return;
+    }
+
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    for (file, path) in paths {
+        match vault.add(&path, &password, shred, keep, name.clone()) {
+            Ok(entry) => {
+                ui::print_success(&format!("Added: {} ({} bytes)", entry.name, entry.size));
+            }
+            Err(e) => ui::print_error(&format!("Failed to add {}: {}", file, e)),
+        }
+    }
+}
+
+/// --per-file: directories are added file by file, other paths as usual
+fn add_files_to_vault(vault: &Vault, files: Vec<String>, shred: bool, keep: bool, recursive: bool) {
+    let password = match prompt_vault_password(vault) {
         Some(p) => p,
         None => return,
     };
@@ -153,47 +310,215 @@ fn add_to_vault(vault: &Vault, files: Vec<String>) {
             }
         };
 
-        match vault.add(&path, &password) {
-            Ok(entry) => {
-                ui::print_success(&format!("Added: {} ({} bytes)", entry.name, entry.size));
+        if !path.is_dir() {
+            match vault.add(&path, &password, shred, keep, None) {
+                Ok(entry) => {
+                    ui::print_success(&format!("Added: {} ({} bytes)", entry.name, entry.size));
+                }
+                Err(e) => ui::print_error(&format!("Failed to add {}: {}", file, e)),
+            }
+            continue;
+        }
+
+        match vault.add_files_in(&path, &password, shred, keep, recursive) {
+            Ok(batch) => {
+                for skipped in &batch.skipped {
+                    ui::print_warning(&format!(
+                        "Skipped {}: name already in the vault",
+                        skipped.display()
+                    ));
+                }
+                for (failed, e) in &batch.failed {
+                    ui::print_error(&format!("Failed to add {}: {}", failed.display(), e));
+                }
+                ui::print_success(&format!(
+                    "{}: {} added, {} skipped, {} failed",
+                    file,
+                    batch.added.len(),
+                    batch.skipped.len(),
+                    batch.failed.len()
+                ));
             }
             Err(e) => ui::print_error(&format!("Failed to add {}: {}", file, e)),
         }
     }
 }
 
-fn list_vault(vault: &Vault) {
-    let password = match prompt_password("Vault password: ") {
+/// --dry-run: the entries `add` would create, checked against the index
+fn preview_vault_add(
+    vault: &Vault,
+    files: Vec<String>,
+    shred: bool,
+    keep: bool,
+    name: Option<String>,
+    per_file: bool,
+    recursive: bool,
+) {
+    if name.is_some() && files.len() > 1 {
+        ui::print_error("--as needs a single file");
+        return;
+    }
+
+    let mut items = Vec::new();
+    for file in files {
+        let path = match resolve_path(&file).map(PathBuf::from) {
+            Some(p) if p.exists() => p,
+            _ => {
+                ui::print_error(&format!("Not found: {}", file));
+                continue;
+            }
+        };
+
+        let sources = if per_file && path.is_dir() {
+            match locker::collect_files(&path, recursive) {
+                Ok(files) => files,
+                Err(e) => {
+                    ui::print_error(&format!("Cannot read {}: {}", file, e));
+                    continue;
+                }
+            }
+        } else {
+            vec![path]
+        };
+        for source in sources {
+            let entry_name = name.clone().unwrap_or_else(|| {
+                source
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "unnamed".to_string())
+            });
+            items.push((source, entry_name));
+        }
+    }
+    if items.is_empty() {
+        return;
+    }
+
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let planned = match vault.plan_add(&password, items) {
+        Ok(planned) => planned,
+        Err(e) => {
+            ui::print_error(&format!("{}", e));
+            return;
+        }
+    };
+
+    let limit = max_folder_bytes();
+    let total: u64 = planned.iter().map(|p| p.size).sum();
+    ui::print_info(&format!(
+        "Dry-run: would add {} entries ({})",
+        planned.len(),
+        format_size(total)
+    ));
+    for entry in &planned {
+        let line = format!(
+            "   {} ({}) -> {}",
+            entry.source.display(),
+            format_size(entry.size),
+            entry.name
+        );
+        if entry.taken {
+            ui::print_warning(&format!(
+                "{}  [name already in the vault]",
+                line.trim_start()
+            ));
+        } else if entry.source.is_dir() && entry.size > limit {
+            ui::print_warning(&format!("{}  [needs --allow-large]", line.trim_start()));
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    let taken = planned.iter().filter(|p| p.taken).count();
+    if taken > 0 {
+        ui::print_warning(&format!(
+            "{} name(s) already taken: use --as, or rename the existing entries first",
+            taken
+        ));
+    }
+    ui::print_info(originals_fate(keep, shred));
+}
+
+/// With `json`, the entries are printed to stdout as a JSON array
+fn list_vault(vault: &Vault, sort: EntrySort, json: bool) {
+    let password = match prompt_vault_password(vault) {
         Some(p) => p,
         None => return,
     };
 
     match vault.list(&password) {
-        Ok(entries) => {
-            if entries.is_empty() {
+        Ok(mut entries) => {
+            storage::sort_entries(&mut entries, sort);
+            if json {
+                ui::print_json(&entries);
+            } else if entries.is_empty() {
                 ui::print_info("Vault is empty");
             } else {
-                println!();
-                println!("{:<30} {:>12} {}", "NAME", "SIZE", "ADDED");
-                println!("{}", "-".repeat(60));
-                for entry in entries {
-                    let size = format_size(entry.size);
-                    let date = entry.added_at.format("%Y-%m-%d %H:%M");
-                    let name = if entry.is_directory {
-                        format!("{}/", entry.name)
-                    } else {
-                        entry.name
-                    };
-                    println!("{:<30} {:>12} {}", name, size, date);
-                }
+                print_entries(entries);
             }
         }
         Err(e) => ui::print_error(&format!("{}", e)),
     }
 }
 
+fn find_in_vault(vault: &Vault, query: &str) {
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    match vault.find(query, &password) {
+        Ok(entries) if entries.is_empty() => {
+            ui::print_info(&format!("No entry name contains '{}'", query))
+        }
+        Ok(entries) => print_entries(entries),
+        Err(e) => ui::print_error(&format!("{}", e)),
+    }
+}
+
+fn print_entries(entries: Vec<VaultEntry>) {
+    println!();
+    println!("{:<30} {:>12} ADDED", "NAME", "SIZE");
+    println!("{}", "-".repeat(60));
+    for entry in entries {
+        let size = format_size(entry.size);
+        let date = entry.added_at.format("%Y-%m-%d %H:%M");
+        let name = if entry.is_directory {
+            format!("{}/", entry.name)
+        } else {
+            entry.name
+        };
+        println!("{:<30} {:>12} {}", name, size, date);
+    }
+}
+
+fn vault_stats(vault: &Vault) {
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    match vault.stats(&password) {
+        Ok(stats) => {
+            println!();
+            println!(
+                "   Entries:   {} ({} files, {} directories)",
+                stats.entries, stats.files, stats.directories
+            );
+            println!("   Original:  {}", format_size(stats.original_bytes));
+            println!("   On disk:   {}", format_size(stats.data_bytes));
+            println!("   Overhead:  {}", format_size(stats.overhead_bytes()));
+        }
+        Err(e) => ui::print_error(&format!("{}", e)),
+    }
+}
+
 fn extract_from_vault(vault: &Vault, name: &str, dest: &str) {
-    let password = match prompt_password("Vault password: ") {
+    let password = match prompt_vault_password(vault) {
         Some(p) => p,
         None => return,
     };
@@ -214,8 +539,86 @@ fn extract_from_vault(vault: &Vault, name: &str, dest: &str) {
     }
 }
 
+/// Decrypt an entry into a private temp folder, open it with the system viewer,
+/// then shred it when the user presses Enter, hits Ctrl+C, or the timeout runs out
+fn open_from_vault(vault: &Vault, name: &str, timeout_minutes: u64) {
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    let temp_dir = env::temp_dir().join(format!("stellar-open-{}", process::id()));
+    if let Err(e) = create_private_dir(&temp_dir) {
+        ui::print_error(&format!("Cannot create temp folder: {}", e));
+        return;
+    }
+
+    let path = match vault.extract(name, &password, &temp_dir) {
+        Ok(p) => p,
+        Err(e) => {
+            ui::print_error(&format!("{}", e));
+            let _ = fs::remove_dir_all(&temp_dir);
+            return;
+        }
+    };
+
+    let cleanup_dir = temp_dir.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        let _ = locker::shred_dir(&cleanup_dir);
+        process::exit(130);
+    }) {
+        ui::print_warning(&format!("Could not set Ctrl+C handler: {}", e));
+    }
+
+    ui::print_warning(&format!(
+        "'{}' is decrypted at {} until you close it here",
+        name,
+        path.display()
+    ));
+
+    if let Err(e) = organizer::open_with_system(&path) {
+        ui::print_error(&format!("Cannot open {}: {}", path.display(), e));
+    }
+
+    // Viewers usually detach, so wait for the user rather than for the process
+    ui::print_info(&format!(
+        "Press Enter when done (removed automatically after {} min)...",
+        timeout_minutes
+    ));
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = io::stdin().read_line(&mut line);
+        let _ = tx.send(());
+    });
+    let _ = rx.recv_timeout(Duration::from_secs(timeout_minutes.saturating_mul(60)));
+
+    match locker::shred_dir(&temp_dir) {
+        Ok(()) => ui::print_success(&format!("Decrypted copy of '{}' shredded", name)),
+        Err(e) => ui::print_error(&format!(
+            "Failed to remove {}: {} (delete it manually)",
+            temp_dir.display(),
+            e
+        )),
+    }
+}
+
+fn rename_in_vault(vault: &Vault, old_name: &str, new_name: &str) {
+    let password = match prompt_vault_password(vault) {
+        Some(p) => p,
+        None => return,
+    };
+
+    match vault.rename(old_name, new_name, &password) {
+        Ok(entry) => {
+            ui::print_success(&format!("Renamed: {} -> {}", old_name, entry.name));
+        }
+        Err(e) => ui::print_error(&format!("{}", e)),
+    }
+}
+
 fn destroy_in_vault(vault: &Vault, name: &str) {
-    let password = match prompt_password("Vault password: ") {
+    let password = match prompt_vault_password(vault) {
         Some(p) => p,
         None => return,
     };
@@ -243,7 +646,10 @@ fn recover_vault(vault: &Vault) {
         return;
     }
 
-    ui::print_info("Enter your recovery codes:");
+    ui::print_info(&format!(
+        "Enter your recovery codes ({}):",
+        recovery_code_format(vault)
+    ));
 
     let code1 = match ui::input_text("Code 1") {
         Some(c) => c,
@@ -279,10 +685,137 @@ fn recover_vault(vault: &Vault) {
 // Helpers
 // ============================================================================
 
+/// --dry-run: the files `lock` would encrypt and where the .stlr files would go
+fn preview_lock(path: &Path, options: &LockOptions, recursive: bool) {
+    let plan = match locker::plan_lock(path, options, recursive) {
+        Ok(plan) => plan,
+        Err(e) => {
+            ui::print_error(&format!("{}", e));
+            return;
+        }
+    };
+
+    let total: u64 = plan.files.iter().map(|f| f.size).sum();
+    ui::print_info(&format!(
+        "Dry-run: would lock {} files ({})",
+        plan.files.len(),
+        format_size(total)
+    ));
+    for file in &plan.files {
+        println!(
+            "   {} ({}) -> {}{}",
+            file.file.display(),
+            format_size(file.size),
+            file.locked.display(),
+            if file.collides {
+                " (already exists)"
+            } else {
+                ""
+            }
+        );
+    }
+    let collisions = plan.files.iter().filter(|f| f.collides).count();
+    if collisions > 0 {
+        ui::print_warning(&format!(
+            "{} file(s) would fail: their .stlr file already exists (originals kept)",
+            collisions
+        ));
+    }
+    if plan.skipped > 0 {
+        ui::print_info(&format!("{} already locked, skipped", plan.skipped));
+    }
+    ui::print_info(originals_fate(options.keep_original, options.shred));
+}
+
+fn originals_fate(keep: bool, shred: bool) -> &'static str {
+    match (keep, shred) {
+        (true, _) => "Originals would be kept",
+        (false, true) => "Originals would be shredded",
+        (false, false) => "Originals would be removed",
+    }
+}
+
+fn print_dir_result(action: &str, skipped: &str, result: &locker::DirLockResult) {
+    for (path, e) in &result.failed {
+        ui::print_error(&format!("{}: {}", path.display(), e));
+    }
+    ui::print_success(&format!(
+        "{} {} files ({} {}, {} failed)",
+        action,
+        result.done.len(),
+        result.skipped,
+        skipped,
+        result.failed.len()
+    ));
+}
+
+/// Temp folder readable by the current user only
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(path)
+}
+
 pub fn prompt_password(prompt: &str) -> Option<String> {
     rpassword::prompt_password(prompt).ok()
 }
 
+/// Ask for the vault password, unless recent wrong passwords still
+/// require waiting before the next attempt
+pub fn prompt_vault_password(vault: &Vault) -> Option<String> {
+    if let Some(delay) = vault.retry_delay() {
+        ui::print_error(&format!(
+            "Too many wrong passwords, try again in {}s",
+            delay.as_secs().max(1)
+        ));
+        return None;
+    }
+
+    prompt_password("Vault password: ")
+}
+
+/// Entropy of the recovery codes, with a warning when they are weaker than
+/// the vault key. The codes bypass Argon2, so each guess is a single hash.
+pub fn print_recovery_strength(codes: &RecoveryCodes) {
+    let bits = codes.entropy_bits();
+    ui::print_info(&format!("Recovery codes strength: {} bits", bits));
+    if bits < (KEY_SIZE * 8) as u32 {
+        ui::print_warning(&format!(
+            "The codes unlock the vault without Argon2, so they are weaker than your password's \
+             protection. Use --recovery-groups {} for full-strength codes.",
+            MAX_CODE_GROUPS
+        ));
+    }
+}
+
+/// [vault] max_folder_size from the config, in bytes
+pub fn max_folder_bytes() -> u64 {
+    // read_config already rejects a limit that does not parse
+    config::read_config()
+        .map(|c| c.vault)
+        .unwrap_or_default()
+        .max_folder_bytes()
+        .unwrap_or(u64::MAX)
+}
+
+/// Estimated size of `path` when it is a folder bigger than `limit`
+pub fn oversized_folder(path: &Path, limit: u64) -> Option<u64> {
+    if !path.is_dir() {
+        return None;
+    }
+    let size = organizer::path_size(path);
+    (size > limit).then_some(size)
+}
+
+/// XXXX-XXXX-XXXX, the shape of this vault's recovery codes
+pub fn recovery_code_format(vault: &Vault) -> String {
+    recovery::code_format(vault.recovery_groups().unwrap_or(DEFAULT_CODE_GROUPS))
+}
+
 pub fn prompt_new_password() -> Option<String> {
     let password = prompt_password("Password: ")?;
     let confirm = prompt_password("Confirm password: ")?;
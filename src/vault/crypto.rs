@@ -4,6 +4,7 @@ use aes_gcm::{
 };
 use argon2::{Algorithm, Argon2, Params, Version};
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
 use crate::vault::{VaultError, VaultResult};
@@ -16,6 +17,56 @@ const ARGON2_M_COST: u32 = 65536;
 const ARGON2_T_COST: u32 = 3;
 const ARGON2_P_COST: u32 = 4;
 
+/// Argon2id cost parameters. A vault stores the ones it was created with,
+/// so changing the profiles never breaks existing vaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+/// The original fixed parameters (64MB, 3 iterations, 4 lanes): used by
+/// .stlr files and by vaults created before profiles existed
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: ARGON2_M_COST,
+            t_cost: ARGON2_T_COST,
+            p_cost: ARGON2_P_COST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfProfile {
+    /// 19MB, 2 iterations, 1 lane - fast on old laptops
+    Interactive,
+    /// 64MB, 3 iterations, 4 lanes
+    Balanced,
+    /// 256MB, 4 iterations, 4 lanes
+    Paranoid,
+}
+
+impl KdfProfile {
+    pub fn params(self) -> KdfParams {
+        match self {
+            KdfProfile::Interactive => KdfParams {
+                m_cost: 19456,
+                t_cost: 2,
+                p_cost: 1,
+            },
+            KdfProfile::Balanced => KdfParams::default(),
+            KdfProfile::Paranoid => KdfParams {
+                m_cost: 262144,
+                t_cost: 4,
+                p_cost: 4,
+            },
+        }
+    }
+}
+
 pub fn generate_salt() -> [u8; SALT_SIZE] {
     let mut salt = [0u8; SALT_SIZE];
     rand::thread_rng().fill_bytes(&mut salt);
@@ -28,8 +79,8 @@ fn generate_nonce() -> [u8; NONCE_SIZE] {
     nonce
 }
 
-pub fn derive_key(password: &str, salt: &[u8]) -> VaultResult<[u8; KEY_SIZE]> {
-    let params = Params::new(ARGON2_M_COST, ARGON2_T_COST, ARGON2_P_COST, Some(KEY_SIZE))
+pub fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> VaultResult<[u8; KEY_SIZE]> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(KEY_SIZE))
         .map_err(|e| VaultError::CryptoError(e.to_string()))?;
 
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
@@ -77,7 +128,7 @@ pub fn decrypt_with_key(encrypted: &[u8], key: &[u8; KEY_SIZE]) -> VaultResult<V
 
 pub fn encrypt(data: &[u8], password: &str) -> VaultResult<Vec<u8>> {
     let salt = generate_salt();
-    let mut key = derive_key(password, &salt)?;
+    let mut key = derive_key(password, &salt, &KdfParams::default())?;
     let encrypted = encrypt_with_key(data, &key)?;
     key.zeroize();
 
@@ -95,7 +146,7 @@ pub fn decrypt(encrypted: &[u8], password: &str) -> VaultResult<Vec<u8>> {
     let salt = &encrypted[..SALT_SIZE];
     let ciphertext = &encrypted[SALT_SIZE..];
 
-    let mut key = derive_key(password, salt)?;
+    let mut key = derive_key(password, salt, &KdfParams::default())?;
     let plaintext = decrypt_with_key(ciphertext, &key)?;
     key.zeroize();
 
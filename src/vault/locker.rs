@@ -1,10 +1,29 @@
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
+use rand::RngCore;
+
 use crate::vault::crypto::{decrypt, encrypt};
 use crate::vault::{VaultError, VaultResult};
 
 const VAULT_EXTENSION: &str = "stlr";
+const SHRED_CHUNK_SIZE: usize = 64 * 1024;
+/// Starts the decrypted payload of a file locked with `hide_name`,
+/// followed by the name length (u16 LE) and the name itself
+const NAME_HEADER_MAGIC: &[u8] = b"\0STLR-NAME\0";
+
+/// How `lock_file` writes the encrypted copy
+#[derive(Debug, Clone, Default)]
+pub struct LockOptions {
+    pub keep_original: bool,
+    pub shred: bool,
+    /// Write the .stlr file here instead of next to the original
+    pub dest: Option<PathBuf>,
+    /// Store the file name inside the encrypted payload and give the
+    /// .stlr file a random name, so the original name never hits the disk
+    pub hide_name: bool,
+}
 
 fn get_vault_path(path: &Path) -> PathBuf {
     let mut vault_path = path.to_path_buf();
@@ -16,6 +35,92 @@ fn get_vault_path(path: &Path) -> PathBuf {
     vault_path
 }
 
+/// A random, unused `<id>.stlr` path in `dir`
+fn opaque_path(dir: &Path) -> PathBuf {
+    use rand::Rng;
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = rand::thread_rng();
+
+    loop {
+        let id: String = (0..16)
+            .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+            .collect();
+        let path = dir.join(format!("{}.{}", id, VAULT_EXTENSION));
+        if !path.exists() {
+            return path;
+        }
+    }
+}
+
+/// Folder the .stlr file of `path` goes to
+fn locked_dir(path: &Path, options: &LockOptions) -> PathBuf {
+    match &options.dest {
+        Some(dest) => dest.clone(),
+        None => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+    }
+}
+
+/// `<name>.<ext>.stlr` in `dir`
+fn locked_name(path: &Path, dir: &Path) -> PathBuf {
+    let vault_path = get_vault_path(path);
+    match vault_path.file_name() {
+        Some(name) => dir.join(name),
+        None => vault_path,
+    }
+}
+
+/// Options for one file of a locked directory: with `dest`, its subfolder
+/// is mirrored under it
+fn dir_file_options(dir: &Path, file: &Path, options: &LockOptions) -> LockOptions {
+    let mut file_options = options.clone();
+    if let (Some(dest), Some(parent)) = (&options.dest, file.parent()) {
+        let relative = parent.strip_prefix(dir).unwrap_or(Path::new(""));
+        file_options.dest = Some(dest.join(relative));
+    }
+    file_options
+}
+
+fn name_header(name: &str) -> VaultResult<Vec<u8>> {
+    let len = u16::try_from(name.len())
+        .map_err(|_| VaultError::CryptoError(format!("File name too long: {}", name)))?;
+
+    let mut header = NAME_HEADER_MAGIC.to_vec();
+    header.extend_from_slice(&len.to_le_bytes());
+    header.extend_from_slice(name.as_bytes());
+    Ok(header)
+}
+
+/// The hidden file name and the header length, if the payload has one
+fn read_name_header(data: &[u8]) -> VaultResult<Option<(String, usize)>> {
+    let Some(rest) = data.strip_prefix(NAME_HEADER_MAGIC) else {
+        return Ok(None);
+    };
+
+    let len = rest
+        .get(..2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or(VaultError::CorruptedData)?;
+    let name = rest
+        .get(2..2 + len)
+        .and_then(|b| String::from_utf8(b.to_vec()).ok())
+        .ok_or(VaultError::CorruptedData)?;
+
+    // Never let a crafted header write outside the folder
+    if Path::new(&name).file_name() != Some(name.as_ref()) {
+        return Err(VaultError::CorruptedData);
+    }
+
+    Ok(Some((name, NAME_HEADER_MAGIC.len() + 2 + len)))
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+fn is_vault_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == VAULT_EXTENSION)
+}
+
 fn get_original_path(vault_path: &Path) -> VaultResult<PathBuf> {
     let path_str = vault_path.to_string_lossy();
     let suffix = format!(".{}", VAULT_EXTENSION);
@@ -27,43 +132,279 @@ fn get_original_path(vault_path: &Path) -> VaultResult<PathBuf> {
     Ok(PathBuf::from(&path_str[..path_str.len() - suffix.len()]))
 }
 
-pub fn lock_file(path: &Path, password: &str, keep_original: bool) -> VaultResult<PathBuf> {
+pub fn lock_file(path: &Path, password: &str, options: &LockOptions) -> VaultResult<PathBuf> {
     if !path.exists() {
         return Err(VaultError::FileNotFound(path.to_path_buf()));
     }
 
-    if path
-        .extension()
-        .map(|e| e == VAULT_EXTENSION)
-        .unwrap_or(false)
-    {
+    if is_vault_file(path) {
         return Err(VaultError::AlreadyExists(path.display().to_string()));
     }
 
-    let data = fs::read(path)?;
+    let dir = locked_dir(path, options);
+    let vault_path = if options.hide_name {
+        None
+    } else {
+        Some(locked_name(path, &dir))
+    };
+    // Checked before any work; write_new still guards against a race
+    if let Some(existing) = vault_path.as_ref().filter(|p| p.exists()) {
+        return Err(VaultError::AlreadyExists(existing.display().to_string()));
+    }
+
+    let mut data = fs::read(path)?;
+    if options.hide_name {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        data = [name_header(&name)?, data].concat();
+    }
     let encrypted = encrypt(&data, password)?;
-    let vault_path = get_vault_path(path);
 
-    fs::write(&vault_path, encrypted)?;
+    if options.dest.is_some() {
+        fs::create_dir_all(&dir)?;
+    }
+    let vault_path = vault_path.unwrap_or_else(|| opaque_path(&dir));
 
-    if !keep_original {
-        fs::remove_file(path)?;
+    write_new(&vault_path, &encrypted)?;
+
+    if !options.keep_original {
+        if options.shred {
+            shred_file(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
     }
 
     Ok(vault_path)
 }
 
+/// A file `lock --dry-run` would encrypt
+#[derive(Debug)]
+pub struct PlannedLock {
+    pub file: PathBuf,
+    pub size: u64,
+    /// The .stlr file; with `hide_name` its name is only chosen when locking
+    pub locked: PathBuf,
+    /// `locked` already exists (or another file of the plan goes there):
+    /// locking this file would fail
+    pub collides: bool,
+}
+
+/// What `lock_file` / `lock_dir` would do, without reading or writing anything
+#[derive(Debug, Default)]
+pub struct LockPlan {
+    pub files: Vec<PlannedLock>,
+    /// Already .stlr files, left alone
+    pub skipped: usize,
+}
+
+/// Plan the lock of a file, or of every file of a directory as `lock_dir` does
+pub fn plan_lock(path: &Path, options: &LockOptions, recursive: bool) -> VaultResult<LockPlan> {
+    if !path.exists() {
+        return Err(VaultError::FileNotFound(path.to_path_buf()));
+    }
+
+    let files = if path.is_dir() {
+        collect_files(path, recursive)?
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut plan = LockPlan::default();
+    let mut claimed = std::collections::HashSet::new();
+    for file in files {
+        if is_vault_file(&file) {
+            plan.skipped += 1;
+            continue;
+        }
+
+        let file_options = dir_file_options(path, &file, options);
+        let dir = locked_dir(&file, &file_options);
+        let locked = if options.hide_name {
+            dir.join(format!("<random>.{}", VAULT_EXTENSION))
+        } else {
+            locked_name(&file, &dir)
+        };
+        let collides = !options.hide_name && (locked.exists() || !claimed.insert(locked.clone()));
+        plan.files.push(PlannedLock {
+            size: fs::metadata(&file)?.len(),
+            file,
+            locked,
+            collides,
+        });
+    }
+
+    Ok(plan)
+}
+
+/// Files handled by `lock_dir` / `unlock_dir`
+#[derive(Debug, Default)]
+pub struct DirLockResult {
+    pub done: Vec<PathBuf>,
+    /// Already locked (lock) or not a .stlr file (unlock)
+    pub skipped: usize,
+    pub failed: Vec<(PathBuf, VaultError)>,
+}
+
+/// Lock every file of a directory in place, next to the original, so the
+/// folder layout is kept. Files that are already .stlr are skipped.
+/// With `dest`, the subfolders are mirrored under it.
+pub fn lock_dir(
+    path: &Path,
+    password: &str,
+    options: &LockOptions,
+    recursive: bool,
+) -> VaultResult<DirLockResult> {
+    let mut result = DirLockResult::default();
+
+    for file in collect_files(path, recursive)? {
+        if is_vault_file(&file) {
+            result.skipped += 1;
+            continue;
+        }
+
+        let file_options = dir_file_options(path, &file, options);
+        match lock_file(&file, password, &file_options) {
+            Ok(vault_path) => result.done.push(vault_path),
+            Err(e) => result.failed.push((file, e)),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Unlock every .stlr file of a directory in place.
+/// Stops at the first wrong password instead of failing on every file.
+pub fn unlock_dir(path: &Path, password: &str, recursive: bool) -> VaultResult<DirLockResult> {
+    let mut result = DirLockResult::default();
+
+    for file in collect_files(path, recursive)? {
+        if !is_vault_file(&file) {
+            result.skipped += 1;
+            continue;
+        }
+
+        match unlock_file(&file, password) {
+            Ok(original_path) => result.done.push(original_path),
+            Err(VaultError::InvalidPassword) => return Err(VaultError::InvalidPassword),
+            Err(e) => result.failed.push((file, e)),
+        }
+    }
+
+    Ok(result)
+}
+
 pub fn unlock_file(vault_path: &Path, password: &str) -> VaultResult<PathBuf> {
     if !vault_path.exists() {
         return Err(VaultError::FileNotFound(vault_path.to_path_buf()));
     }
 
-    let original_path = get_original_path(vault_path)?;
+    let mut original_path = get_original_path(vault_path)?;
     let encrypted = fs::read(vault_path)?;
-    let data = decrypt(&encrypted, password)?;
+    let mut data = decrypt(&encrypted, password)?;
+
+    // Locked with hide_name: the real name is in the payload
+    if let Some((name, header_len)) = read_name_header(&data)? {
+        original_path = original_path.with_file_name(name);
+        data.drain(..header_len);
+    }
 
-    fs::write(&original_path, data)?;
+    write_new(&original_path, &data)?;
     fs::remove_file(vault_path)?;
 
     Ok(original_path)
 }
+
+/// Write `data` to a file that must not exist yet, so locking or unlocking
+/// never replaces another file (a partial file is removed on failure)
+fn write_new(path: &Path, data: &[u8]) -> VaultResult<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => VaultError::AlreadyExists(path.display().to_string()),
+            _ => e.into(),
+        })?;
+
+    if let Err(e) = file.write_all(data).and_then(|_| file.sync_all()) {
+        drop(file);
+        let _ = fs::remove_file(path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Overwrite a file with random bytes in a single pass, then truncate and delete it.
+///
+/// This is best-effort: SSD wear-leveling and copy-on-write filesystems
+/// (APFS, Btrfs, ZFS) may keep the original blocks around regardless.
+/// A symbolic link is only removed: the file it points to is not shredded.
+pub fn shred_file(path: &Path) -> VaultResult<()> {
+    if is_symlink(path) {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+
+    let mut rng = rand::thread_rng();
+    let mut buffer = vec![0u8; SHRED_CHUNK_SIZE];
+    let mut remaining = len;
+
+    while remaining > 0 {
+        let n = remaining.min(SHRED_CHUNK_SIZE as u64) as usize;
+        rng.fill_bytes(&mut buffer[..n]);
+        file.write_all(&buffer[..n])?;
+        remaining -= n as u64;
+    }
+
+    file.sync_all()?;
+    file.set_len(0)?;
+    drop(file);
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Shred every file in a directory tree, then remove the directories.
+/// Symbolic links are removed without touching what they point to.
+pub fn shred_dir(path: &Path) -> VaultResult<()> {
+    if is_symlink(path) {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            shred_dir(&entry.path())?;
+        } else {
+            shred_file(&entry.path())?;
+        }
+    }
+
+    fs::remove_dir(path)?;
+    Ok(())
+}
+
+/// Regular files of `dir` in name order (symbolic links are ignored)
+pub fn collect_files(dir: &Path, recursive: bool) -> VaultResult<Vec<PathBuf>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        if file_type.is_file() {
+            files.push(entry.path());
+        } else if file_type.is_dir() && recursive {
+            files.extend(collect_files(&entry.path(), recursive)?);
+        }
+    }
+
+    Ok(files)
+}
@@ -7,7 +7,7 @@ pub mod storage;
 use std::io;
 use std::path::PathBuf;
 
-pub use locker::{lock_file, unlock_file};
+pub use locker::{lock_dir, lock_file, unlock_dir, unlock_file};
 pub use storage::Vault;
 
 #[derive(Debug)]
@@ -22,6 +22,7 @@ pub enum VaultError {
     InvalidRecoveryCode,
     WeakPassword(String),
     NotVaultFile(PathBuf),
+    InvalidName(String),
 }
 
 impl std::fmt::Display for VaultError {
@@ -37,6 +38,7 @@ impl std::fmt::Display for VaultError {
             VaultError::InvalidRecoveryCode => write!(f, "Invalid recovery code"),
             VaultError::WeakPassword(msg) => write!(f, "Password too weak: {}", msg),
             VaultError::NotVaultFile(p) => write!(f, "Not a .stlr file: {}", p.display()),
+            VaultError::InvalidName(n) => write!(f, "Invalid entry name: '{}'", n),
         }
     }
 }
@@ -5,9 +5,13 @@ use crate::vault::crypto::{decrypt_with_key, encrypt_with_key, KEY_SIZE};
 use crate::vault::{VaultError, VaultResult};
 
 const CODE_SEGMENT_LENGTH: usize = 4;
-const CODE_SEGMENTS: usize = 3;
 const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
 
+/// Groups of 4 characters per code: XXXX-XXXX-XXXX
+pub const DEFAULT_CODE_GROUPS: usize = 3;
+/// Enough groups for the two codes to carry a full 256-bit key
+pub const MAX_CODE_GROUPS: usize = 7;
+
 #[derive(Debug, Zeroize, ZeroizeOnDrop)]
 pub struct RecoveryCodes {
     pub code1: String,
@@ -15,13 +19,22 @@ pub struct RecoveryCodes {
 }
 
 impl RecoveryCodes {
-    pub fn generate() -> Self {
+    /// Two codes of `groups` groups of 4 characters (at least one group)
+    pub fn generate(groups: usize) -> Self {
+        let groups = groups.max(1);
         Self {
-            code1: generate_code(),
-            code2: generate_code(),
+            code1: generate_code(groups),
+            code2: generate_code(groups),
         }
     }
 
+    /// Bits of entropy of both codes together. The codes are hashed into a
+    /// 256-bit key, so that is the ceiling.
+    pub fn entropy_bits(&self) -> u32 {
+        let chars = normalize(&self.code1).len() + normalize(&self.code2).len();
+        (chars as u32 * CODE_CHARS.len().ilog2()).min(KEY_SIZE as u32 * 8)
+    }
+
     pub fn encrypt_key(&self, key: &[u8; KEY_SIZE]) -> VaultResult<Vec<u8>> {
         let combined = self.combined_key();
         encrypt_with_key(key, &combined)
@@ -40,9 +53,14 @@ impl RecoveryCodes {
     }
 }
 
-fn generate_code() -> String {
+/// The XXXX-XXXX... shape of a code with `groups` groups, shown when asking for one
+pub fn code_format(groups: usize) -> String {
+    vec!["X".repeat(CODE_SEGMENT_LENGTH); groups.max(1)].join("-")
+}
+
+fn generate_code(groups: usize) -> String {
     let mut rng = rand::thread_rng();
-    (0..CODE_SEGMENTS)
+    (0..groups)
         .map(|_| {
             (0..CODE_SEGMENT_LENGTH)
                 .map(|_| CODE_CHARS[rng.gen_range(0..CODE_CHARS.len())] as char)
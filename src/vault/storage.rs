@@ -1,16 +1,27 @@
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
 
+use crate::organizer;
 use crate::vault::crypto::{
-    decrypt_with_key, derive_key, encrypt_with_key, generate_salt, KEY_SIZE,
+    decrypt_with_key, derive_key, encrypt_with_key, generate_salt, KdfParams, KdfProfile, KEY_SIZE,
 };
-use crate::vault::recovery::RecoveryCodes;
+use crate::vault::locker::{collect_files, shred_dir, shred_file};
+use crate::vault::recovery::{RecoveryCodes, DEFAULT_CODE_GROUPS};
 use crate::vault::{VaultError, VaultResult};
 
+/// Wrong passwords allowed before attempts are throttled
+const FREE_ATTEMPTS: u32 = 3;
+const BASE_DELAY_SECS: i64 = 5;
+const MAX_DELAY_SECS: i64 = 15 * 60;
+/// Failures older than this no longer count
+const FAILURE_MEMORY_SECS: i64 = 60 * 60;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SecurityLevel {
     Standard,
@@ -26,6 +37,83 @@ pub struct VaultEntry {
     pub is_directory: bool,
 }
 
+/// Order of `vault list`: by name, largest first, or most recently added first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntrySort {
+    #[default]
+    Name,
+    Size,
+    Date,
+}
+
+impl EntrySort {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "size" => Self::Size,
+            "date" => Self::Date,
+            _ => Self::Name,
+        }
+    }
+}
+
+/// Sort entries for display; ties fall back to the name
+pub fn sort_entries(entries: &mut [VaultEntry], sort: EntrySort) {
+    entries.sort_by(|a, b| {
+        let by_name = a.name.to_lowercase().cmp(&b.name.to_lowercase());
+        match sort {
+            EntrySort::Name => by_name,
+            EntrySort::Size => b.size.cmp(&a.size).then(by_name),
+            EntrySort::Date => b.added_at.cmp(&a.added_at).then(by_name),
+        }
+    });
+}
+
+/// Entries whose name contains `query`, ignoring case
+pub fn filter_entries(entries: Vec<VaultEntry>, query: &str) -> Vec<VaultEntry> {
+    let query = query.to_lowercase();
+    entries
+        .into_iter()
+        .filter(|e| e.name.to_lowercase().contains(&query))
+        .collect()
+}
+
+/// Logical sizes come from the index; `data_bytes` is what the encrypted
+/// entries actually take on disk
+#[derive(Debug, Clone, Default)]
+pub struct VaultStats {
+    pub entries: usize,
+    pub files: usize,
+    pub directories: usize,
+    pub original_bytes: u64,
+    pub data_bytes: u64,
+}
+
+impl VaultStats {
+    /// Bytes added by encryption (nonces, tags) and leftover data files
+    pub fn overhead_bytes(&self) -> u64 {
+        self.data_bytes.saturating_sub(self.original_bytes)
+    }
+}
+
+/// An entry `vault add --dry-run` would create
+#[derive(Debug)]
+pub struct PlannedEntry {
+    pub source: PathBuf,
+    pub name: String,
+    pub size: u64,
+    /// The vault, or an earlier file of the same batch, already has this name
+    pub taken: bool,
+}
+
+/// Outcome of adding a folder file by file
+#[derive(Debug, Default)]
+pub struct BatchAdd {
+    pub added: Vec<VaultEntry>,
+    /// Files left alone because an entry already has their name
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, VaultError)>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct VaultIndex {
     security_level: SecurityLevel,
@@ -45,6 +133,21 @@ impl VaultIndex {
 struct VaultMeta {
     salt: Vec<u8>,
     security_level: SecurityLevel,
+    /// Missing in vaults created before profiles: those used the defaults
+    #[serde(default)]
+    kdf: KdfParams,
+    /// Consecutive wrong passwords, reset by a successful unlock
+    #[serde(default)]
+    failed_attempts: u32,
+    #[serde(default)]
+    last_failure: Option<DateTime<Utc>>,
+    /// Groups per recovery code; vaults created before the setting used 3
+    #[serde(default = "default_code_groups")]
+    recovery_groups: usize,
+}
+
+fn default_code_groups() -> usize {
+    DEFAULT_CODE_GROUPS
 }
 
 pub struct Vault {
@@ -79,6 +182,24 @@ impl Vault {
         self.data_path().join(format!("{}.stlr", id))
     }
 
+    fn lock_path(&self) -> PathBuf {
+        self.path.join("vault.lock")
+    }
+
+    /// Exclusive lock held for the whole of a mutating operation (released
+    /// when the file is dropped), so two processes adding at once take turns
+    /// instead of one overwriting the other's index. Reads take it only to
+    /// update the wrong-password counter.
+    fn lock_for_writing(&self) -> VaultResult<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.lock_path())?;
+        FileExt::lock_exclusive(&file)?;
+        Ok(file)
+    }
+
     fn ensure_dirs(&self) -> VaultResult<()> {
         fs::create_dir_all(&self.path)?;
         fs::create_dir_all(self.data_path())?;
@@ -96,9 +217,80 @@ impl Vault {
         Ok(())
     }
 
-    fn derive_master_key(&self, password: &str) -> VaultResult<[u8; KEY_SIZE]> {
+    /// Derive the key and decrypt the index, counting wrong passwords in
+    /// meta.json so the command layer can slow down guessing. The caller
+    /// holds `lock_for_writing`.
+    fn open_index(&self, password: &str) -> VaultResult<([u8; KEY_SIZE], VaultIndex)> {
+        self.open_index_with(password, || Ok(None))
+    }
+
+    /// Same as `open_index`, for reads that do not hold the lock: it is
+    /// taken only to update the counter
+    fn open_index_for_reading(&self, password: &str) -> VaultResult<([u8; KEY_SIZE], VaultIndex)> {
+        self.open_index_with(password, || self.lock_for_writing().map(Some))
+    }
+
+    fn open_index_with(
+        &self,
+        password: &str,
+        lock: impl FnOnce() -> VaultResult<Option<File>>,
+    ) -> VaultResult<([u8; KEY_SIZE], VaultIndex)> {
         let meta = self.read_meta()?;
-        derive_key(password, &meta.salt)
+        let key = derive_key(password, &meta.salt, &meta.kdf)?;
+
+        let result = self.read_index(&key);
+        let failed = match result {
+            Ok(_) => false,
+            Err(VaultError::InvalidPassword) => true,
+            Err(e) => return Err(e),
+        };
+
+        if failed || meta.failed_attempts > 0 {
+            let _lock = lock()?;
+            // Re-read under the lock: a recover may have replaced the salt
+            // since, and writing back the meta read above would undo it
+            let mut current = self.read_meta()?;
+            if current.salt == meta.salt {
+                if failed {
+                    let recent = current
+                        .last_failure
+                        .is_some_and(|t| (Utc::now() - t).num_seconds() < FAILURE_MEMORY_SECS);
+                    current.failed_attempts = if recent {
+                        current.failed_attempts + 1
+                    } else {
+                        1
+                    };
+                    current.last_failure = Some(Utc::now());
+                } else {
+                    current.failed_attempts = 0;
+                    current.last_failure = None;
+                }
+                self.write_meta(&current)?;
+            }
+        }
+
+        result.map(|index| (key, index))
+    }
+
+    /// How long to wait before the next password attempt is accepted.
+    /// The first few failures are free; each later one doubles the delay,
+    /// and the count is forgotten an hour after the last failure.
+    pub fn retry_delay(&self) -> Option<Duration> {
+        let meta = self.read_meta().ok()?;
+        let last_failure = meta.last_failure?;
+        if meta.failed_attempts < FREE_ATTEMPTS {
+            return None;
+        }
+
+        let elapsed = (Utc::now() - last_failure).num_seconds().max(0);
+        if elapsed >= FAILURE_MEMORY_SECS {
+            return None;
+        }
+
+        let doublings = (meta.failed_attempts - FREE_ATTEMPTS).min(16);
+        let delay = (BASE_DELAY_SECS << doublings).min(MAX_DELAY_SECS);
+        let remaining = delay - elapsed;
+        (remaining > 0).then(|| Duration::from_secs(remaining as u64))
     }
 
     fn read_index(&self, key: &[u8; KEY_SIZE]) -> VaultResult<VaultIndex> {
@@ -139,23 +331,37 @@ impl Vault {
         self.meta_path().exists()
     }
 
+    /// Create the vault; a standard vault also gets two recovery codes of
+    /// `recovery_groups` groups each
     pub fn init(
         &self,
         password: &str,
         security_level: SecurityLevel,
+        profile: KdfProfile,
+        recovery_groups: usize,
     ) -> VaultResult<Option<RecoveryCodes>> {
         if self.is_initialized() {
             return Err(VaultError::AlreadyExists("Vault".to_string()));
         }
 
         self.ensure_dirs()?;
+        let _lock = self.lock_for_writing()?;
+        // Another init may have finished while this one waited for the lock
+        if self.is_initialized() {
+            return Err(VaultError::AlreadyExists("Vault".to_string()));
+        }
 
         let salt = generate_salt();
-        let key = derive_key(password, &salt)?;
+        let kdf = profile.params();
+        let key = derive_key(password, &salt, &kdf)?;
 
         let meta = VaultMeta {
             salt: salt.to_vec(),
             security_level,
+            kdf,
+            failed_attempts: 0,
+            last_failure: None,
+            recovery_groups,
         };
         self.write_meta(&meta)?;
 
@@ -163,7 +369,7 @@ impl Vault {
         self.write_index(&index, &key)?;
 
         if security_level == SecurityLevel::Standard {
-            let codes = RecoveryCodes::generate();
+            let codes = RecoveryCodes::generate(recovery_groups);
             let encrypted_key = codes.encrypt_key(&key)?;
             fs::write(self.recovery_path(), encrypted_key)?;
             Ok(Some(codes))
@@ -172,65 +378,138 @@ impl Vault {
         }
     }
 
-    pub fn add(&self, path: &Path, password: &str) -> VaultResult<VaultEntry> {
+    /// Store a file or folder under its own name, or `name_override` when given.
+    /// The original is deleted (shredded with `shred`) unless `keep_source` is set.
+    pub fn add(
+        &self,
+        path: &Path,
+        password: &str,
+        shred: bool,
+        keep_source: bool,
+        name_override: Option<String>,
+    ) -> VaultResult<VaultEntry> {
         if !path.exists() {
             return Err(VaultError::FileNotFound(path.to_path_buf()));
         }
 
-        let key = self.derive_master_key(password)?;
-        let mut index = self.read_index(&key)?;
+        let name = match name_override {
+            Some(name) => Self::validate_name(name)?,
+            None => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unnamed".to_string()),
+        };
 
-        let name = path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "unnamed".to_string());
+        let _lock = self.lock_for_writing()?;
+        let (key, mut index) = self.open_index(password)?;
 
         if index.entries.values().any(|e| e.name == name) {
             return Err(VaultError::AlreadyExists(name));
         }
 
-        let is_directory = path.is_dir();
-        let data = if is_directory {
-            self.compress_directory(path)?
-        } else {
-            fs::read(path)?
-        };
-
-        let size = data.len() as u64;
-        let id = Self::generate_id();
-
-        let encrypted = encrypt_with_key(&data, &key)?;
-        fs::write(self.entry_path(&id), encrypted)?;
-
-        let entry = VaultEntry {
-            id: id.clone(),
-            name,
-            size,
-            added_at: Utc::now(),
-            is_directory,
-        };
+        self.store_entry(path, name, shred, keep_source, &mut index, &key)
+    }
 
-        index.entries.insert(id, entry.clone());
-        self.write_index(&index, &key)?;
+    /// Add every file of `dir` as its own entry (descending into subfolders
+    /// with `recursive`). The key is derived once for the whole batch; files
+    /// whose name is already taken are skipped and folders are left in place.
+    pub fn add_files_in(
+        &self,
+        dir: &Path,
+        password: &str,
+        shred: bool,
+        keep_source: bool,
+        recursive: bool,
+    ) -> VaultResult<BatchAdd> {
+        if !dir.is_dir() {
+            return Err(VaultError::FileNotFound(dir.to_path_buf()));
+        }
 
-        if is_directory {
-            fs::remove_dir_all(path)?;
-        } else {
-            fs::remove_file(path)?;
+        let _lock = self.lock_for_writing()?;
+        let (key, mut index) = self.open_index(password)?;
+        let mut batch = BatchAdd::default();
+
+        for path in collect_files(dir, recursive)? {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unnamed".to_string());
+
+            if index.entries.values().any(|e| e.name == name) {
+                batch.skipped.push(path);
+                continue;
+            }
+
+            match self.store_entry(&path, name, shred, keep_source, &mut index, &key) {
+                Ok(entry) => batch.added.push(entry),
+                Err(e) => batch.failed.push((path, e)),
+            }
         }
 
-        Ok(entry)
+        Ok(batch)
+    }
+
+    /// Check `(source, name)` pairs against the index without storing anything
+    pub fn plan_add(
+        &self,
+        password: &str,
+        items: Vec<(PathBuf, String)>,
+    ) -> VaultResult<Vec<PlannedEntry>> {
+        let (_, index) = self.open_index_for_reading(password)?;
+        let mut names: HashSet<String> = index.entries.into_values().map(|e| e.name).collect();
+
+        items
+            .into_iter()
+            .map(|(source, name)| {
+                if !source.exists() {
+                    return Err(VaultError::FileNotFound(source));
+                }
+                let name = Self::validate_name(name)?;
+                Ok(PlannedEntry {
+                    size: organizer::path_size(&source),
+                    taken: !names.insert(name.clone()),
+                    source,
+                    name,
+                })
+            })
+            .collect()
     }
 
     pub fn list(&self, password: &str) -> VaultResult<Vec<VaultEntry>> {
-        let key = self.derive_master_key(password)?;
-        let index = self.read_index(&key)?;
+        let (_, index) = self.open_index_for_reading(password)?;
         Ok(index.entries.values().cloned().collect())
     }
 
+    /// Entries whose name contains `query` (case-insensitive), by name
+    pub fn find(&self, query: &str, password: &str) -> VaultResult<Vec<VaultEntry>> {
+        let mut entries = filter_entries(self.list(password)?, query);
+        sort_entries(&mut entries, EntrySort::Name);
+        Ok(entries)
+    }
+
+    pub fn stats(&self, password: &str) -> VaultResult<VaultStats> {
+        let (_, index) = self.open_index_for_reading(password)?;
+
+        let directories = index.entries.values().filter(|e| e.is_directory).count();
+        let mut data_bytes = 0;
+        for entry in fs::read_dir(self.data_path())? {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                data_bytes += metadata.len();
+            }
+        }
+
+        Ok(VaultStats {
+            entries: index.entries.len(),
+            files: index.entries.len() - directories,
+            directories,
+            original_bytes: index.entries.values().map(|e| e.size).sum(),
+            data_bytes,
+        })
+    }
+
     pub fn extract(&self, name: &str, password: &str, dest: &Path) -> VaultResult<PathBuf> {
-        let key = self.derive_master_key(password)?;
-        let index = self.read_index(&key)?;
+        let (key, index) = self.open_index_for_reading(password)?;
 
         let entry = index
             .entries
@@ -241,7 +520,8 @@ impl Vault {
         let encrypted = fs::read(self.entry_path(&entry.id))?;
         let data = decrypt_with_key(&encrypted, &key)?;
 
-        let output_path = dest.join(&entry.name);
+        // An entry renamed before names were validated could point outside `dest`
+        let output_path = dest.join(Self::validate_name(entry.name.clone())?);
 
         if entry.is_directory {
             self.extract_directory(&data, &output_path)?;
@@ -252,9 +532,36 @@ impl Vault {
         Ok(output_path)
     }
 
+    pub fn rename(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        password: &str,
+    ) -> VaultResult<VaultEntry> {
+        let new_name = Self::validate_name(new_name.to_string())?;
+        let _lock = self.lock_for_writing()?;
+        let (key, mut index) = self.open_index(password)?;
+
+        if index.entries.values().any(|e| e.name == new_name) {
+            return Err(VaultError::AlreadyExists(new_name));
+        }
+
+        let entry = index
+            .entries
+            .values_mut()
+            .find(|e| e.name == old_name)
+            .ok_or_else(|| VaultError::FileNotFound(PathBuf::from(old_name)))?;
+
+        entry.name = new_name;
+        let renamed = entry.clone();
+        self.write_index(&index, &key)?;
+
+        Ok(renamed)
+    }
+
     pub fn destroy(&self, name: &str, password: &str) -> VaultResult<()> {
-        let key = self.derive_master_key(password)?;
-        let mut index = self.read_index(&key)?;
+        let _lock = self.lock_for_writing()?;
+        let (key, mut index) = self.open_index(password)?;
 
         let id = index
             .entries
@@ -274,12 +581,20 @@ impl Vault {
         Ok(())
     }
 
+    /// Groups per recovery code, to show the expected format when asking for them
+    pub fn recovery_groups(&self) -> VaultResult<usize> {
+        Ok(self.read_meta()?.recovery_groups)
+    }
+
+    /// Replace the password with the recovery codes; new codes of the same
+    /// length are returned and the old ones stop working
     pub fn recover(
         &self,
         code1: &str,
         code2: &str,
         new_password: &str,
     ) -> VaultResult<RecoveryCodes> {
+        let _lock = self.lock_for_writing()?;
         let meta = self.read_meta()?;
 
         if meta.security_level == SecurityLevel::Maximum {
@@ -297,11 +612,15 @@ impl Vault {
         let index = self.read_index(&old_key)?;
 
         let new_salt = generate_salt();
-        let new_key = derive_key(new_password, &new_salt)?;
+        let new_key = derive_key(new_password, &new_salt, &meta.kdf)?;
 
         let new_meta = VaultMeta {
             salt: new_salt.to_vec(),
             security_level: meta.security_level,
+            kdf: meta.kdf,
+            failed_attempts: 0,
+            last_failure: None,
+            recovery_groups: meta.recovery_groups,
         };
         self.write_meta(&new_meta)?;
         self.write_index(&index, &new_key)?;
@@ -314,13 +633,72 @@ impl Vault {
             fs::write(&entry_path, re_encrypted)?;
         }
 
-        let new_codes = RecoveryCodes::generate();
+        let new_codes = RecoveryCodes::generate(meta.recovery_groups);
         let encrypted_new_key = new_codes.encrypt_key(&new_key)?;
         fs::write(&recovery_path, encrypted_new_key)?;
 
         Ok(new_codes)
     }
 
+    /// Encrypt `path` into a new entry, save the index, then remove the original
+    fn store_entry(
+        &self,
+        path: &Path,
+        name: String,
+        shred: bool,
+        keep_source: bool,
+        index: &mut VaultIndex,
+        key: &[u8; KEY_SIZE],
+    ) -> VaultResult<VaultEntry> {
+        let is_directory = path.is_dir();
+        let data = if is_directory {
+            self.compress_directory(path)?
+        } else {
+            fs::read(path)?
+        };
+
+        let size = data.len() as u64;
+        let id = Self::generate_id();
+
+        let encrypted = encrypt_with_key(&data, key)?;
+        fs::write(self.entry_path(&id), encrypted)?;
+
+        let entry = VaultEntry {
+            id: id.clone(),
+            name,
+            size,
+            added_at: Utc::now(),
+            is_directory,
+        };
+
+        index.entries.insert(id, entry.clone());
+        self.write_index(index, key)?;
+
+        if keep_source {
+            return Ok(entry);
+        }
+        match (is_directory, shred) {
+            (true, true) => shred_dir(path)?,
+            (true, false) => fs::remove_dir_all(path)?,
+            (false, true) => shred_file(path)?,
+            (false, false) => fs::remove_file(path)?,
+        }
+
+        Ok(entry)
+    }
+
+    /// Entry names become file names on extract, so they must stay a single component
+    fn validate_name(name: String) -> VaultResult<String> {
+        let is_plain =
+            !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\']);
+
+        if is_plain {
+            Ok(name)
+        } else {
+            Err(VaultError::InvalidName(name))
+        }
+    }
+
     fn compress_directory(&self, path: &Path) -> VaultResult<Vec<u8>> {
         use tar::Builder;
         let mut archive = Builder::new(Vec::new());
@@ -3,28 +3,125 @@
 //
 // Monitors a folder for new files and automatically organizes them.
 // Uses the notify crate for cross-platform filesystem events.
+// An optional sweep organizes the files already there (--sweep).
+// With dry_run, each new file's destination is printed and nothing moves.
+// Downloads in progress (.part, .crdownload...) are ignored, and a new file is
+// only moved once its size is stable and no other process holds a lock on it.
+// Waiting files are checked between events, so one slow download never holds
+// up the others (or Ctrl+C); a new event for a file restarts its wait.
+// Files matching the folder's .stellarignore are left where they are.
 // Gracefully handles Ctrl+C interruption.
 
+use fs2::FileExt;
+use notify::event::ModifyKind;
 use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
-use std::path::Path;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::config;
-use crate::organizer;
-use crate::renamer::RenameMode;
+use crate::config::{self, WatchConfig};
+use crate::organizer::{self, OrganizeOptions};
+use crate::scanner::IgnoreList;
 use crate::ui;
 
-/// Watch a folder and auto-organize new files
+/// Time between the two size checks of a new file
+const SETTLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// New files waiting to look complete, with the size seen at their last check
+pub struct PendingFiles {
+    files: HashMap<PathBuf, Sample>,
+    interval: Duration,
+}
+
+struct Sample {
+    size: Option<u64>,
+    seen: Instant,
+    /// Already found still being written once (reported to the user)
+    busy: bool,
+}
+
+/// What a check of the waiting files found
+#[derive(Default)]
+pub struct PendingCheck {
+    /// Stable and unlocked: ready to be organized
+    pub ready: Vec<PathBuf>,
+    /// Found still being written for the first time
+    pub busy: Vec<PathBuf>,
+}
+
+impl PendingFiles {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            files: HashMap::new(),
+            interval,
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Wait for `path` to settle, starting over if it was already waiting
+    pub fn add(&mut self, path: PathBuf) {
+        let busy = self.files.get(&path).is_some_and(|s| s.busy);
+        let sample = Sample {
+            size: file_size(&path),
+            seen: Instant::now(),
+            busy,
+        };
+        self.files.insert(path, sample);
+    }
+
+    /// Check the files that have waited a full interval. Ready ones leave the
+    /// list, as do files that are gone; the others wait another interval.
+    pub fn check(&mut self) -> PendingCheck {
+        let mut result = PendingCheck::default();
+        let interval = self.interval;
+
+        self.files.retain(|path, sample| {
+            if sample.seen.elapsed() < interval {
+                return true;
+            }
+            let size = file_size(path);
+            if size.is_none() {
+                return false;
+            }
+            if size == sample.size && !is_locked(path) {
+                result.ready.push(path.clone());
+                return false;
+            }
+            if !sample.busy {
+                sample.busy = true;
+                result.busy.push(path.clone());
+            }
+            sample.size = size;
+            sample.seen = Instant::now();
+            true
+        });
+
+        result.ready.sort();
+        result.busy.sort();
+        result
+    }
+}
+
+/// Watch a folder and auto-organize new files.
+/// `sweep` runs once the watcher is listening, so files arriving during it are not missed.
 pub fn watch_folder(
     folder_path: &str,
-    categories: &HashMap<String, Vec<String>>,
-    rename_mode: Option<RenameMode>,
+    options: &OrganizeOptions,
+    settings: &WatchConfig,
+    dry_run: bool,
+    sweep: Option<&dyn Fn()>,
 ) {
     ui::print_info(&format!("Watching folder: {}", folder_path));
+    if dry_run {
+        ui::print_info("Dry-run: new files are only reported, nothing is moved.");
+    }
     ui::print_info("Press Ctrl+C to stop watching...\n");
 
     let running = Arc::new(AtomicBool::new(true));
@@ -51,43 +148,124 @@ pub fn watch_folder(
         return;
     }
 
+    if let Some(sweep) = sweep {
+        ui::print_info("Organizing the files already in the folder...");
+        sweep();
+    }
+
+    let mut pending = PendingFiles::new(SETTLE_INTERVAL);
+
     while running.load(Ordering::SeqCst) {
         match rx.recv_timeout(Duration::from_millis(500)) {
             Ok(Ok(event)) => {
-                if matches!(event.kind, EventKind::Create(_)) {
-                    for path in event.paths {
-                        if path.is_file() {
-                            process_new_file(&path, folder_path, categories, rename_mode.as_ref());
-                        }
+                // A finished download usually arrives as a rename to its final name
+                let is_new = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                );
+                for path in event.paths {
+                    if !(is_new || pending.contains(&path)) || !path.is_file() {
+                        continue;
+                    }
+                    if is_download_in_progress(&path, &settings.ignore_extensions) {
+                        continue;
+                    }
+                    // Re-read on each file so edits apply without restarting the watch
+                    if IgnoreList::load(Path::new(folder_path)).is_ignored(&path) {
+                        continue;
                     }
+                    pending.add(path);
                 }
             }
             Ok(Err(e)) => ui::print_error(&format!("Watch error: {}", e)),
             Err(_) => {} // Timeout, check if still running
         }
+
+        let checked = pending.check();
+        for path in checked.busy {
+            ui::print_info(&format!("Waiting for {} to be complete", path.display()));
+        }
+        for path in checked.ready {
+            process_new_file(&path, folder_path, options, dry_run);
+        }
     }
 
     ui::print_info("\nWatch mode stopped.");
 }
 
-fn process_new_file(
-    file_path: &Path,
-    folder_path: &str,
-    categories: &HashMap<String, Vec<String>>,
-    rename_mode: Option<&RenameMode>,
-) {
-    let ext = match file_path.extension() {
-        Some(e) => e.to_string_lossy().to_lowercase(),
-        None => return,
+/// Whether the file carries one of the in-progress download extensions
+pub fn is_download_in_progress(path: &Path, ignore_extensions: &[String]) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy())
+        .is_some_and(|ext| {
+            ignore_extensions
+                .iter()
+                .any(|i| i.eq_ignore_ascii_case(&ext))
+        })
+}
+
+fn file_size(path: &Path) -> Option<u64> {
+    path.metadata().map(|m| m.len()).ok()
+}
+
+/// Whether another process holds an exclusive lock on the file (or it cannot be opened)
+fn is_locked(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return true;
     };
+    match FileExt::try_lock_shared(&file) {
+        Ok(()) => {
+            let _ = FileExt::unlock(&file);
+            false
+        }
+        Err(_) => true,
+    }
+}
 
-    let category = config::find_category(categories, &ext).unwrap_or_else(|| "Others".into());
-    let file_name = file_path.file_name().unwrap().to_string_lossy();
+/// Category folder for a new file, as a scan would pick it;
+/// None when the unmatched policy leaves it in place
+pub fn new_file_folder(file_path: &Path, options: &OrganizeOptions) -> Option<String> {
+    let ext = file_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if ext.is_empty() {
+        return Some(options.no_extension_folder.to_string());
+    }
 
-    ui::print_info(&format!("New file: {} -> {}", file_name, category));
+    let resolved = config::resolve_folder(options.categories, options.overrides, &ext);
+    if resolved == "Others" {
+        options.unmatched.folder(&ext)
+    } else {
+        Some(resolved)
+    }
+}
+
+fn process_new_file(file_path: &Path, folder_path: &str, options: &OrganizeOptions, dry_run: bool) {
+    let file_name = file_path.file_name().unwrap().to_string_lossy();
+    let Some(category) = new_file_folder(file_path, options) else {
+        ui::print_info(&format!("Left in place (no category): {}", file_name));
+        return;
+    };
 
     let mut files_map = HashMap::new();
-    files_map.insert(category, vec![file_path.to_path_buf()]);
+    files_map.insert(category.clone(), vec![file_path.to_path_buf()]);
 
-    organizer::move_files(folder_path, &files_map, rename_mode);
+    if dry_run {
+        let preview = organizer::generate_dry_run_preview(folder_path, &files_map, options);
+        for planned in &preview.moves {
+            ui::print_info(&format!(
+                "Would move {} -> {}",
+                file_name,
+                planned.to.display()
+            ));
+        }
+        return;
+    }
+
+    ui::print_info(&format!("New file: {} -> {}", file_name, category));
+
+    if let Err(e) = ui::move_files_with_progress(folder_path, &files_map, options) {
+        ui::print_error(&e.to_string());
+    }
 }